@@ -4,7 +4,7 @@ use criterion::{
 
 use calyx::{frontend, ir, passes};
 use ir::traversal::Visitor;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 fn resource_sharing_bench(c: &mut Criterion) {
     let mut gemm_group = c.benchmark_group("gemm");
@@ -18,7 +18,7 @@ fn resource_sharing_bench(c: &mut Criterion) {
                         let name =
                             format!("benches/component-sharing/{}.futil", name);
                         let bench = Path::new(&name);
-                        let lib = Path::new(".");
+                        let lib = [PathBuf::from(".")];
 
                         let namespace = frontend::NamespaceDef::new(
                             &Some(bench.into()),
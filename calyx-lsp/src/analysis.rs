@@ -0,0 +1,530 @@
+//! Analysis of a single Calyx document.
+//!
+//! All positions used in this module are byte offsets into the source text.
+//! The conversion to and from LSP positions happens in [crate::document].
+use calyx::errors::{Error, FutilResult, Span, STDIN};
+use calyx::frontend::{ast, parser::FutilParser};
+use calyx::ir::{self, from_ast, LibrarySignatures};
+use calyx::pass_manager::PassManager;
+use std::path::PathBuf;
+
+/// An error found in the document.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// Start of the erroneous region.
+    pub start: usize,
+    /// End of the erroneous region.
+    pub end: usize,
+    pub message: String,
+}
+
+/// The location of a definition. `file` is `None` when the definition is in
+/// the document being analyzed.
+#[derive(Debug)]
+pub struct Definition {
+    pub file: Option<PathBuf>,
+    pub span: Span,
+}
+
+/// Result of analyzing a document.
+pub struct Analysis {
+    /// The parsed document. `None` if the document does not parse.
+    document: Option<ast::NamespaceDef>,
+    /// The document along with all the files it transitively imports.
+    /// `None` if the imports could not be loaded.
+    program: Option<ast::NamespaceDef>,
+    /// Signatures of all primitives visible in the document.
+    lib: LibrarySignatures,
+    /// Errors found in the document.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+    /// Analyze the document `text` stored in `file`. Imports are resolved
+    /// in the same way as the compiler, using `lib_paths` as the library
+    /// paths.
+    pub fn new(
+        text: &str,
+        file: &Option<PathBuf>,
+        lib_paths: &[PathBuf],
+    ) -> Self {
+        let mut analysis = Analysis {
+            document: None,
+            program: None,
+            lib: LibrarySignatures::default(),
+            diagnostics: vec![],
+        };
+
+        let document = match FutilParser::parse(text.as_bytes()) {
+            Ok(ns) => ns,
+            Err(err) => {
+                analysis.report(err, text);
+                return analysis;
+            }
+        };
+        match load(text, file, lib_paths) {
+            Ok(program) => {
+                analysis.lib = program.externs.clone().into();
+                analysis.program = Some(program);
+            }
+            Err(err) => analysis.report(err, text),
+        }
+
+        // Build the program with all the imported definitions and run the
+        // validation passes.
+        if analysis.diagnostics.is_empty() {
+            if let Err(err) = validate(text, file, lib_paths) {
+                analysis.report(err, text);
+            }
+        }
+        analysis.document = Some(document);
+        analysis
+    }
+
+    /// If the document did not parse, use the definitions from `previous`
+    /// so that completions keep working while the document is being edited.
+    pub fn with_fallback(mut self, previous: Option<Analysis>) -> Self {
+        if let (None, Some(prev)) = (&self.document, previous) {
+            self.document = prev.document;
+            self.program = prev.program;
+            self.lib = prev.lib;
+        }
+        self
+    }
+
+    /// Add diagnostics for `err`. Errors in other files are reported at the
+    /// start of the document.
+    fn report(&mut self, err: Error, text: &str) {
+        match err {
+            Error::Multiple(errs) => {
+                errs.into_iter().for_each(|err| self.report(err, text))
+            }
+            err => {
+                let (start, end, message) = match err.location() {
+                    Some((STDIN, start, end)) => (
+                        start.min(text.len()),
+                        end.min(text.len()),
+                        err.message(),
+                    ),
+                    Some((file, _, _)) => {
+                        (0, 0, format!("Error in {}: {}", file, err.message()))
+                    }
+                    None => (0, 0, err.message()),
+                };
+                self.diagnostics.push(Diagnostic {
+                    start,
+                    end,
+                    message,
+                })
+            }
+        }
+    }
+
+    /// The document followed by the program it is part of.
+    fn namespaces(&self) -> impl Iterator<Item = &ast::NamespaceDef> {
+        self.document.iter().chain(self.program.iter())
+    }
+
+    /// The component in the document that contains `offset`.
+    fn component_at(&self, offset: usize) -> Option<&ast::ComponentDef> {
+        self.document.as_ref().and_then(|ns| {
+            ns.components.iter().rev().find(|comp| {
+                matches!(comp.name.span(), Some(span) if span.start() <= offset)
+            })
+        })
+    }
+
+    /// The cell named `name` in the component containing `offset`.
+    fn cell_at(&self, offset: usize, name: &str) -> Option<&ast::Cell> {
+        self.component_at(offset)?
+            .cells
+            .iter()
+            .find(|cell| cell.name == name)
+    }
+
+    /// The component named `name`.
+    fn component(&self, name: &str) -> Option<&ast::ComponentDef> {
+        self.namespaces()
+            .find_map(|ns| ns.components.iter().find(|comp| comp.name == name))
+    }
+
+    /// The primitive named `name`.
+    fn primitive(&self, name: &str) -> Option<&ir::Primitive> {
+        self.namespaces().find_map(|ns| {
+            ns.externs
+                .iter()
+                .flat_map(|(_, prims)| prims)
+                .find(|prim| prim.name == name)
+        })
+    }
+
+    /// The signature of the component or primitive named `name` as written
+    /// in the source program.
+    fn signature(&self, name: &str) -> Option<&[ir::PortDef]> {
+        self.component(name)
+            .map(|comp| comp.signature.as_slice())
+            .or_else(|| {
+                self.primitive(name).map(|prim| prim.signature.as_slice())
+            })
+    }
+
+    /// Find the definition of the cell, group, component, primitive, or port
+    /// referred to at `offset`.
+    pub fn definition(&self, text: &str, offset: usize) -> Option<Definition> {
+        let (start, end) = word_at(text, offset)?;
+        let word = &text[start..end];
+        let def = |id: &ir::Id| {
+            id.span().map(|span| Definition {
+                file: if span.file() == STDIN {
+                    None
+                } else {
+                    Some(PathBuf::from(span.file()))
+                },
+                span: span.clone(),
+            })
+        };
+
+        // Ports on cells: `cell.port`
+        if let Some(cell) = cell_before(text, start) {
+            let cell = self.cell_at(offset, cell)?;
+            let sig = self.signature(cell.prototype.name.as_ref())?;
+            let port = sig.iter().find(|pd| pd.name == word)?;
+            return def(&port.name);
+        }
+
+        if let Some(comp) = self.component_at(offset) {
+            if let Some(cell) = comp.cells.iter().find(|c| c.name == word) {
+                return def(&cell.name);
+            }
+            if let Some(group) = comp.groups.iter().find(|g| g.name == word) {
+                return def(&group.name);
+            }
+        }
+        if let Some(comp) = self.component(word) {
+            return def(&comp.name);
+        }
+        def(&self.primitive(word)?.name)
+    }
+
+    /// Describe the cell, component, or primitive referred to at `offset`.
+    pub fn hover(&self, text: &str, offset: usize) -> Option<String> {
+        let (start, end) = word_at(text, offset)?;
+        let word = &text[start..end];
+
+        if let Some(cell) = cell_before(text, start) {
+            let cell = self.cell_at(offset, cell)?;
+            let sig = self.signature(cell.prototype.name.as_ref())?;
+            let port = sig.iter().find(|pd| pd.name == word)?;
+            return Some(format!(
+                "{} {}: {}",
+                direction(&port.direction),
+                port.name,
+                width(&port.width)
+            ));
+        }
+
+        let mut desc = String::new();
+        let proto = match self.cell_at(offset, word) {
+            Some(cell) => {
+                desc += &format!(
+                    "{} = {}({})\n",
+                    cell.name,
+                    cell.prototype.name,
+                    cell.prototype
+                        .params
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                cell.prototype.name.as_ref()
+            }
+            None => word,
+        };
+        if let Some(prim) = self.lib.find_primitive(proto) {
+            let params = if prim.params.is_empty() {
+                "".to_string()
+            } else {
+                format!(
+                    "[{}]",
+                    prim.params
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            desc += &format!(
+                "primitive {}{}{}",
+                prim.name,
+                params,
+                signature(&prim.signature)
+            );
+        } else if let Some(comp) = self.component(proto.as_ref()) {
+            desc += &format!(
+                "component {}{}",
+                comp.name,
+                signature(&comp.signature)
+            );
+        } else if desc.is_empty() {
+            return None;
+        }
+        Some(desc)
+    }
+
+    /// Completions at `offset`. Returns the names and widths of the ports
+    /// of the cell if `offset` follows `cell.`.
+    pub fn completion(
+        &self,
+        text: &str,
+        offset: usize,
+    ) -> Vec<(String, String)> {
+        let start = word_start(text, offset);
+        let ports = cell_before(text, start)
+            .and_then(|cell| self.cell_at(offset, cell))
+            .and_then(|cell| {
+                let proto = &cell.prototype.name;
+                if let Some(comp) = self.component(proto.as_ref()) {
+                    let mut sig = comp.signature.clone();
+                    from_ast::extend_signature(&mut sig);
+                    Some(sig)
+                } else {
+                    self.lib
+                        .find_primitive(proto)
+                        .map(|prim| prim.signature.clone())
+                }
+            })
+            .unwrap_or_default();
+        ports
+            .into_iter()
+            .map(|pd| {
+                let detail = format!(
+                    "{} {}",
+                    direction(&pd.direction),
+                    width(&pd.width)
+                );
+                (pd.name.to_string(), detail)
+            })
+            .collect()
+    }
+}
+
+/// Parse the document `text` stored in `file` and load its imports.
+fn load(
+    text: &str,
+    file: &Option<PathBuf>,
+    lib_paths: &[PathBuf],
+) -> FutilResult<ast::NamespaceDef> {
+    FutilParser::parse(text.as_bytes())?.with_imports(file, lib_paths)
+}
+
+/// Build the IR for the document and run the validation passes on it.
+fn validate(
+    text: &str,
+    file: &Option<PathBuf>,
+    lib_paths: &[PathBuf],
+) -> FutilResult<()> {
+    let mut ctx =
+        from_ast::ast_to_ir(load(text, file, lib_paths)?, false, false)?;
+    PassManager::default_passes()?.execute_plan(
+        &mut ctx,
+        &["validate".to_string()],
+        &[],
+    )
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// The start of the identifier that ends at `end`.
+fn word_start(text: &str, end: usize) -> usize {
+    text[..end]
+        .rfind(|c| !is_ident_char(c))
+        .map_or(0, |pos| pos + 1)
+}
+
+/// The bounds of the identifier at `offset`.
+fn word_at(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let offset = offset.min(text.len());
+    let start = word_start(text, offset);
+    let end = text[offset..]
+        .find(|c| !is_ident_char(c))
+        .map_or(text.len(), |pos| offset + pos);
+    if start == end {
+        None
+    } else {
+        Some((start, end))
+    }
+}
+
+/// If the identifier starting at `start` is preceded by `cell.`, returns
+/// the name of the cell.
+fn cell_before(text: &str, start: usize) -> Option<&str> {
+    let dot = start.checked_sub(1)?;
+    if &text[dot..start] != "." {
+        return None;
+    }
+    let cell_start = word_start(text, dot);
+    if cell_start == dot {
+        None
+    } else {
+        Some(&text[cell_start..dot])
+    }
+}
+
+fn direction(dir: &ir::Direction) -> &'static str {
+    match dir {
+        ir::Direction::Input => "input",
+        ir::Direction::Output => "output",
+        ir::Direction::Inout => "inout",
+    }
+}
+
+fn width(width: &ir::Width) -> String {
+    match width {
+        ir::Width::Const { value } => value.to_string(),
+        ir::Width::Param { value } => value.to_string(),
+    }
+}
+
+/// Format a signature as `(in: 32, ...) -> (out: 32, ...)`.
+fn signature(sig: &[ir::PortDef]) -> String {
+    let ports = |dir: ir::Direction| {
+        sig.iter()
+            .filter(|pd| pd.direction == dir)
+            .map(|pd| format!("{}: {}", pd.name, width(&pd.width)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!(
+        "({}) -> ({})",
+        ports(ir::Direction::Input),
+        ports(ir::Direction::Output)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = r#"import "primitives/std.lib";
+component main() -> () {
+  cells {
+    r = std_reg(32);
+  }
+  wires {
+    group incr {
+      r.in = 32'd1;
+      r.write_en = 1'd1;
+      incr[done] = r.done;
+    }
+  }
+  control {
+    incr;
+  }
+}
+"#;
+
+    fn root() -> PathBuf {
+        PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+    }
+
+    fn analyze(text: &str) -> Analysis {
+        Analysis::new(text, &None, &[root()])
+    }
+
+    #[test]
+    fn no_diagnostics() {
+        let analysis = analyze(PROGRAM);
+        assert!(
+            analysis.diagnostics.is_empty(),
+            "{:?}",
+            analysis.diagnostics
+        );
+    }
+
+    #[test]
+    fn unused_group() {
+        let text = PROGRAM.replace("    incr;\n", "");
+        let analysis = analyze(&text);
+        assert_eq!(analysis.diagnostics.len(), 1);
+        let diag = &analysis.diagnostics[0];
+        assert_eq!(&text[diag.start..diag.end], "incr");
+        assert_eq!(diag.message, "Group not used in control");
+    }
+
+    #[test]
+    fn later_lib_path() {
+        let lib_paths = [root().join("tests"), root()];
+        let analysis = Analysis::new(PROGRAM, &None, &lib_paths);
+        assert!(
+            analysis.diagnostics.is_empty(),
+            "{:?}",
+            analysis.diagnostics
+        );
+    }
+
+    #[test]
+    fn redefined_primitive() {
+        let text = PROGRAM.replace(
+            "component main",
+            "component std_reg() -> () {\n  cells {}\n  wires {}\n  control {}\n}\ncomponent main",
+        );
+        let analysis = analyze(&text);
+        assert_eq!(analysis.diagnostics.len(), 1);
+        // The primitive is reported because it is defined after the
+        // component in the document.
+        let diag = &analysis.diagnostics[0];
+        assert_eq!((diag.start, diag.end), (0, 0));
+        assert!(diag.message.contains("primitives/core.futil"));
+        assert!(diag.message.ends_with(
+            "Name already bound by component or primitive in <stdin>"
+        ));
+    }
+
+    #[test]
+    fn missing_import() {
+        let text = PROGRAM.replace("primitives/std.lib", "primitives/none.lib");
+        let analysis = analyze(&text);
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert!(analysis.diagnostics[0]
+            .message
+            .starts_with("Could not find import `primitives/none.lib`"));
+    }
+
+    #[test]
+    fn definitions() {
+        let analysis = analyze(PROGRAM);
+        let use_site = PROGRAM.rfind("incr;").unwrap();
+        let def = analysis.definition(PROGRAM, use_site).unwrap();
+        assert_eq!(def.file, None);
+        assert_eq!(def.span.start(), PROGRAM.find("incr {").unwrap());
+
+        let prim = PROGRAM.find("std_reg").unwrap();
+        let def = analysis.definition(PROGRAM, prim).unwrap();
+        assert!(def.file.unwrap().ends_with("primitives/core.futil"));
+    }
+
+    #[test]
+    fn hover_primitive() {
+        let analysis = analyze(PROGRAM);
+        let cell = PROGRAM.find("r.in").unwrap();
+        let desc = analysis.hover(PROGRAM, cell).unwrap();
+        assert!(desc.starts_with("r = std_reg(32)\nprimitive std_reg[WIDTH]("));
+    }
+
+    #[test]
+    fn complete_ports() {
+        let text = PROGRAM.replace("r.in = 32'd1;", "r.");
+        let analysis = analyze(PROGRAM);
+        let offset = text.find("r.").unwrap() + 2;
+        let ports: Vec<_> = analysis
+            .completion(&text, offset)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert!(ports.contains(&"write_en".to_string()));
+        assert!(ports.contains(&"out".to_string()));
+    }
+}
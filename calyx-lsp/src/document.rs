@@ -0,0 +1,39 @@
+//! Conversion between byte offsets and LSP positions.
+//!
+//! LSP positions are zero-based lines and columns counted in UTF-16 code
+//! units.
+use lsp_types::{Position, Range};
+
+/// The LSP position of the byte `offset` in `text`.
+pub fn position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let prefix = &text[..offset];
+    let line = prefix.matches('\n').count();
+    let line_start = prefix.rfind('\n').map_or(0, |pos| pos + 1);
+    let character = prefix[line_start..].encode_utf16().count();
+    Position::new(line as u32, character as u32)
+}
+
+/// The LSP range between the byte offsets `start` and `end` in `text`.
+pub fn range(text: &str, start: usize, end: usize) -> Range {
+    Range::new(position(text, start), position(text, end))
+}
+
+/// The byte offset of the LSP position `pos` in `text`. Positions past the
+/// end of a line refer to the end of the line.
+pub fn offset(text: &str, pos: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(pos.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = text[line_start..].split('\n').next().unwrap_or("");
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= pos.character as usize {
+            return line_start + idx;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
@@ -0,0 +1,260 @@
+//! Language server for Calyx programs.
+//!
+//! Provides diagnostics from the parser and the `validate` passes,
+//! go-to-definition for cells, groups, components and primitives, hover
+//! information with the signatures of primitives, and completion of port
+//! names after `cell.`.
+//!
+//! The primitive libraries are resolved relative to the `libPath`
+//! initialization option, falling back to the root of the workspace.
+mod analysis;
+mod document;
+
+use analysis::Analysis;
+use lsp_server::{
+    Connection, ErrorCode, Message, Notification, Request, Response,
+};
+use lsp_types::{
+    notification::{self, Notification as _},
+    request::{self, Request as _},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams,
+    CompletionResponse, Diagnostic, DiagnosticSeverity, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, InitializeParams, Location, MarkupContent,
+    MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+type ServerResult<T> = Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+/// State of the language server.
+struct Server {
+    /// Library paths used to resolve imports.
+    lib_paths: Vec<PathBuf>,
+    /// The text and analysis of each open document.
+    documents: HashMap<Url, (String, Analysis)>,
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::FULL,
+        )),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![".".to_string()]),
+            ..CompletionOptions::default()
+        }),
+        ..ServerCapabilities::default()
+    }
+}
+
+impl Server {
+    fn new(params: &InitializeParams) -> Self {
+        // `libPath` is either a single path or a list of paths.
+        let lib_paths = match params
+            .initialization_options
+            .as_ref()
+            .and_then(|opts| opts.get("libPath"))
+        {
+            Some(serde_json::Value::String(path)) => vec![PathBuf::from(path)],
+            Some(serde_json::Value::Array(paths)) => paths
+                .iter()
+                .filter_map(|path| path.as_str())
+                .map(PathBuf::from)
+                .collect(),
+            _ => vec![params
+                .root_uri
+                .as_ref()
+                .and_then(|uri| uri.to_file_path().ok())
+                .unwrap_or_else(|| PathBuf::from("."))],
+        };
+        Server {
+            lib_paths,
+            documents: HashMap::new(),
+        }
+    }
+
+    fn run(&mut self, connection: &Connection) -> ServerResult<()> {
+        for msg in &connection.receiver {
+            match msg {
+                Message::Request(req) => {
+                    if connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let resp = self.request(req)?;
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                Message::Notification(not) => {
+                    if let Some(diags) = self.notification(not)? {
+                        connection.sender.send(Message::Notification(
+                            Notification::new(
+                                notification::PublishDiagnostics::METHOD
+                                    .to_string(),
+                                diags,
+                            ),
+                        ))?;
+                    }
+                }
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a request from the client.
+    fn request(&self, req: Request) -> ServerResult<Response> {
+        match req.method.as_str() {
+            request::GotoDefinition::METHOD => {
+                let (id, params) = req.extract::<GotoDefinitionParams>(
+                    request::GotoDefinition::METHOD,
+                )?;
+                Ok(Response::new_ok(id, self.definition(params)))
+            }
+            request::HoverRequest::METHOD => {
+                let (id, params) =
+                    req.extract::<HoverParams>(request::HoverRequest::METHOD)?;
+                Ok(Response::new_ok(id, self.hover(params)))
+            }
+            request::Completion::METHOD => {
+                let (id, params) = req
+                    .extract::<CompletionParams>(request::Completion::METHOD)?;
+                Ok(Response::new_ok(id, self.completion(params)))
+            }
+            _ => Ok(Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request: {}", req.method),
+            )),
+        }
+    }
+
+    /// Handle a notification from the client. Returns the diagnostics to
+    /// publish if a document changed.
+    fn notification(
+        &mut self,
+        not: Notification,
+    ) -> ServerResult<Option<PublishDiagnosticsParams>> {
+        let (uri, text) = match not.method.as_str() {
+            notification::DidOpenTextDocument::METHOD => {
+                let params = not
+                    .extract::<lsp_types::DidOpenTextDocumentParams>(
+                        notification::DidOpenTextDocument::METHOD,
+                    )?;
+                (params.text_document.uri, params.text_document.text)
+            }
+            notification::DidChangeTextDocument::METHOD => {
+                let mut params = not
+                    .extract::<lsp_types::DidChangeTextDocumentParams>(
+                        notification::DidChangeTextDocument::METHOD,
+                    )?;
+                // Documents are synchronized by sending their full text.
+                match params.content_changes.pop() {
+                    Some(change) => (params.text_document.uri, change.text),
+                    None => return Ok(None),
+                }
+            }
+            notification::DidCloseTextDocument::METHOD => {
+                let params = not
+                    .extract::<lsp_types::DidCloseTextDocumentParams>(
+                        notification::DidCloseTextDocument::METHOD,
+                    )?;
+                self.documents.remove(&params.text_document.uri);
+                return Ok(Some(PublishDiagnosticsParams::new(
+                    params.text_document.uri,
+                    vec![],
+                    None,
+                )));
+            }
+            _ => return Ok(None),
+        };
+
+        let previous = self.documents.remove(&uri).map(|(_, an)| an);
+        let file = uri.to_file_path().ok();
+        let analysis = Analysis::new(&text, &file, &self.lib_paths)
+            .with_fallback(previous);
+        let diags = analysis
+            .diagnostics
+            .iter()
+            .map(|diag| Diagnostic {
+                range: document::range(&text, diag.start, diag.end),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("calyx".to_string()),
+                message: diag.message.clone(),
+                ..Diagnostic::default()
+            })
+            .collect();
+        self.documents.insert(uri.clone(), (text, analysis));
+        Ok(Some(PublishDiagnosticsParams::new(uri, diags, None)))
+    }
+
+    fn definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Option<GotoDefinitionResponse> {
+        let pos = params.text_document_position_params;
+        let (text, analysis) = self.documents.get(&pos.text_document.uri)?;
+        let offset = document::offset(text, pos.position);
+        let def = analysis.definition(text, offset)?;
+        let uri = match def.file {
+            Some(path) => {
+                Url::from_file_path(path.canonicalize().ok()?).ok()?
+            }
+            None => pos.text_document.uri,
+        };
+        let span = def.span;
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            uri,
+            document::range(span.input(), span.start(), span.end()),
+        )))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let pos = params.text_document_position_params;
+        let (text, analysis) = self.documents.get(&pos.text_document.uri)?;
+        let offset = document::offset(text, pos.position);
+        let desc = analysis.hover(text, offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```calyx\n{}\n```", desc),
+            }),
+            range: None,
+        })
+    }
+
+    fn completion(
+        &self,
+        params: CompletionParams,
+    ) -> Option<CompletionResponse> {
+        let pos = params.text_document_position;
+        let (text, analysis) = self.documents.get(&pos.text_document.uri)?;
+        let offset = document::offset(text, pos.position);
+        let items = analysis
+            .completion(text, offset)
+            .into_iter()
+            .map(|(label, detail)| CompletionItem {
+                label,
+                kind: Some(CompletionItemKind::FIELD),
+                detail: Some(detail),
+                ..CompletionItem::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+}
+
+fn main() -> ServerResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let caps = serde_json::to_value(capabilities())?;
+    let params: InitializeParams =
+        serde_json::from_value(connection.initialize(caps)?)?;
+    Server::new(&params).run(&connection)?;
+    // The I/O threads exit once the connection is closed.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
@@ -6,7 +6,10 @@ use petgraph::{
     Direction::{Incoming, Outgoing},
 };
 use std::fmt::Write;
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    rc::Rc,
+};
 
 type Node = RRC<ir::Port>;
 type Edge = ();
@@ -148,6 +151,17 @@ impl GraphAnalysis {
         }
     }
 
+    /// Add the nodes and edges for each assignment in `assigns` to the graph.
+    pub fn add_assignments<'a, I>(mut self, assigns: I) -> Self
+    where
+        I: IntoIterator<Item = &'a ir::Assignment>,
+    {
+        for asgn in assigns {
+            self.insert_assignment(asgn);
+        }
+        self
+    }
+
     /// Add each edge in `edges` to the graph.
     pub fn add_edges(self, edges: &[(RRC<ir::Port>, RRC<ir::Port>)]) -> Self {
         let Self { graph, nodes } = self;
@@ -289,6 +303,136 @@ impl GraphAnalysis {
     pub fn has_cycles(&self) -> bool {
         algo::is_cyclic_directed(&self.graph)
     }
+
+    /// Enumerates the elementary cycles of the graph using Johnson's
+    /// algorithm. Returns at most `limit` cycles along with the total number
+    /// of cycles in the graph. Each cycle starts at its smallest port and the
+    /// first port is not repeated at the end.
+    pub fn cycles(&self, limit: usize) -> (Vec<Vec<RRC<ir::Port>>>, usize) {
+        let mut cycles = vec![];
+        let mut count = 0;
+        let mut found = |stack: &[NodeIndex]| {
+            count += 1;
+            if cycles.len() < limit {
+                let mut cycle: Vec<_> = stack
+                    .iter()
+                    .map(|idx| Rc::clone(&self.graph[*idx]))
+                    .collect();
+                let first = (0..cycle.len())
+                    .min_by_key(|i| cycle[*i].borrow().canonical())
+                    .unwrap();
+                cycle.rotate_left(first);
+                cycles.push(cycle);
+            }
+        };
+        for start in self.graph.node_indices() {
+            // Cycles through `start` that only use nodes after it. Earlier
+            // nodes were the start of a previous search, so every cycle is
+            // found exactly once.
+            let component = self.component_after(start);
+            let mut search = CycleSearch {
+                graph: &self.graph,
+                start,
+                component: &component,
+                blocked: HashSet::new(),
+                blocked_by: HashMap::new(),
+                stack: vec![],
+            };
+            search.circuit(start, &mut found);
+        }
+        (cycles, count)
+    }
+
+    /// The strongly connected component containing `start` in the subgraph
+    /// of the nodes that are not before `start`.
+    fn component_after(&self, start: NodeIndex) -> HashSet<NodeIndex> {
+        let reachable = |direction| {
+            let mut seen = HashSet::new();
+            let mut queue = VecDeque::from(vec![start]);
+            while let Some(idx) = queue.pop_front() {
+                for next in self.graph.neighbors_directed(idx, direction) {
+                    if next >= start && seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+            seen
+        };
+        let forward = reachable(Outgoing);
+        reachable(Incoming)
+            .into_iter()
+            .filter(|idx| forward.contains(idx))
+            .collect()
+    }
+}
+
+/// The state of the search for the cycles through `start` in Johnson's
+/// algorithm.
+struct CycleSearch<'a> {
+    graph: &'a CellGraph,
+    start: NodeIndex,
+    /// Nodes that may be part of a cycle through `start`.
+    component: &'a HashSet<NodeIndex>,
+    /// Nodes that cannot currently reach `start` without revisiting a node on
+    /// the stack.
+    blocked: HashSet<NodeIndex>,
+    /// The nodes to unblock when a node is unblocked.
+    blocked_by: HashMap<NodeIndex, HashSet<NodeIndex>>,
+    /// The current path from `start`.
+    stack: Vec<NodeIndex>,
+}
+
+impl CycleSearch<'_> {
+    /// The successors of `idx` in the component, without duplicates.
+    fn successors(&self, idx: NodeIndex) -> Vec<NodeIndex> {
+        let mut successors: Vec<_> = self
+            .graph
+            .neighbors_directed(idx, Outgoing)
+            .filter(|next| self.component.contains(next))
+            .collect();
+        successors.sort();
+        successors.dedup();
+        successors
+    }
+
+    /// Extend the path with `idx` and report every cycle that closes at
+    /// `start`. Returns true if a cycle was found.
+    fn circuit<F>(&mut self, idx: NodeIndex, found: &mut F) -> bool
+    where
+        F: FnMut(&[NodeIndex]),
+    {
+        let mut closed = false;
+        self.stack.push(idx);
+        self.blocked.insert(idx);
+        let successors = self.successors(idx);
+        for next in &successors {
+            if *next == self.start {
+                found(&self.stack);
+                closed = true;
+            } else if !self.blocked.contains(next) && self.circuit(*next, found)
+            {
+                closed = true;
+            }
+        }
+        if closed {
+            self.unblock(idx);
+        } else {
+            for next in successors {
+                self.blocked_by.entry(next).or_default().insert(idx);
+            }
+        }
+        self.stack.pop();
+        closed
+    }
+
+    fn unblock(&mut self, idx: NodeIndex) {
+        self.blocked.remove(&idx);
+        for next in self.blocked_by.remove(&idx).unwrap_or_default() {
+            if self.blocked.contains(&next) {
+                self.unblock(next);
+            }
+        }
+    }
 }
 
 impl ToString for GraphAnalysis {
@@ -2,11 +2,32 @@ use crate::utils::{Idx, WeightGraph};
 use itertools::Itertools;
 use petgraph::algo;
 use std::{
+    cmp::Reverse,
     collections::{BTreeSet, HashMap},
     hash::Hash,
+    time::{Duration, Instant},
 };
 
-/// Defines a greedy graph coloring algorithm over a generic conflict graph.
+/// The algorithm used to color a [GraphColoring].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColoringStrategy {
+    /// Use [GraphColoring::color_greedy].
+    Greedy,
+    /// Use [GraphColoring::color_dsatur].
+    DSatur,
+    /// Use [GraphColoring::color_exact] with the given time budget.
+    Exact(Duration),
+}
+
+// `#[default]` on enum variants is not supported by the toolchain used by CI.
+#[allow(clippy::derivable_impls)]
+impl Default for ColoringStrategy {
+    fn default() -> Self {
+        ColoringStrategy::Greedy
+    }
+}
+
+/// Defines graph coloring algorithms over a generic conflict graph.
 pub struct GraphColoring<T> {
     graph: WeightGraph<T>,
 }
@@ -44,6 +65,11 @@ where
         self.graph.graph.node_count() > 0
     }
 
+    /// The number of nodes in the graph.
+    pub fn node_count(&self) -> usize {
+        self.graph.graph.node_count()
+    }
+
     /// Given an `ordering` of `T`s, find a mapping from nodes to `T`s such
     /// that no node has a neighbor with the same `T`.
     pub fn color_greedy(&self) -> HashMap<T, T> {
@@ -106,6 +132,76 @@ where
             .collect()
     }
 
+    /// Color the graph using `strategy`.
+    pub fn color(&self, strategy: ColoringStrategy) -> HashMap<T, T> {
+        match strategy {
+            ColoringStrategy::Greedy => self.color_greedy(),
+            ColoringStrategy::DSatur => self.color_dsatur(),
+            ColoringStrategy::Exact(budget) => self.color_exact(budget),
+        }
+    }
+
+    /// Color the graph using the DSatur heuristic: repeatedly color the node
+    /// whose neighbors already use the most distinct colors, breaking ties
+    /// with the degree of the node.
+    pub fn color_dsatur(&self) -> HashMap<T, T> {
+        self.rewrites(dsatur(&self.adjacency()))
+    }
+
+    /// Find a coloring that uses the fewest colors with a branch and bound
+    /// search ordered by the DSatur heuristic.
+    /// The search starts from the DSatur coloring and returns the best
+    /// coloring found if it does not finish within `budget`.
+    pub fn color_exact(&self, budget: Duration) -> HashMap<T, T> {
+        let adj = self.adjacency();
+        // Start the search with the DSatur coloring as the upper bound.
+        let best = dsatur(&adj);
+        let mut search = ExactSearch {
+            deadline: Instant::now() + budget,
+            colors: vec![None; adj.len()],
+            best_count: best.iter().max().map(|max| max + 1).unwrap_or(0),
+            best,
+            adj,
+        };
+        search.search(0);
+        self.rewrites(search.best)
+    }
+
+    /// The neighbors of each node in the graph.
+    fn adjacency(&self) -> Vec<Vec<usize>> {
+        (0..self.graph.graph.node_count())
+            .map(|idx| {
+                self.graph
+                    .graph
+                    .neighbors(Idx::new(idx))
+                    .map(|n| n.index())
+                    .sorted()
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Convert a color for each node into a mapping from each node to the
+    /// first node with the same color.
+    fn rewrites(&self, colors: Vec<usize>) -> HashMap<T, T> {
+        let mut first: HashMap<usize, usize> = HashMap::new();
+        for (node, color) in colors.iter().enumerate() {
+            first.entry(*color).or_insert(node);
+        }
+        let rev_map = self.graph.reverse_index();
+        colors
+            .iter()
+            .enumerate()
+            .filter(|(node, color)| first[color] != *node)
+            .map(|(node, color)| {
+                (
+                    rev_map[&Idx::new(node)].clone(),
+                    rev_map[&Idx::new(first[color])].clone(),
+                )
+            })
+            .collect()
+    }
+
     pub fn welsh_powell_coloring(&self) -> HashMap<T, T> {
         let mut coloring: HashMap<T, T> = HashMap::new();
 
@@ -142,6 +238,88 @@ where
     }
 }
 
+/// The colors used by `neighbors`.
+fn neighbor_colors(
+    neighbors: &[usize],
+    colors: &[Option<usize>],
+) -> BTreeSet<usize> {
+    neighbors.iter().filter_map(|&n| colors[n]).collect()
+}
+
+/// The uncolored node whose neighbors use the most distinct colors.
+/// Ties are broken by the degree and then the index of the node.
+fn most_saturated(
+    adj: &[Vec<usize>],
+    colors: &[Option<usize>],
+) -> Option<usize> {
+    (0..adj.len())
+        .filter(|&node| colors[node].is_none())
+        .max_by_key(|&node| {
+            (
+                neighbor_colors(&adj[node], colors).len(),
+                adj[node].len(),
+                Reverse(node),
+            )
+        })
+}
+
+/// Color the graph with adjacency lists `adj` using the DSatur heuristic.
+fn dsatur(adj: &[Vec<usize>]) -> Vec<usize> {
+    let mut colors = vec![None; adj.len()];
+    while let Some(node) = most_saturated(adj, &colors) {
+        let used = neighbor_colors(&adj[node], &colors);
+        colors[node] = (0..).find(|c| !used.contains(c));
+    }
+    colors.into_iter().map(Option::unwrap).collect()
+}
+
+/// State of the branch and bound search used by [GraphColoring::color_exact].
+struct ExactSearch {
+    adj: Vec<Vec<usize>>,
+    deadline: Instant,
+    /// The partial coloring being extended.
+    colors: Vec<Option<usize>>,
+    /// The best complete coloring found so far.
+    best: Vec<usize>,
+    /// Number of colors used by `best`.
+    best_count: usize,
+}
+
+impl ExactSearch {
+    /// Extend the partial coloring which uses `used` colors. Returns false
+    /// once the deadline has passed.
+    fn search(&mut self, used: usize) -> bool {
+        if Instant::now() > self.deadline {
+            return false;
+        }
+        let node = match most_saturated(&self.adj, &self.colors) {
+            Some(node) => node,
+            None => {
+                self.best = self.colors.iter().map(|c| c.unwrap()).collect();
+                self.best_count = used;
+                return true;
+            }
+        };
+        let taken = neighbor_colors(&self.adj[node], &self.colors);
+        // Only colorings that use fewer colors than the best one are explored.
+        for color in 0..=used {
+            if color + 1 >= self.best_count {
+                break;
+            }
+            if taken.contains(&color) {
+                continue;
+            }
+            self.colors[node] = Some(color);
+            let finished = self.search(used.max(color + 1));
+            self.colors[node] = None;
+            if !finished {
+                return false;
+            }
+        }
+        true
+    }
+}
+
 impl<T: Eq + Hash + ToString + Clone + Ord> ToString for GraphColoring<T> {
     fn to_string(&self) -> String {
         self.graph.to_string()
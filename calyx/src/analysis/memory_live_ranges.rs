@@ -0,0 +1,218 @@
+use crate::{
+    analysis::ReadWriteSet,
+    ir::{self, CloneName, RRC},
+};
+use std::collections::{HashMap, HashSet};
+
+/// Computes the memories that are alive during the execution of each group.
+///
+/// Unlike registers, writes to a memory only update one element so they
+/// never kill the contents of the memory. Instead, a memory is alive at a
+/// group if it is used (read or written) by the group or if it is used both
+/// by something that may run before the group and by something that may run
+/// after it.
+///
+/// The analysis is conservative for loops: every memory used in the body or
+/// the condition of a `while` is considered to be used both before and after
+/// every group in the loop.
+/// Memories used by continuous assignments are alive at every group.
+#[derive(Default)]
+pub struct MemoryLiveRanges {
+    /// Memories that may be used before or by each group.
+    before: HashMap<ir::Id, HashSet<ir::Id>>,
+    /// Memories that may be used by or after each group.
+    after: HashMap<ir::Id, HashSet<ir::Id>>,
+    /// Memories used by continuous assignments.
+    global: HashSet<ir::Id>,
+}
+
+impl MemoryLiveRanges {
+    /// Construct the live ranges of the cells in `comp` for which `filter`
+    /// returns true.
+    pub fn new<F>(comp: &ir::Component, filter: F) -> Self
+    where
+        F: Fn(&ir::Cell) -> bool,
+    {
+        let uses = |assigns: &[ir::Assignment]| -> HashSet<ir::Id> {
+            ReadWriteSet::uses(assigns)
+                .into_iter()
+                .filter(|cell| filter(&cell.borrow()))
+                .map(|cell| cell.clone_name())
+                .collect()
+        };
+        let group_uses: HashMap<ir::Id, HashSet<ir::Id>> = comp
+            .groups
+            .iter()
+            .map(|group| {
+                (group.clone_name(), uses(&group.borrow().assignments))
+            })
+            .collect();
+
+        let mut ranges = MemoryLiveRanges {
+            global: uses(&comp.continuous_assignments),
+            ..Default::default()
+        };
+        let control = comp.control.borrow();
+        ranges.forward(&control, HashSet::new(), &group_uses);
+        ranges.backward(&control, HashSet::new(), &group_uses);
+        ranges
+    }
+
+    /// The memories alive while `group` executes.
+    pub fn get(&self, group: &ir::Id) -> HashSet<ir::Id> {
+        match (self.before.get(group), self.after.get(group)) {
+            (Some(before), Some(after)) => before & after,
+            _ => HashSet::new(),
+        }
+        .union(&self.global)
+        .cloned()
+        .collect()
+    }
+
+    /// The memories used by continuous assignments.
+    pub fn global(&self) -> &HashSet<ir::Id> {
+        &self.global
+    }
+
+    /// Record the memories used before each group in `c` given that the
+    /// memories in `used` are used before `c`. Returns the memories used
+    /// before or by `c`.
+    fn forward(
+        &mut self,
+        c: &ir::Control,
+        used: HashSet<ir::Id>,
+        uses: &HashMap<ir::Id, HashSet<ir::Id>>,
+    ) -> HashSet<ir::Id> {
+        match c {
+            ir::Control::Empty(_) | ir::Control::Invoke(_) => used,
+            ir::Control::Enable(ir::Enable { group, .. }) => {
+                self.enable(group.borrow().name(), used, uses, true)
+            }
+            ir::Control::Seq(ir::Seq { stmts, .. }) => stmts
+                .iter()
+                .fold(used, |used, stmt| self.forward(stmt, used, uses)),
+            ir::Control::If(ir::If {
+                cond,
+                tbranch,
+                fbranch,
+                ..
+            }) => {
+                let used = self.enable(cond.borrow().name(), used, uses, true);
+                let t_used = self.forward(tbranch, used.clone(), uses);
+                let f_used = self.forward(fbranch, used, uses);
+                &t_used | &f_used
+            }
+            ir::Control::While(ir::While { cond, body, .. }) => {
+                let mut used = used;
+                used.extend(control_uses(c, uses));
+                let used = self.enable(cond.borrow().name(), used, uses, true);
+                self.forward(body, used, uses)
+            }
+            ir::Control::Par(ir::Par { stmts, .. }) => stmts
+                .iter()
+                .map(|stmt| self.forward(stmt, used.clone(), uses))
+                .fold(HashSet::new(), |acc, used| &acc | &used),
+        }
+    }
+
+    /// Record the memories used after each group in `c` given that the
+    /// memories in `used` are used after `c`. Returns the memories used by or
+    /// after `c`.
+    fn backward(
+        &mut self,
+        c: &ir::Control,
+        used: HashSet<ir::Id>,
+        uses: &HashMap<ir::Id, HashSet<ir::Id>>,
+    ) -> HashSet<ir::Id> {
+        match c {
+            ir::Control::Empty(_) | ir::Control::Invoke(_) => used,
+            ir::Control::Enable(ir::Enable { group, .. }) => {
+                self.enable(group.borrow().name(), used, uses, false)
+            }
+            ir::Control::Seq(ir::Seq { stmts, .. }) => stmts
+                .iter()
+                .rev()
+                .fold(used, |used, stmt| self.backward(stmt, used, uses)),
+            ir::Control::If(ir::If {
+                cond,
+                tbranch,
+                fbranch,
+                ..
+            }) => {
+                let t_used = self.backward(tbranch, used.clone(), uses);
+                let f_used = self.backward(fbranch, used, uses);
+                self.enable(
+                    cond.borrow().name(),
+                    &t_used | &f_used,
+                    uses,
+                    false,
+                )
+            }
+            ir::Control::While(ir::While { cond, body, .. }) => {
+                let mut used = used;
+                used.extend(control_uses(c, uses));
+                let used = self.backward(body, used, uses);
+                self.enable(cond.borrow().name(), used, uses, false)
+            }
+            ir::Control::Par(ir::Par { stmts, .. }) => stmts
+                .iter()
+                .map(|stmt| self.backward(stmt, used.clone(), uses))
+                .fold(HashSet::new(), |acc, used| &acc | &used),
+        }
+    }
+
+    /// Add the memories used by `group` to `used` and record the result for
+    /// the group.
+    fn enable(
+        &mut self,
+        group: &ir::Id,
+        mut used: HashSet<ir::Id>,
+        uses: &HashMap<ir::Id, HashSet<ir::Id>>,
+        forward: bool,
+    ) -> HashSet<ir::Id> {
+        used.extend(uses[group].iter().cloned());
+        let map = if forward {
+            &mut self.before
+        } else {
+            &mut self.after
+        };
+        map.entry(group.clone())
+            .or_default()
+            .extend(used.iter().cloned());
+        used
+    }
+}
+
+/// The memories used by any group in `c`.
+fn control_uses(
+    c: &ir::Control,
+    uses: &HashMap<ir::Id, HashSet<ir::Id>>,
+) -> HashSet<ir::Id> {
+    let group_uses =
+        |group: &RRC<ir::Group>| uses[group.borrow().name()].clone();
+    match c {
+        ir::Control::Empty(_) | ir::Control::Invoke(_) => HashSet::new(),
+        ir::Control::Enable(ir::Enable { group, .. }) => group_uses(group),
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => stmts
+            .iter()
+            .flat_map(|stmt| control_uses(stmt, uses))
+            .collect(),
+        ir::Control::If(ir::If {
+            cond,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            let mut used = group_uses(cond);
+            used.extend(control_uses(tbranch, uses));
+            used.extend(control_uses(fbranch, uses));
+            used
+        }
+        ir::Control::While(ir::While { cond, body, .. }) => {
+            let mut used = group_uses(cond);
+            used.extend(control_uses(body, uses));
+            used
+        }
+    }
+}
@@ -6,14 +6,20 @@
 mod graph;
 mod graph_coloring;
 mod live_range_analysis;
+mod memory_live_ranges;
 pub mod reaching_defns;
 mod read_write_set;
+mod resource_estimate;
 mod schedule_conflicts;
 mod variable_detection;
 
 pub use graph::GraphAnalysis;
-pub use graph_coloring::GraphColoring;
+pub use graph_coloring::{ColoringStrategy, GraphColoring};
 pub use live_range_analysis::LiveRangeAnalysis;
+pub use memory_live_ranges::MemoryLiveRanges;
 pub use read_write_set::ReadWriteSet;
+pub use resource_estimate::{
+    ComponentEstimate, Latency, ResourceEstimate, Resources,
+};
 pub use schedule_conflicts::ScheduleConflicts;
 pub use variable_detection::VariableDetection;
@@ -0,0 +1,326 @@
+use crate::ir::{self, GetAttributes};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// The best- and worst-case latency of a component in cycles. `None` if the
+/// latency cannot be derived from the `static` attributes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Latency {
+    pub best: Option<u64>,
+    pub worst: Option<u64>,
+}
+
+impl Latency {
+    fn exact(time: u64) -> Self {
+        Latency {
+            best: Some(time),
+            worst: Some(time),
+        }
+    }
+
+    fn unknown() -> Self {
+        Latency::default()
+    }
+
+    /// Combine the best and worst cases of `self` and `other` with `f`.
+    fn zip<F>(self, other: Self, f: F) -> Self
+    where
+        F: Fn(u64, u64) -> u64,
+    {
+        Latency {
+            best: self.best.zip(other.best).map(|(a, b)| f(a, b)),
+            worst: self.worst.zip(other.worst).map(|(a, b)| f(a, b)),
+        }
+    }
+}
+
+/// The hardware used by a component.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Resources {
+    /// The number of instances of each primitive, by width.
+    pub primitives: BTreeMap<String, BTreeMap<u64, u64>>,
+    /// Bits stored in `std_reg` cells.
+    pub register_bits: u64,
+    /// Bits stored in memories.
+    pub memory_bits: u64,
+    /// The number of states of each FSM register generated by
+    /// `top-down-compile-control`.
+    pub fsm_states: BTreeMap<String, u64>,
+}
+
+impl Resources {
+    /// Add the resources in `other` to `self`. The names of the FSMs of
+    /// `other` are prefixed with `prefix` if it is given.
+    fn add(&mut self, other: &Resources, prefix: Option<&str>) {
+        for (prim, widths) in &other.primitives {
+            let entry = self.primitives.entry(prim.clone()).or_default();
+            for (width, count) in widths {
+                *entry.entry(*width).or_default() += count;
+            }
+        }
+        self.register_bits += other.register_bits;
+        self.memory_bits += other.memory_bits;
+        for (fsm, states) in &other.fsm_states {
+            let name = match prefix {
+                Some(prefix) => format!("{}.{}", prefix, fsm),
+                None => fsm.clone(),
+            };
+            self.fsm_states.insert(name, *states);
+        }
+    }
+}
+
+/// The resources used by a component and its latency.
+#[derive(Clone, Debug, Serialize)]
+pub struct ComponentEstimate {
+    pub name: String,
+    /// Resources used by the cells defined in the component. Does not
+    /// include the resources of the components it instantiates.
+    #[serde(flatten)]
+    pub resources: Resources,
+    /// The number of instances of each component.
+    pub instances: BTreeMap<String, u64>,
+    pub latency: Latency,
+}
+
+/// Estimates the area and latency of a program without synthesizing it.
+///
+/// For each component, counts the primitives it instantiates by width, the
+/// bits stored in registers and memories, and the states of the FSMs
+/// generated by `top-down-compile-control`. The total for the toplevel
+/// component includes the resources of every component it instantiates.
+///
+/// The latency is derived from the `static` attributes of the component,
+/// its control statements, and its groups:
+/// - `if` takes one cycle more than its condition group and a branch,
+/// - a `while` with the `bound(n)` attribute runs the body `n` times and the
+///   condition group `n + 1` times, and
+/// - the best case of a `while` without a bound runs the condition group
+///   once while its worst case is unknown.
+///
+/// Once the control program of a component has been compiled, its latency is
+/// only known if the component has the `static` attribute.
+#[derive(Clone, Debug, Serialize)]
+pub struct ResourceEstimate {
+    pub components: Vec<ComponentEstimate>,
+    /// Name of the toplevel component.
+    pub toplevel: String,
+    /// Resources used by the toplevel component and every component it
+    /// instantiates.
+    pub total: Resources,
+    /// Latency of the toplevel component.
+    pub latency: Latency,
+}
+
+/// The width of the primitive `cell`: its `WIDTH` parameter if it has one
+/// and the width of its widest port otherwise.
+fn width(cell: &ir::Cell) -> u64 {
+    cell.get_parameter("WIDTH").unwrap_or_else(|| {
+        cell.ports
+            .iter()
+            .map(|port| port.borrow().width)
+            .max()
+            .unwrap_or(0)
+    })
+}
+
+/// The number of elements in the memory `cell` or `None` if it is not a
+/// memory.
+fn memory_size(cell: &ir::Cell) -> Option<u64> {
+    match &cell.prototype {
+        ir::CellType::Primitive { param_binding, .. } => {
+            let sizes: Vec<u64> = param_binding
+                .iter()
+                .filter(|(param, _)| {
+                    param == "SIZE"
+                        || (param.as_ref().ends_with("_SIZE")
+                            && !param.as_ref().ends_with("IDX_SIZE"))
+                })
+                .map(|(_, size)| *size)
+                .collect();
+            if sizes.is_empty() {
+                None
+            } else {
+                Some(sizes.iter().product())
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The latency of the control program `con` given the latencies of the
+/// components defined so far.
+fn control_latency(
+    con: &ir::Control,
+    ctx: &ir::Context,
+    latencies: &HashMap<ir::Id, Latency>,
+) -> Latency {
+    if let Some(&time) =
+        con.get_attributes().and_then(|attrs| attrs.get("static"))
+    {
+        return Latency::exact(time);
+    }
+    let group_latency = |group: &ir::RRC<ir::Group>| match group
+        .borrow()
+        .attributes
+        .get("static")
+    {
+        Some(&time) => Latency::exact(time),
+        None => Latency::unknown(),
+    };
+    match con {
+        ir::Control::Empty(_) => Latency::exact(0),
+        ir::Control::Enable(ir::Enable { group, .. }) => group_latency(group),
+        ir::Control::Invoke(ir::Invoke { comp, .. }) => {
+            match &comp.borrow().prototype {
+                ir::CellType::Component { name } => {
+                    latencies.get(name).copied().unwrap_or_default()
+                }
+                ir::CellType::Primitive { name, .. } => ctx
+                    .lib
+                    .find_primitive(name)
+                    .and_then(|prim| prim.attributes.get("static"))
+                    .map_or_else(Latency::unknown, |&time| {
+                        Latency::exact(time)
+                    }),
+                _ => Latency::unknown(),
+            }
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. }) => {
+            stmts.iter().fold(Latency::exact(0), |acc, stmt| {
+                acc.zip(control_latency(stmt, ctx, latencies), |a, b| a + b)
+            })
+        }
+        ir::Control::Par(ir::Par { stmts, .. }) => {
+            stmts.iter().fold(Latency::exact(0), |acc, stmt| {
+                acc.zip(control_latency(stmt, ctx, latencies), u64::max)
+            })
+        }
+        ir::Control::If(ir::If {
+            cond,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            let t = control_latency(tbranch, ctx, latencies);
+            let f = control_latency(fbranch, ctx, latencies);
+            let branch = Latency {
+                best: t.best.zip(f.best).map(|(t, f)| t.min(f)),
+                worst: t.worst.zip(f.worst).map(|(t, f)| t.max(f)),
+            };
+            group_latency(cond).zip(branch, |c, b| c + 1 + b)
+        }
+        ir::Control::While(ir::While {
+            cond,
+            body,
+            attributes,
+            ..
+        }) => {
+            let cond = group_latency(cond);
+            match attributes.get("bound") {
+                Some(&bound) => cond
+                    .zip(control_latency(body, ctx, latencies), |c, b| {
+                        bound * b + (bound + 1) * c
+                    }),
+                None => Latency {
+                    best: cond.best,
+                    worst: None,
+                },
+            }
+        }
+    }
+}
+
+impl ResourceEstimate {
+    /// Estimate the resources and latency of every component in `ctx`.
+    pub fn new(ctx: &ir::Context) -> Self {
+        let mut latencies: HashMap<ir::Id, Latency> = HashMap::new();
+        let mut totals: HashMap<ir::Id, Resources> = HashMap::new();
+        let mut components = vec![];
+        // Components are defined before they are instantiated.
+        for comp in &ctx.components {
+            let mut resources = Resources::default();
+            let mut total = Resources::default();
+            let mut instances: BTreeMap<String, u64> = BTreeMap::new();
+            for cell_ref in comp.cells.iter() {
+                let cell = cell_ref.borrow();
+                match &cell.prototype {
+                    ir::CellType::Primitive { name, .. } => {
+                        *resources
+                            .primitives
+                            .entry(name.to_string())
+                            .or_default()
+                            .entry(width(&cell))
+                            .or_default() += 1;
+                        if name == "std_reg" {
+                            resources.register_bits += width(&cell);
+                        }
+                        if let Some(size) = memory_size(&cell) {
+                            resources.memory_bits += size * width(&cell);
+                        }
+                    }
+                    ir::CellType::Component { name } => {
+                        *instances.entry(name.to_string()).or_default() += 1;
+                        if let Some(inst) = totals.get(name) {
+                            total.add(inst, Some(cell.name().as_ref()));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            for fsm in &comp.fsm_states {
+                // States are numbered from zero and the last state is always
+                // labeled.
+                let states = fsm
+                    .states
+                    .iter()
+                    .map(|(range, _)| range.end() + 1)
+                    .max()
+                    .unwrap_or(0);
+                resources.fsm_states.insert(fsm.cell.to_string(), states);
+            }
+            let control = comp.control.borrow();
+            let latency = match comp.attributes.get("static") {
+                Some(&time) => Latency::exact(time),
+                // Compiled components have no control program.
+                None if matches!(*control, ir::Control::Empty(_))
+                    && !comp.continuous_assignments.is_empty() =>
+                {
+                    Latency::unknown()
+                }
+                None => control_latency(&control, ctx, &latencies),
+            };
+
+            total.add(&resources, None);
+
+            latencies.insert(comp.name.clone(), latency);
+            totals.insert(comp.name.clone(), total);
+            components.push(ComponentEstimate {
+                name: comp.name.to_string(),
+                resources,
+                instances,
+                latency,
+            });
+        }
+
+        let toplevel = ctx
+            .components
+            .iter()
+            .find(|comp| comp.attributes.has("toplevel") || comp.name == "main")
+            .or_else(|| ctx.components.last());
+        match toplevel {
+            Some(top) => ResourceEstimate {
+                components,
+                toplevel: top.name.to_string(),
+                total: totals.remove(&top.name).unwrap_or_default(),
+                latency: latencies[&top.name],
+            },
+            None => ResourceEstimate {
+                components,
+                toplevel: String::new(),
+                total: Resources::default(),
+                latency: Latency::unknown(),
+            },
+        }
+    }
+}
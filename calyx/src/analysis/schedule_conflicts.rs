@@ -99,11 +99,68 @@ fn all_conflicting(
     }
 }
 
+/// Adds edges between groups in different vectors whose active cycles
+/// overlap. Each vector contains tuples of a group and the interval of cycles
+/// `[start, end)` in which it is active.
+fn overlapping(
+    arms: &[Vec<(ir::Id, u64, u64)>],
+    current_conflicts: &mut ScheduleConflicts,
+) {
+    for arm1 in 0..arms.len() {
+        for arm2 in arm1 + 1..arms.len() {
+            for (node1, start1, end1) in &arms[arm1] {
+                for (node2, start2, end2) in &arms[arm2] {
+                    if start1 < end2 && start2 < end1 {
+                        current_conflicts.add_edge(node1, node2);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Computes the cycles in which the groups enabled by `c` are active if `c`
+/// starts in cycle `start` and returns the latency of `c`.
+/// Returns `None` if `c` is not a `seq` or `par` of groups with the "static"
+/// attribute because such programs are not compiled using the `static-timing`
+/// pass and their groups may not run at fixed offsets.
+fn static_intervals(
+    c: &ir::Control,
+    start: u64,
+    intervals: &mut Vec<(ir::Id, u64, u64)>,
+) -> Option<u64> {
+    match c {
+        ir::Control::Enable(ir::Enable { group, .. }) => {
+            let time = group.borrow().attributes.get("static").copied()?;
+            intervals.push((group.clone_name(), start, start + time));
+            Some(time)
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. }) => {
+            stmts.iter().try_fold(0, |time, stmt| {
+                static_intervals(stmt, start + time, intervals)
+                    .map(|t| time + t)
+            })
+        }
+        ir::Control::Par(ir::Par { stmts, .. }) => {
+            stmts.iter().try_fold(0, |time, stmt| {
+                static_intervals(stmt, start, intervals).map(|t| time.max(t))
+            })
+        }
+        ir::Control::Empty(_)
+        | ir::Control::Invoke(_)
+        | ir::Control::If(_)
+        | ir::Control::While(_) => None,
+    }
+}
+
 /// Construct a conflict graph by traversing the Control program.
+/// When `static_timing` is true, groups in different arms of a `par` only
+/// conflict if their active cycles overlap.
 fn build_conflict_graph(
     c: &ir::Control,
     confs: &mut ScheduleConflicts,
     all_enables: &mut Vec<ir::Id>,
+    static_timing: bool,
 ) {
     match c {
         ir::Control::Empty(_) => (),
@@ -112,9 +169,9 @@ fn build_conflict_graph(
             confs.add_node(&group.borrow().name());
             all_enables.push(group.clone_name());
         }
-        ir::Control::Seq(ir::Seq { stmts, .. }) => stmts
-            .iter()
-            .for_each(|c| build_conflict_graph(c, confs, all_enables)),
+        ir::Control::Seq(ir::Seq { stmts, .. }) => stmts.iter().for_each(|c| {
+            build_conflict_graph(c, confs, all_enables, static_timing)
+        }),
         ir::Control::If(ir::If {
             cond,
             tbranch,
@@ -123,13 +180,13 @@ fn build_conflict_graph(
         }) => {
             all_enables.push(cond.clone_name());
             confs.add_node(&cond.borrow().name());
-            build_conflict_graph(tbranch, confs, all_enables);
-            build_conflict_graph(fbranch, confs, all_enables);
+            build_conflict_graph(tbranch, confs, all_enables, static_timing);
+            build_conflict_graph(fbranch, confs, all_enables, static_timing);
         }
         ir::Control::While(ir::While { cond, body, .. }) => {
             all_enables.push(cond.clone_name());
             confs.add_node(&cond.borrow().name());
-            build_conflict_graph(body, confs, all_enables);
+            build_conflict_graph(body, confs, all_enables, static_timing);
         }
         ir::Control::Par(ir::Par { stmts, .. }) => {
             let enables = stmts
@@ -138,13 +195,32 @@ fn build_conflict_graph(
                     // Visit this child and add conflict edges.
                     // Collect the enables in this into a new vector.
                     let mut enables = Vec::new();
-                    build_conflict_graph(c, confs, &mut enables);
+                    build_conflict_graph(c, confs, &mut enables, static_timing);
                     enables
                 })
                 .collect::<Vec<_>>();
 
-            // Add conflict edges between all children.
-            all_conflicting(&enables, confs);
+            // Use the active cycles of the groups if every child is
+            // statically timed.
+            let intervals = if static_timing {
+                stmts
+                    .iter()
+                    .map(|c| {
+                        let mut intervals = Vec::new();
+                        static_intervals(c, 0, &mut intervals)
+                            .map(|_| intervals)
+                    })
+                    .collect::<Option<Vec<_>>>()
+            } else {
+                None
+            };
+
+            match intervals {
+                // Add conflict edges between groups active in the same cycle.
+                Some(arms) => overlapping(&arms, confs),
+                // Add conflict edges between all children.
+                None => all_conflicting(&enables, confs),
+            }
 
             // Add the enables from visiting the children to the current
             // set of enables.
@@ -157,9 +233,24 @@ fn build_conflict_graph(
 impl From<&ir::Control> for ScheduleConflicts {
     fn from(control: &ir::Control) -> Self {
         let mut confs = ScheduleConflicts::default();
-        build_conflict_graph(control, &mut confs, &mut vec![]);
+        build_conflict_graph(control, &mut confs, &mut vec![], false);
         // Build the reverse index
         confs.rev_map = confs.graph.reverse_index();
         confs
     }
 }
+
+impl ScheduleConflicts {
+    /// Construct ScheduleConflicts from a ir::Control using the "static"
+    /// attribute of groups. Groups in different arms of a `par` whose arms are
+    /// all statically timed only conflict if they are active in the same
+    /// cycle.
+    /// This is only correct if every such `par` is compiled by the
+    /// `static-timing` pass.
+    pub fn from_static(control: &ir::Control) -> Self {
+        let mut confs = ScheduleConflicts::default();
+        build_conflict_graph(control, &mut confs, &mut vec![], true);
+        confs.rev_map = confs.graph.reverse_index();
+        confs
+    }
+}
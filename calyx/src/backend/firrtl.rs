@@ -0,0 +1,347 @@
+//! FIRRTL backend for the Calyx compiler.
+//!
+//! Transforms an [`ir::Context`](crate::ir::Context) into a low FIRRTL circuit
+//! that can be instantiated from Chisel.
+use crate::{
+    backend::{
+        traits::Backend,
+        verilog::{signal_name, VerilogBackend},
+    },
+    errors::{Error, FutilResult},
+    ir,
+    utils::OutputFile,
+};
+use ir::{Guard, RRC};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// Emits a low FIRRTL circuit for programs that have been lowered to
+/// continuous assignments. Primitives that correspond to FIRRTL operations are
+/// implemented directly and every other primitive becomes an `extmodule`
+/// implemented by the SystemVerilog primitive libraries.
+#[derive(Default)]
+pub struct FirrtlBackend;
+
+impl Backend for FirrtlBackend {
+    fn name(&self) -> &'static str {
+        "firrtl"
+    }
+
+    fn validate(ctx: &ir::Context) -> FutilResult<()> {
+        VerilogBackend::validate(ctx)
+    }
+
+    /// The primitives are declared as `extmodule`s and have to be provided to
+    /// the FIRRTL compiler separately.
+    fn link_externs(
+        _ctx: &ir::Context,
+        _file: &mut OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> FutilResult<()> {
+        let top = ctx
+            .components
+            .iter()
+            .find(|comp| comp.attributes.has("toplevel") || comp.name == "main")
+            .or_else(|| ctx.components.last())
+            .ok_or_else(|| Error::Misc("No components to emit".to_string()))?;
+
+        let mut out = String::new();
+        writeln!(out, "circuit {} :", top.name).unwrap();
+
+        // extmodules for every instantiated primitive and parameter binding.
+        let mut extmodules = HashSet::new();
+        for comp in &ctx.components {
+            for cell_ref in comp.cells.iter() {
+                let cell = cell_ref.borrow();
+                if builtin(&cell).is_none() {
+                    if let Some(name) = extmodule_name(&cell) {
+                        if extmodules.insert(name.clone()) {
+                            emit_extmodule(&cell, &name, &mut out);
+                        }
+                    }
+                }
+            }
+        }
+
+        for comp in &ctx.components {
+            emit_component(comp, &mut out);
+        }
+
+        write!(file.get_write(), "{}", out).map_err(|_| {
+            Error::WriteError(format!(
+                "File not found: {}",
+                file.as_path_string()
+            ))
+        })?;
+        Ok(())
+    }
+}
+
+/// The FIRRTL type of `port`.
+fn port_type(port: &ir::Port) -> String {
+    if port.attributes.has("clk") {
+        "Clock".to_string()
+    } else {
+        format!("UInt<{}>", port.width)
+    }
+}
+
+/// Name of the `extmodule` implementing the primitive cell `cell`. Every
+/// binding of the parameters of a primitive gets its own `extmodule`.
+fn extmodule_name(cell: &ir::Cell) -> Option<String> {
+    match &cell.prototype {
+        ir::CellType::Primitive {
+            name,
+            param_binding,
+        } => Some(
+            std::iter::once(name.to_string())
+                .chain(param_binding.iter().map(|(_, v)| v.to_string()))
+                .join("_"),
+        ),
+        _ => None,
+    }
+}
+
+fn emit_extmodule(cell: &ir::Cell, name: &str, out: &mut String) {
+    writeln!(out, "  extmodule {} :", name).unwrap();
+    for port_ref in &cell.ports {
+        let port = port_ref.borrow();
+        let dir = match port.direction {
+            ir::Direction::Input => "input",
+            ir::Direction::Output => "output",
+            ir::Direction::Inout => {
+                panic!("Unexpected Inout port on Cell: {}", port.name)
+            }
+        };
+        writeln!(out, "    {} {} : {}", dir, port.name, port_type(&port))
+            .unwrap();
+    }
+    if let ir::CellType::Primitive {
+        name,
+        param_binding,
+    } = &cell.prototype
+    {
+        writeln!(out, "    defname = {}", name).unwrap();
+        for (param, value) in param_binding {
+            writeln!(out, "    parameter {} = {}", param, value).unwrap();
+        }
+    }
+    writeln!(out).unwrap();
+}
+
+/// The FIRRTL expression computing the `out` port of `cell` if it is a
+/// primitive that maps to a FIRRTL operation.
+fn builtin(cell: &ir::Cell) -> Option<String> {
+    let (name, params) = match &cell.prototype {
+        ir::CellType::Primitive {
+            name,
+            param_binding,
+        } => (name, param_binding),
+        _ => return None,
+    };
+    let param = |p: &str| {
+        params
+            .iter()
+            .find(|(name, _)| name == p)
+            .map(|(_, v)| *v)
+            .unwrap()
+    };
+    let port = |p: &str| signal_name(cell.name(), &ir::Id::from(p));
+    let binop =
+        |op: &str| format!("{}({}, {})", op, port("left"), port("right"));
+    let expr = match name.as_ref() {
+        "std_const" => {
+            format!("UInt<{}>({})", param("WIDTH"), param("VALUE"))
+        }
+        "std_wire" => port("in"),
+        "std_not" => format!("not({})", port("in")),
+        "std_slice" => {
+            format!("bits({}, {}, 0)", port("in"), param("OUT_WIDTH") - 1)
+        }
+        "std_pad" => format!("pad({}, {})", port("in"), param("OUT_WIDTH")),
+        "std_and" => binop("and"),
+        "std_or" => binop("or"),
+        "std_xor" => binop("xor"),
+        // Addition and subtraction widen the result by one bit.
+        "std_add" => format!("tail({}, 1)", binop("add")),
+        "std_sub" => format!("tail({}, 1)", binop("sub")),
+        "std_rsh" => binop("dshr"),
+        "std_eq" => binop("eq"),
+        "std_neq" => binop("neq"),
+        "std_gt" => binop("gt"),
+        "std_lt" => binop("lt"),
+        "std_ge" => binop("geq"),
+        "std_le" => binop("leq"),
+        "std_mux" => {
+            format!("mux({}, {}, {})", port("cond"), port("tru"), port("fal"))
+        }
+        _ => return None,
+    };
+    Some(expr)
+}
+
+fn emit_component(comp: &ir::Component, out: &mut String) {
+    writeln!(out, "  module {} :", comp.name).unwrap();
+    // Ports driven by this module.
+    let mut sinks: Vec<RRC<ir::Port>> = vec![];
+    for port_ref in &comp.signature.borrow().ports {
+        let port = port_ref.borrow();
+        // NOTE: The signature port definitions are reversed inside the component.
+        let dir = match port.direction {
+            ir::Direction::Input => {
+                sinks.push(Rc::clone(port_ref));
+                "output"
+            }
+            ir::Direction::Output => "input",
+            ir::Direction::Inout => {
+                panic!("Unexpected Inout port on Component: {}", port.name)
+            }
+        };
+        writeln!(out, "    {} {} : {}", dir, port.name, port_type(&port))
+            .unwrap();
+    }
+    writeln!(out).unwrap();
+
+    // Cells implemented by FIRRTL operations use a wire for each port while
+    // other cells are instances.
+    let mut ops = vec![];
+    for cell_ref in comp.cells.iter() {
+        let cell = cell_ref.borrow();
+        if let Some(op) = builtin(&cell) {
+            for port_ref in &cell.ports {
+                let port = port_ref.borrow();
+                writeln!(
+                    out,
+                    "    wire {} : {}",
+                    signal_name(cell.name(), &port.name),
+                    port_type(&port)
+                )
+                .unwrap();
+                if port.direction == ir::Direction::Input {
+                    sinks.push(Rc::clone(port_ref));
+                }
+            }
+            ops.push((signal_name(cell.name(), &ir::Id::from("out")), op));
+        } else if let Some(name) = extmodule_name(&cell)
+            .or_else(|| cell.type_name().map(|name| name.to_string()))
+        {
+            writeln!(out, "    inst {} of {}", cell.name(), name).unwrap();
+            sinks.extend(
+                cell.ports
+                    .iter()
+                    .filter(|port| {
+                        port.borrow().direction == ir::Direction::Input
+                    })
+                    .cloned(),
+            );
+        }
+    }
+
+    for (signal, op) in ops {
+        writeln!(out, "    {} <= {}", signal, op).unwrap();
+    }
+
+    // gather assignments keyed by destination
+    let mut map: HashMap<_, (RRC<ir::Port>, Vec<_>)> = HashMap::new();
+    for asgn in &comp.continuous_assignments {
+        map.entry(asgn.dst.borrow().canonical())
+            .and_modify(|(_, v)| v.push(asgn))
+            .or_insert((Rc::clone(&asgn.dst), vec![asgn]));
+    }
+    map.values()
+        .sorted_by_key(|(port, _)| port.borrow().canonical())
+        .for_each(|asgns| {
+            writeln!(out, "    {}", emit_assignment(asgns)).unwrap();
+        });
+
+    // Low FIRRTL requires every sink to be driven.
+    for port in sinks {
+        if !map.contains_key(&port.borrow().canonical()) {
+            writeln!(out, "    {} is invalid", port_to_ref(&port)).unwrap();
+        }
+    }
+    writeln!(out).unwrap();
+}
+
+/// Connects the destination to a `mux` tree selecting the source of the
+/// assignment with an active guard. Unguarded assignments are connected
+/// directly.
+/// Clocks cannot be multiplexed, so clock ports are connected to the source of
+/// the last assignment.
+fn emit_assignment(
+    (dst_ref, assignments): &(RRC<ir::Port>, Vec<&ir::Assignment>),
+) -> String {
+    let dst = dst_ref.borrow();
+    let rhs = if dst.attributes.has("clk") {
+        port_to_ref(&assignments.last().unwrap().src)
+    } else {
+        let init = format!("UInt<{}>(0)", dst.width);
+        assignments.iter().rfold(init, |acc, e| match &*e.guard {
+            Guard::True => port_to_ref(&e.src),
+            guard => format!(
+                "mux({}, {}, {})",
+                guard_to_expr(guard),
+                port_to_ref(&e.src),
+                acc
+            ),
+        })
+    };
+    format!("{} <= {}", port_to_ref(dst_ref), rhs)
+}
+
+fn port_to_ref(port_ref: &RRC<ir::Port>) -> String {
+    let port = port_ref.borrow();
+    match &port.parent {
+        ir::PortParent::Cell(cell) => {
+            let parent_ref = cell.upgrade();
+            let parent = parent_ref.borrow();
+            match parent.prototype {
+                ir::CellType::Constant { val, width } => {
+                    format!("UInt<{}>({})", width, val)
+                }
+                ir::CellType::ThisComponent => port.name.to_string(),
+                _ if builtin(&parent).is_some() => {
+                    signal_name(parent.name(), &port.name)
+                }
+                _ => format!("{}.{}", parent.name(), port.name),
+            }
+        }
+        ir::PortParent::Group(_) => unreachable!(),
+    }
+}
+
+fn guard_to_expr(guard: &ir::Guard) -> String {
+    let op = |g: &ir::Guard| match g {
+        Guard::Or(..) => "or",
+        Guard::And(..) => "and",
+        Guard::Eq(..) => "eq",
+        Guard::Neq(..) => "neq",
+        Guard::Gt(..) => "gt",
+        Guard::Lt(..) => "lt",
+        Guard::Geq(..) => "geq",
+        Guard::Leq(..) => "leq",
+        Guard::Not(..) | Guard::Port(..) | Guard::True => unreachable!(),
+    };
+
+    match guard {
+        Guard::And(l, r) | Guard::Or(l, r) => {
+            format!("{}({}, {})", op(guard), guard_to_expr(l), guard_to_expr(r))
+        }
+        Guard::Neq(l, r)
+        | Guard::Eq(l, r)
+        | Guard::Gt(l, r)
+        | Guard::Lt(l, r)
+        | Guard::Geq(l, r)
+        | Guard::Leq(l, r) => {
+            format!("{}({}, {})", op(guard), port_to_ref(l), port_to_ref(r))
+        }
+        Guard::Not(o) => format!("not({})", guard_to_expr(o)),
+        Guard::Port(p) => port_to_ref(p),
+        Guard::True => "UInt<1>(1)".to_string(),
+    }
+}
@@ -1,4 +1,8 @@
 //! Backends for the Calyx compiler.
+pub mod firrtl;
+pub mod resources;
+pub mod source_map;
+pub mod testbench;
 pub mod traits;
 pub mod verilog;
 pub mod xilinx;
@@ -0,0 +1,150 @@
+//! Reports the estimated area and latency of a program.
+//!
+//! The `resources` backend prints a table for every component and for the
+//! whole program while `resources-json` prints the same
+//! [ResourceEstimate] as JSON.
+use crate::{
+    analysis::{Latency, ResourceEstimate, Resources},
+    backend::traits::Backend,
+    errors::FutilResult,
+    ir,
+    utils::OutputFile,
+};
+use std::io::Write;
+
+/// Backend that prints tables of the resources used by each component.
+#[derive(Default)]
+pub struct ResourcesBackend;
+
+/// Backend that prints the resources used by each component as JSON.
+#[derive(Default)]
+pub struct ResourcesJsonBackend;
+
+impl Backend for ResourcesBackend {
+    fn name(&self) -> &'static str {
+        "resources"
+    }
+
+    fn validate(_ctx: &ir::Context) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _ctx: &ir::Context,
+        _file: &mut OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> FutilResult<()> {
+        let estimate = ResourceEstimate::new(ctx);
+        let out = &mut file.get_write();
+        for comp in &estimate.components {
+            writeln!(out, "component {}", comp.name)?;
+            write_resources(out, &comp.resources, &comp.latency)?;
+            if !comp.instances.is_empty() {
+                let rows = comp
+                    .instances
+                    .iter()
+                    .map(|(name, count)| vec![name.clone(), count.to_string()])
+                    .collect();
+                write_table(out, &["component", "instances"], rows)?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "total ({})", estimate.toplevel)?;
+        write_resources(out, &estimate.total, &estimate.latency)?;
+        Ok(())
+    }
+}
+
+impl Backend for ResourcesJsonBackend {
+    fn name(&self) -> &'static str {
+        "resources-json"
+    }
+
+    fn validate(_ctx: &ir::Context) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _ctx: &ir::Context,
+        _file: &mut OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> FutilResult<()> {
+        let json = serde_json::to_string_pretty(&ResourceEstimate::new(ctx))
+            .expect("Failed to serialize the resource estimate");
+        writeln!(file.get_write(), "{}", json)?;
+        Ok(())
+    }
+}
+
+fn cycles(time: Option<u64>) -> String {
+    time.map_or_else(|| "unknown".to_string(), |t| t.to_string())
+}
+
+fn write_resources<W: Write>(
+    out: &mut W,
+    resources: &Resources,
+    latency: &Latency,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "  latency: best {}, worst {}",
+        cycles(latency.best),
+        cycles(latency.worst)
+    )?;
+    writeln!(out, "  register bits: {}", resources.register_bits)?;
+    writeln!(out, "  memory bits: {}", resources.memory_bits)?;
+    let rows = resources
+        .primitives
+        .iter()
+        .flat_map(|(prim, widths)| {
+            widths.iter().map(move |(width, count)| {
+                vec![prim.clone(), width.to_string(), count.to_string()]
+            })
+        })
+        .collect();
+    write_table(out, &["primitive", "width", "count"], rows)?;
+    if !resources.fsm_states.is_empty() {
+        let rows = resources
+            .fsm_states
+            .iter()
+            .map(|(fsm, states)| vec![fsm.clone(), states.to_string()])
+            .collect();
+        write_table(out, &["fsm", "states"], rows)?;
+    }
+    Ok(())
+}
+
+/// Write a table with the columns `header` and `rows`. The columns are
+/// aligned to the left.
+fn write_table<W: Write>(
+    out: &mut W,
+    header: &[&str],
+    rows: Vec<Vec<String>>,
+) -> std::io::Result<()> {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|col| {
+            rows.iter()
+                .map(|row| row[col].len())
+                .chain(std::iter::once(header[col].len()))
+                .max()
+                .unwrap()
+        })
+        .collect();
+    let header = header.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "  {}", line.trim_end())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+//! Source map for the Verilog generated by the Calyx compiler.
+//!
+//! Maps every signal of the generated Verilog to the Calyx cell and port it
+//! implements, the groups that drive it, and the values of FSM registers to the
+//! control statements they implement.
+use super::verilog::signal_name;
+use crate::{
+    backend::traits::Backend, errors::FutilResult, ir, utils::OutputFile,
+};
+use serde::Serialize;
+
+/// Backend that generates a JSON source map for the Verilog generated by the
+/// `verilog` backend.
+#[derive(Default)]
+pub struct SourceMapBackend;
+
+#[derive(Serialize)]
+struct SourceMap {
+    components: Vec<ComponentMap>,
+}
+
+#[derive(Serialize)]
+struct ComponentMap {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    signals: Vec<Signal>,
+    groups: Vec<Group>,
+    fsms: Vec<Fsm>,
+}
+
+/// A signal in the generated Verilog.
+#[derive(Serialize)]
+struct Signal {
+    /// Name of the Verilog signal.
+    signal: String,
+    /// The cell the signal belongs to. `None` for ports of the component.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell: Option<String>,
+    port: String,
+    /// Line the cell is defined on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    /// Groups with assignments to this signal.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<String>,
+}
+
+/// A group that was removed while compiling the component.
+#[derive(Serialize)]
+struct Group {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+}
+
+/// An FSM register generated while compiling control.
+#[derive(Serialize)]
+struct Fsm {
+    /// The Verilog signal holding the current state.
+    signal: String,
+    cell: String,
+    /// The group that implemented the FSM.
+    group: String,
+    states: Vec<State>,
+}
+
+#[derive(Serialize)]
+struct State {
+    value: u64,
+    /// The last state of the range when the control statements span several
+    /// states.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last: Option<u64>,
+    /// The control statements started in this state.
+    control: Vec<String>,
+}
+
+fn line(id: &ir::Id) -> Option<usize> {
+    id.span().map(|span| span.line())
+}
+
+/// Groups that drive `port` in the continuous assignments of `comp`.
+fn driving_groups(comp: &ir::Component, port: &ir::Port) -> Vec<String> {
+    let mut groups: Vec<String> = vec![];
+    for asgn in &comp.continuous_assignments {
+        if let Some(group) = &asgn.origin {
+            if asgn.dst.borrow().canonical() == port.canonical()
+                && !groups.iter().any(|g| g == group.as_ref())
+            {
+                groups.push(group.to_string());
+            }
+        }
+    }
+    groups
+}
+
+fn component_map(comp: &ir::Component) -> ComponentMap {
+    let mut signals: Vec<Signal> = comp
+        .signature
+        .borrow()
+        .ports
+        .iter()
+        .map(|port| Signal {
+            signal: port.borrow().name.to_string(),
+            cell: None,
+            port: port.borrow().name.to_string(),
+            line: None,
+            groups: driving_groups(comp, &port.borrow()),
+        })
+        .collect();
+    for cell_ref in comp.cells.iter() {
+        let cell = cell_ref.borrow();
+        if !matches!(
+            cell.prototype,
+            ir::CellType::Primitive { .. } | ir::CellType::Component { .. }
+        ) {
+            continue;
+        }
+        signals.extend(cell.ports.iter().map(|port| {
+            let port = port.borrow();
+            Signal {
+                signal: signal_name(cell.name(), &port.name),
+                cell: Some(cell.name().to_string()),
+                port: port.name.to_string(),
+                line: line(cell.name()),
+                groups: driving_groups(comp, &port),
+            }
+        }));
+    }
+
+    let mut groups: Vec<Group> = vec![];
+    for origin in comp
+        .continuous_assignments
+        .iter()
+        .filter_map(|asgn| asgn.origin.as_ref())
+    {
+        if !groups.iter().any(|g| g.name == origin.as_ref()) {
+            groups.push(Group {
+                name: origin.to_string(),
+                line: line(origin),
+            });
+        }
+    }
+
+    let fsms = comp
+        .fsm_states
+        .iter()
+        .map(|fsm| Fsm {
+            signal: signal_name(&fsm.cell, &"out".into()),
+            cell: fsm.cell.to_string(),
+            group: fsm.group.to_string(),
+            states: fsm
+                .states
+                .iter()
+                .map(|(range, control)| State {
+                    value: *range.start(),
+                    last: Some(*range.end())
+                        .filter(|last| last != range.start()),
+                    control: control.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+
+    ComponentMap {
+        name: comp.name.to_string(),
+        line: line(&comp.name),
+        signals,
+        groups,
+        fsms,
+    }
+}
+
+impl Backend for SourceMapBackend {
+    fn name(&self) -> &'static str {
+        "source-map"
+    }
+
+    fn validate(_ctx: &ir::Context) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _ctx: &ir::Context,
+        _file: &mut OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> FutilResult<()> {
+        let map = SourceMap {
+            components: ctx.components.iter().map(component_map).collect(),
+        };
+        let json = serde_json::to_string_pretty(&map)
+            .expect("Failed to serialize the source map");
+        writeln!(file.get_write(), "{}", json)?;
+        Ok(())
+    }
+}
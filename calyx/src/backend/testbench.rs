@@ -0,0 +1,331 @@
+//! Testbench backend for the Calyx compiler.
+//!
+//! Generates a self-contained directory that can be compiled with Verilator
+//! to simulate the toplevel component of a program.
+use crate::{
+    backend::{
+        traits::Backend,
+        verilog::{self, VerilogBackend},
+    },
+    errors::{Error, FutilResult},
+    ir,
+    utils::OutputFile,
+};
+use std::fs;
+use vast::v17::ast as v;
+
+/// Writes a SystemVerilog testbench and a C++ Verilator harness for the
+/// toplevel component to the output directory:
+/// - `main.sv`: the primitive libraries and every component.
+/// - `tb.sv`: the `tb` module that resets and starts the toplevel, waits for
+///   `done`, and loads and dumps every `@external` memory.
+/// - `harness.cpp`: the Verilator harness that drives the clock of `tb`.
+/// - `Makefile`: builds the simulator with Verilator and runs it.
+#[derive(Default)]
+pub struct TestbenchBackend;
+
+/// Name of the module instantiating the toplevel component.
+const TESTBENCH: &str = "tb";
+/// Number of cycles `reset` is held high before the toplevel is started.
+const RESET_CYCLES: u32 = 5;
+/// Default value of the `+CYCLE_LIMIT` plusarg.
+const CYCLE_LIMIT: u32 = 10_000_000;
+
+impl Backend for TestbenchBackend {
+    fn name(&self) -> &'static str {
+        "testbench"
+    }
+
+    fn validate(ctx: &ir::Context) -> FutilResult<()> {
+        VerilogBackend::validate(ctx)?;
+        toplevel(ctx).map(|_| ())
+    }
+
+    /// The libraries are copied into `main.sv` by `emit`.
+    fn link_externs(
+        _ctx: &ir::Context,
+        _file: &mut OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> FutilResult<()> {
+        let dir = match &*file {
+            OutputFile::File(path) => path,
+            OutputFile::Stdout => {
+                return Err(Error::Misc(
+                    "The testbench backend writes a directory. Provide it using `-o <dir>`.".to_string(),
+                ))
+            }
+        };
+        let write = |name: &str, contents: String| {
+            let path = dir.join(name);
+            fs::write(&path, contents).map_err(|_| {
+                Error::WriteError(format!("Failed to write {}", path.display()))
+            })
+        };
+        fs::create_dir_all(dir).map_err(|_| {
+            Error::WriteError(format!("Failed to write {}", dir.display()))
+        })?;
+
+        let top = toplevel(ctx)?;
+        write("main.sv", design(ctx)?)?;
+        write("tb.sv", testbench(top).to_string())?;
+        write("harness.cpp", HARNESS.to_string())?;
+        write("Makefile", MAKEFILE.to_string())?;
+        Ok(())
+    }
+}
+
+/// The toplevel component of the program.
+fn toplevel(ctx: &ir::Context) -> FutilResult<&ir::Component> {
+    ctx.components
+        .iter()
+        .find(|comp| comp.attributes.has("toplevel") || comp.name == "main")
+        .ok_or_else(|| {
+            Error::Misc(
+                "The testbench backend requires a `main` component."
+                    .to_string(),
+            )
+        })
+}
+
+/// The primitive libraries followed by every component. Memories are loaded
+/// and dumped by the testbench instead of the components.
+fn design(ctx: &ir::Context) -> FutilResult<String> {
+    let mut out = String::new();
+    for path in &ctx.lib.paths {
+        let lib = fs::read_to_string(path).map_err(|_| {
+            Error::WriteError(format!("File not found: {}", path))
+        })?;
+        out.push_str(&lib);
+    }
+    for comp in &ctx.components {
+        out.push_str(&verilog::emit_component(comp, ctx, false).to_string());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Path to the file `<mem><ext>` in the data directory.
+fn data_file(mem: &str, ext: &str) -> v::Expr {
+    // NOTE: vast prints concatenations in reverse order.
+    v::Expr::Concat(v::ExprConcat {
+        exprs: vec![
+            v::Expr::new_str(&format!("/{}.{}", mem, ext)),
+            v::Expr::new_ref("DATA"),
+        ],
+    })
+}
+
+/// Set `var` from the plusarg `<var>=<fmt>` or to `default` if it is absent.
+fn plusarg(var: &str, fmt: &str, default: v::Expr) -> v::Sequential {
+    let mut read = v::SequentialIfElse::new(v::Expr::new_eq(
+        v::Expr::new_call(
+            "$value$plusargs",
+            vec![
+                v::Expr::new_str(&format!("{}={}", var, fmt)),
+                v::Expr::new_ref(var),
+            ],
+        ),
+        v::Expr::new_int(0),
+    ));
+    read.add_seq(v::Sequential::new_blk_assign(
+        v::Expr::new_ref(var),
+        default,
+    ));
+    read.into()
+}
+
+/// The `tb` module driving the toplevel component `top`.
+fn testbench(top: &ir::Component) -> v::Module {
+    let mut module = v::Module::new(TESTBENCH);
+    module.add_input("clk", 1);
+    module.add_output("timed_out", 1);
+    module.add_decl(v::Decl::new_param_uint("RESET_CYCLES", RESET_CYCLES));
+    module.add_stmt(v::Stmt::new_rawstr("string DATA;".to_string()));
+    module.add_decl(v::Decl::new_int("CYCLE_LIMIT"));
+    // `cycles` is only written by the `always_ff` block.
+    module.add_stmt(v::Stmt::new_rawstr("int cycles = 0;".to_string()));
+    module.add_decl(v::Decl::new_logic("reset", 1));
+    module.add_decl(v::Decl::new_logic("go", 1));
+    module.add_decl(v::Decl::new_logic("done", 1));
+
+    // Hold the toplevel in reset and then keep `go` high until it is done.
+    module.add_stmt(v::Stmt::new_parallel(v::Parallel::ParAssign(
+        v::Expr::new_ref("reset"),
+        v::Expr::new_lt(
+            v::Expr::new_ref("cycles"),
+            v::Expr::new_ref("RESET_CYCLES"),
+        ),
+    )));
+    module.add_stmt(v::Stmt::new_parallel(v::Parallel::ParAssign(
+        v::Expr::new_ref("go"),
+        v::Expr::new_not(v::Expr::new_ref("reset")),
+    )));
+    module.add_stmt(v::Stmt::new_parallel(v::Parallel::ParAssign(
+        v::Expr::new_ref("timed_out"),
+        v::Expr::new_geq(
+            v::Expr::new_ref("cycles"),
+            v::Expr::new_add(
+                v::Expr::new_ref("RESET_CYCLES"),
+                v::Expr::new_ref("CYCLE_LIMIT"),
+            ),
+        ),
+    )));
+
+    // The signature ports are reversed inside the component, so outputs of
+    // the signature are the inputs of the module.
+    let mut dut = v::Instance::new("dut", top.name.as_ref());
+    for port in &top.signature.borrow().ports {
+        let port = port.borrow();
+        let name = port.name.as_ref();
+        if matches!(name, "clk" | "reset" | "go" | "done") {
+            dut.connect_ref(name, name);
+        } else if port.direction == ir::Direction::Output {
+            dut.connect(name, v::Expr::new_ulit_dec(port.width as u32, "0"));
+        }
+    }
+    module.add_instance(dut);
+
+    let memories = verilog::external_memories(top);
+    let mut initial = v::ParallelProcess::new_initial();
+    initial
+        .add_seq(plusarg("DATA", "%s", v::Expr::new_str(".")))
+        .add_seq(plusarg(
+            "CYCLE_LIMIT",
+            "%d",
+            v::Expr::new_int(CYCLE_LIMIT as i32),
+        ));
+    for mem in &memories {
+        initial.add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
+            "$readmemh",
+            vec![
+                data_file(mem, "dat"),
+                v::Expr::new_ipath(&format!("dut.{}.mem", mem)),
+            ],
+        )));
+    }
+    module.add_process(initial);
+
+    // Once the toplevel is done, report the number of cycles `go` was high
+    // and dump the memories. Stop the simulation after `CYCLE_LIMIT` cycles.
+    let mut finished = v::SequentialIfElse::new(v::Expr::new_ref("done"));
+    finished.add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
+        "$display",
+        vec![
+            v::Expr::new_str("Simulated %0d cycles"),
+            v::Expr::new_add(
+                v::Expr::new_sub(
+                    v::Expr::new_ref("cycles"),
+                    v::Expr::new_ref("RESET_CYCLES"),
+                ),
+                v::Expr::new_int(1),
+            ),
+        ],
+    )));
+    for mem in &memories {
+        finished.add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
+            "$writememh",
+            vec![
+                data_file(mem, "out"),
+                v::Expr::new_ipath(&format!("dut.{}.mem", mem)),
+            ],
+        )));
+    }
+    finished.add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
+        "$finish",
+        vec![],
+    )));
+
+    let mut timeout = v::SequentialIfElse::new(v::Expr::new_ref("timed_out"));
+    timeout
+        .add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
+            "$display",
+            vec![
+                v::Expr::new_str("Timed out after %0d cycles"),
+                v::Expr::new_ref("CYCLE_LIMIT"),
+            ],
+        )))
+        .add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
+            "$finish",
+            vec![],
+        )));
+    finished.set_else(timeout);
+
+    let mut always = v::ParallelProcess::new_always_ff();
+    always.set_event(v::Sequential::Event(
+        v::EventTy::Posedge,
+        v::Expr::new_ref("clk"),
+    ));
+    always
+        .add_seq(v::Sequential::new_nonblk_assign(
+            v::Expr::new_ref("cycles"),
+            v::Expr::new_add(v::Expr::new_ref("cycles"), v::Expr::new_int(1)),
+        ))
+        .add_seq(finished);
+    module.add_process(always);
+
+    module
+}
+
+/// Verilator harness toggling the clock of `tb` until it finishes. Exits with
+/// a non-zero status when the simulation timed out.
+const HARNESS: &str = r#"// Verilator harness generated by the Calyx compiler.
+#include <memory>
+
+#include "Vtb.h"
+#include "verilated.h"
+#include "verilated_vcd_c.h"
+
+int main(int argc, char **argv) {
+  auto ctx = std::make_unique<VerilatedContext>();
+  ctx->commandArgs(argc, argv);
+  auto tb = std::make_unique<Vtb>(ctx.get());
+
+  // Pass +trace=<file> to dump a VCD trace.
+  std::unique_ptr<VerilatedVcdC> trace;
+  const char *trace_arg = ctx->commandArgsPlusMatch("trace=");
+  if (trace_arg[0]) {
+    ctx->traceEverOn(true);
+    trace = std::make_unique<VerilatedVcdC>();
+    tb->trace(trace.get(), 99);
+    trace->open(trace_arg + sizeof("+trace=") - 1);
+  }
+
+  tb->clk = 0;
+  while (!ctx->gotFinish()) {
+    tb->clk = !tb->clk;
+    tb->eval();
+    if (trace) {
+      trace->dump(ctx->time());
+    }
+    ctx->timeInc(1);
+  }
+  tb->final();
+  if (trace) {
+    trace->close();
+  }
+  return tb->timed_out ? 1 : 0;
+}
+"#;
+
+/// Builds the simulator with Verilator. `make run` runs it using the memories
+/// in `DATA`.
+const MAKEFILE: &str = r#"# Makefile generated by the Calyx compiler.
+DATA ?= .
+CYCLE_LIMIT ?= 10000000
+VERILATOR ?= verilator
+
+obj_dir/Vtb: main.sv tb.sv harness.cpp
+	$(VERILATOR) --cc --exe --build --trace -Wno-fatal --top-module tb \
+		main.sv tb.sv harness.cpp
+
+run: obj_dir/Vtb
+	./obj_dir/Vtb +DATA=$(DATA) +CYCLE_LIMIT=$(CYCLE_LIMIT)
+
+clean:
+	rm -rf obj_dir
+
+.PHONY: run clean
+"#;
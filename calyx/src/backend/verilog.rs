@@ -11,9 +11,12 @@ use crate::{
 };
 use ir::{Control, Group, Guard, RRC};
 use itertools::Itertools;
-use std::fs::File;
-use std::io;
-use std::{collections::HashMap, rc::Rc};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 use vast::v17::ast as v;
 
 /// Implements a simple Verilog backend. The backend
@@ -117,7 +120,9 @@ impl Backend for VerilogBackend {
         let modules = &ctx
             .components
             .iter()
-            .map(|comp| emit_component(&comp, !ctx.synthesis_mode).to_string())
+            .map(|comp| {
+                emit_component(&comp, ctx, memory_simulation(ctx)).to_string()
+            })
             .collect::<Vec<_>>();
 
         write!(file.get_write(), "{}", modules.join("\n")).map_err(|err| {
@@ -131,7 +136,210 @@ impl Backend for VerilogBackend {
     }
 }
 
-fn emit_component(comp: &ir::Component, memory_simulation: bool) -> v::Module {
+/// Writes every component to its own `<component>.sv` file in the output
+/// directory. Instead of copying the primitive libraries, the backend writes
+/// the modules of the primitives instantiated by the program, and the
+/// modules they instantiate, to `primitives.sv`. The filelist `files.f`
+/// references `primitives.sv` followed by the component files.
+#[derive(Default)]
+pub struct VerilogSplitBackend;
+
+/// Name of the filelist generated by [VerilogSplitBackend].
+const FILELIST: &str = "files.f";
+
+/// Name of the file with the primitives generated by [VerilogSplitBackend].
+const PRIMITIVES: &str = "primitives.sv";
+
+impl Backend for VerilogSplitBackend {
+    fn name(&self) -> &'static str {
+        "verilog-split"
+    }
+
+    fn validate(ctx: &ir::Context) -> FutilResult<()> {
+        VerilogBackend::validate(ctx)
+    }
+
+    /// The libraries are referenced by the filelist generated by `emit`.
+    fn link_externs(
+        _ctx: &ir::Context,
+        _file: &mut OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(ctx: &ir::Context, file: &mut OutputFile) -> FutilResult<()> {
+        let dir = match &*file {
+            OutputFile::File(path) => path,
+            OutputFile::Stdout => {
+                return Err(Error::Misc(
+                    "The verilog-split backend writes a directory. Provide it using `-o <dir>`.".to_string(),
+                ))
+            }
+        };
+        let write_err = |path: &std::path::Path| {
+            Error::WriteError(format!("Failed to write {}", path.display()))
+        };
+        fs::create_dir_all(dir).map_err(|_| write_err(dir))?;
+
+        if let Some(comp) = ctx
+            .components
+            .iter()
+            .find(|comp| format!("{}.sv", comp.name.as_ref()) == PRIMITIVES)
+        {
+            return Err(Error::Misc(format!(
+                "Component `{}` conflicts with the file {} written by the verilog-split backend.",
+                comp.name, PRIMITIVES
+            )));
+        }
+        let path = dir.join(PRIMITIVES);
+        fs::write(&path, used_primitives(ctx)?)
+            .map_err(|_| write_err(&path))?;
+
+        let mut filelist = vec![PRIMITIVES.to_string()];
+        for comp in &ctx.components {
+            let name = format!("{}.sv", comp.name.as_ref());
+            let path = dir.join(&name);
+            let module = emit_component(comp, ctx, memory_simulation(ctx));
+            fs::write(&path, module.to_string())
+                .map_err(|_| write_err(&path))?;
+            filelist.push(name);
+        }
+
+        let path = dir.join(FILELIST);
+        let mut out = File::create(&path).map_err(|_| write_err(&path))?;
+        for entry in filelist {
+            writeln!(out, "{}", entry).map_err(|_| write_err(&path))?;
+        }
+        Ok(())
+    }
+}
+
+/// A part of a primitive library.
+enum LibraryItem<'a> {
+    /// A compiler directive or a tool pragma outside of a module.
+    Directive(&'a str),
+    /// The text of the module `name`.
+    Module { name: &'a str, text: String },
+}
+
+/// Split the primitive library `src` into its modules and directives.
+/// Comments outside of modules are dropped.
+fn library_items(src: &str) -> Vec<LibraryItem<'_>> {
+    let mut items = vec![];
+    let mut module: Option<(&str, String)> = None;
+    for line in src.lines() {
+        let trimmed = line.trim_start();
+        match &mut module {
+            Some((_, text)) => {
+                text.push_str(line);
+                text.push('\n');
+                if trimmed.starts_with("endmodule") {
+                    let (name, text) = module.take().unwrap();
+                    items.push(LibraryItem::Module { name, text });
+                }
+            }
+            None if trimmed.starts_with("module ") => {
+                let name = trimmed["module ".len()..]
+                    .trim_start()
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .next()
+                    .unwrap_or_default();
+                module = Some((name, format!("{}\n", line)));
+            }
+            None if trimmed.starts_with('`')
+                || trimmed.starts_with("/* verilator") =>
+            {
+                items.push(LibraryItem::Directive(line))
+            }
+            None => (),
+        }
+    }
+    items
+}
+
+/// The modules of the primitives instantiated by `ctx` and of the modules
+/// they instantiate, along with the directives of the libraries that define
+/// them. Modules are kept in the order of the libraries.
+fn used_primitives(ctx: &ir::Context) -> FutilResult<String> {
+    let sources = ctx
+        .lib
+        .paths
+        .iter()
+        .unique()
+        .map(|path| {
+            fs::read_to_string(path).map_err(|_| {
+                Error::InvalidFile(format!("File not found: {}", path))
+            })
+        })
+        .collect::<FutilResult<Vec<_>>>()?;
+    let libraries: Vec<_> =
+        sources.iter().map(|src| library_items(src)).collect();
+    let modules: HashMap<&str, &str> = libraries
+        .iter()
+        .flatten()
+        .filter_map(|item| match item {
+            LibraryItem::Module { name, text } => Some((*name, text.as_str())),
+            LibraryItem::Directive(_) => None,
+        })
+        .collect();
+
+    // Find the modules transitively instantiated by the program.
+    let mut todo: Vec<String> = ctx
+        .components
+        .iter()
+        .flat_map(|comp| comp.cells.iter())
+        .filter_map(|cell| match &cell.borrow().prototype {
+            ir::CellType::Primitive { name, .. } => Some(name.to_string()),
+            _ => None,
+        })
+        .collect();
+    let mut used = HashSet::new();
+    while let Some(name) = todo.pop() {
+        if let Some(text) = modules.get(name.as_str()) {
+            if used.insert(name) {
+                todo.extend(
+                    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+                        .filter(|word| modules.contains_key(word))
+                        .map(str::to_string),
+                );
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for items in &libraries {
+        let is_used = |item: &LibraryItem| matches!(item, LibraryItem::Module { name, .. } if used.contains(*name));
+        if !items.iter().any(is_used) {
+            continue;
+        }
+        for item in items {
+            match item {
+                LibraryItem::Directive(line) => {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                LibraryItem::Module { text, .. } if is_used(item) => {
+                    out.push_str(text)
+                }
+                LibraryItem::Module { .. } => (),
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Should the backend emit blocks to load and dump external memories.
+fn memory_simulation(ctx: &ir::Context) -> bool {
+    !ctx.synthesis_mode && !ctx.disable_init
+}
+
+/// Emit the module implementing `comp`. When `memory_simulation` is set, the
+/// module loads and dumps its external memories.
+pub(crate) fn emit_component(
+    comp: &ir::Component,
+    ctx: &ir::Context,
+    memory_simulation: bool,
+) -> v::Module {
     let mut module = v::Module::new(comp.name.as_ref());
     let sig = comp.signature.borrow();
     for port_ref in &sig.ports {
@@ -163,27 +371,11 @@ fn emit_component(comp: &ir::Component, memory_simulation: bool) -> v::Module {
         .flat_map(|cell| wire_decls(&cell.borrow()))
         .collect_vec();
     // structure wire declarations
-    wires.iter().for_each(|(name, width, _)| {
+    // Stateful cells are reset synchronously using the `reset` port connected
+    // by the `reset-insertion` pass.
+    wires.iter().for_each(|(name, width)| {
         module.add_decl(v::Decl::new_logic(name, *width));
     });
-    let mut initial = v::ParallelProcess::new_initial();
-    wires.iter().for_each(|(name, width, dir)| {
-        if *dir == ir::Direction::Input {
-            // HACK: this is not the right way to reset
-            // registers. we should have real reset ports.
-            let value = String::from("0");
-            // let value = if name.contains("write_en") {
-            //     String::from("0")
-            // } else {
-            //     String::from("0")
-            // };
-            initial.add_seq(v::Sequential::new_blk_assign(
-                v::Expr::new_ref(name),
-                v::Expr::new_ulit_dec(*width as u32, &value),
-            ));
-        }
-    });
-    module.add_process(initial);
 
     // cell instances
     comp.cells
@@ -204,13 +396,23 @@ fn emit_component(comp: &ir::Component, memory_simulation: bool) -> v::Module {
     map.values()
         .sorted_by_key(|(port, _)| port.borrow().canonical())
         .for_each(|asgns| {
+            if ctx.source_comments {
+                for asgn in &asgns.1 {
+                    module.add_stmt(v::Stmt::new_rawstr(source_comment(asgn)));
+                }
+            }
             module.add_stmt(v::Stmt::new_parallel(emit_assignment(asgns)));
         });
 
     module
 }
 
-fn wire_decls(cell: &ir::Cell) -> Vec<(String, u64, ir::Direction)> {
+/// Name of the Verilog signal for the port `port` of the cell `cell`.
+pub(crate) fn signal_name(cell: &ir::Id, port: &ir::Id) -> String {
+    format!("{}_{}", cell.as_ref(), port.as_ref())
+}
+
+fn wire_decls(cell: &ir::Cell) -> Vec<(String, u64)> {
     cell.ports
         .iter()
         .filter_map(|port| match &port.borrow().parent {
@@ -220,13 +422,8 @@ fn wire_decls(cell: &ir::Cell) -> Vec<(String, u64, ir::Direction)> {
                 match parent.prototype {
                     ir::CellType::Component { .. }
                     | ir::CellType::Primitive { .. } => Some((
-                        format!(
-                            "{}_{}",
-                            parent.name().as_ref(),
-                            port.borrow().name.as_ref()
-                        ),
+                        signal_name(parent.name(), &port.borrow().name),
                         port.borrow().width,
-                        port.borrow().direction.clone(),
                     )),
                     _ => None,
                 }
@@ -265,6 +462,23 @@ fn cell_instance(cell: &ir::Cell) -> Option<v::Instance> {
     }
 }
 
+/// A `// calyx:` comment showing the Calyx assignment `asgn` and the group it
+/// was defined in.
+fn source_comment(asgn: &ir::Assignment) -> String {
+    let mut buf = Vec::new();
+    ir::IRPrinter::write_assignment(asgn, 0, &mut buf)
+        .expect("Failed to print assignment");
+    let mut comment = format!("// calyx: {}", String::from_utf8_lossy(&buf));
+    if let Some(group) = &asgn.origin {
+        comment += &format!(" (group {}", group);
+        if let Some(span) = group.span() {
+            comment += &format!(", line {}", span.line());
+        }
+        comment += ")";
+    }
+    comment
+}
+
 fn emit_assignment(
     (dst_ref, assignments): &(RRC<ir::Port>, Vec<&ir::Assignment>),
 ) -> v::Parallel {
@@ -289,11 +503,7 @@ fn port_to_ref(port_ref: RRC<ir::Port>) -> v::Expr {
                     v::Expr::new_ulit_dec(width as u32, &val.to_string())
                 }
                 ir::CellType::ThisComponent => v::Expr::new_ref(&port.name),
-                _ => v::Expr::Ref(format!(
-                    "{}_{}",
-                    parent.name().as_ref(),
-                    port.name.as_ref()
-                )),
+                _ => v::Expr::Ref(signal_name(parent.name(), &port.name)),
             }
         }
         ir::PortParent::Group(_) => unreachable!(),
@@ -373,22 +583,9 @@ fn memory_read_write(comp: &ir::Component) -> Vec<v::Stmt> {
             ],
         )));
 
-    let memories = comp.cells.iter().filter_map(|cell| {
-        let is_external = cell.borrow().get_attribute("external").is_some();
-        if is_external
-            && cell
-                .borrow()
-                .type_name()
-                .map(|proto| proto.id.contains("mem"))
-                .unwrap_or_default()
-        {
-            Some(cell.borrow().name().id.clone())
-        } else {
-            None
-        }
-    });
+    let memories = external_memories(comp);
 
-    memories.clone().for_each(|name| {
+    memories.iter().for_each(|name| {
         initial_block.add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
             "$readmemh",
             vec![
@@ -404,7 +601,7 @@ fn memory_read_write(comp: &ir::Component) -> Vec<v::Stmt> {
     });
 
     let mut final_block = v::ParallelProcess::new_final();
-    memories.for_each(|name| {
+    memories.iter().for_each(|name| {
         final_block.add_seq(v::Sequential::new_seqexpr(v::Expr::new_call(
             "$writememh",
             vec![
@@ -426,3 +623,26 @@ fn memory_read_write(comp: &ir::Component) -> Vec<v::Stmt> {
         v::Stmt::new_parallel(v::Parallel::new_process(final_block)),
     ]
 }
+
+/// Names of the `@external` memories of `comp`. Every memory primitive,
+/// including `seq_mem_d1` and the dual-port `dp_mem_d1`, stores its contents
+/// in an array named `mem` which is loaded and dumped by the simulation.
+pub(crate) fn external_memories(comp: &ir::Component) -> Vec<String> {
+    comp.cells
+        .iter()
+        .filter_map(|cell| {
+            let cell = cell.borrow();
+            let is_external = cell.get_attribute("external").is_some();
+            if is_external
+                && cell
+                    .type_name()
+                    .map(|proto| proto.id.contains("mem"))
+                    .unwrap_or_default()
+            {
+                Some(cell.name().id.clone())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -114,21 +114,10 @@ impl AddressSpace {
         }
     }
 
-    /// Builder style method for adding an address.
-    pub fn address(
-        mut self,
-        address: usize,
-        name: &str,
-        bit_meaning: Vec<(Range<usize>, &str, Range<usize>, Flags)>,
-    ) -> Self {
-        self.add_address(address, name, bit_meaning);
-        self
-    }
-
     /// Add an address called `name` with meanings: `bit_meaning`.
     /// For example,
     /// ```
-    /// space.address(
+    /// space.add_address(
     ///   0x0,
     ///   "CTRL",
     ///   vec![
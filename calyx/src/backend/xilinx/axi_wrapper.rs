@@ -0,0 +1,105 @@
+//! Backends for generating standalone AXI IP that does not depend on the
+//! Vitis flow.
+//!
+//! The IP is controlled by an AXI-Lite interface whose register map is
+//! documented by the `axi-wrapper-header` and `axi-wrapper-json` backends.
+use super::{
+    params::{Convention, Kernel},
+    register_map::RegisterMap,
+    toplevel,
+};
+use crate::{backend::traits::Backend, errors::FutilResult, ir};
+
+/// Backend that generates a standalone AXI-Lite controlled IP for a Calyx
+/// program.
+#[derive(Default)]
+pub struct AxiWrapperBackend;
+
+impl Backend for AxiWrapperBackend {
+    fn name(&self) -> &'static str {
+        "axi-wrapper"
+    }
+
+    fn validate(_ctx: &ir::Context) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _prog: &ir::Context,
+        _write: &mut crate::utils::OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(
+        prog: &ir::Context,
+        file: &mut crate::utils::OutputFile,
+    ) -> FutilResult<()> {
+        let kernel = Kernel::new(prog, Convention::Generic)?;
+        toplevel::write_modules(file, toplevel::interface_modules(&kernel))
+    }
+}
+
+/// Backend that generates a C header defining the register map of the
+/// `axi-wrapper` IP.
+#[derive(Default)]
+pub struct AxiWrapperHeaderBackend;
+
+impl Backend for AxiWrapperHeaderBackend {
+    fn name(&self) -> &'static str {
+        "axi-wrapper-header"
+    }
+
+    fn validate(_ctx: &ir::Context) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _prog: &ir::Context,
+        _write: &mut crate::utils::OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(
+        prog: &ir::Context,
+        file: &mut crate::utils::OutputFile,
+    ) -> FutilResult<()> {
+        let kernel = Kernel::new(prog, Convention::Generic)?;
+        write!(file.get_write(), "{}", RegisterMap::new(&kernel).c_header())?;
+        Ok(())
+    }
+}
+
+/// Backend that generates a JSON description of the register map of the
+/// `axi-wrapper` IP.
+#[derive(Default)]
+pub struct AxiWrapperJsonBackend;
+
+impl Backend for AxiWrapperJsonBackend {
+    fn name(&self) -> &'static str {
+        "axi-wrapper-json"
+    }
+
+    fn validate(_ctx: &ir::Context) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn link_externs(
+        _prog: &ir::Context,
+        _write: &mut crate::utils::OutputFile,
+    ) -> FutilResult<()> {
+        Ok(())
+    }
+
+    fn emit(
+        prog: &ir::Context,
+        file: &mut crate::utils::OutputFile,
+    ) -> FutilResult<()> {
+        let kernel = Kernel::new(prog, Convention::Generic)?;
+        let json = serde_json::to_string_pretty(&RegisterMap::new(&kernel))
+            .expect("Failed to serialize the register map");
+        writeln!(file.get_write(), "{}", json)?;
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use vast::v05::ast as v;
 
 use super::axi::{AxiChannel, AxiInterface, ChannelDirection};
-use super::axi_address_space::{AddressSpace, Flags};
+use super::register_map::RegisterMap;
 
 /// Represents the AXI control interface that Xilinx expects
 /// kernels to have.
@@ -11,60 +11,7 @@ pub trait ControlInterface {
         data_width: u64,
         prefix: &str,
     ) -> Self;
-    fn control_module(
-        name: &str,
-        address_width: u64,
-        data_width: u64,
-        memories: &[String],
-    ) -> v::Module;
-}
-
-/// Generate the base address space for the Xilinx control interface.
-fn axi_address_space(
-    axi: &AxiInterface,
-    address_width: u64,
-    data_width: u64,
-) -> AddressSpace {
-    AddressSpace::new(address_width, data_width)
-        .address(
-            0x0,
-            "AP_CONTROL",
-            vec![
-                (
-                    0..1,
-                    "int_ap_start",
-                    0..1,
-                    Flags::default().write().clear_on_handshake("ap_done"),
-                ),
-                (
-                    1..2,
-                    "int_ap_done",
-                    0..1,
-                    Flags::default()
-                        .read("ap_done")
-                        .clear_on_read(axi.read_data.clone(), "raddr"),
-                ),
-                // (2..3, "ap_idle", 0..1),,
-            ],
-        )
-        .address(
-            0x4,
-            "GIE",
-            vec![(0..1, "int_gie", 0..1, Flags::default().write())],
-        )
-        .address(
-            0x8,
-            "IER",
-            vec![(0..2, "int_ier", 0..2, Flags::default().write())],
-        )
-        .address(
-            0xc,
-            "ISR",
-            vec![
-                (0..1, "int_isr_done", 0..1, Flags::default().write()), // XXX should be read
-                (1..2, "int_isr_ready", 0..1, Flags::default().write()),
-            ],
-        )
+    fn control_module(name: &str, map: &RegisterMap) -> v::Module;
 }
 
 impl ControlInterface for AxiInterface {
@@ -118,12 +65,9 @@ impl ControlInterface for AxiInterface {
         }
     }
 
-    fn control_module(
-        name: &str,
-        address_width: u64,
-        data_width: u64,
-        memories: &[String],
-    ) -> v::Module {
+    fn control_module(name: &str, map: &RegisterMap) -> v::Module {
+        let address_width = map.address_width;
+        let data_width = map.data_width;
         let mut module = v::Module::new(name);
 
         module.add_input("ACLK", 1);
@@ -133,34 +77,14 @@ impl ControlInterface for AxiInterface {
             AxiInterface::control_channels(address_width, data_width, "");
 
         // define the address space of the control interface
-        let mut addr_space =
-            axi_address_space(&axi4, address_width, data_width);
-        addr_space.add_address(
-            0x10,
-            "TIMEOUT",
-            vec![(0..32, "int_timeout", 0..32, Flags::default().write())],
-        );
-        for (idx, memory_name) in memories.iter().enumerate() {
-            let part0_name = format!("{}_0", memory_name);
-            let part1_name = format!("{}_1", memory_name);
-            let addr_name = format!("addr_{}", memory_name);
-            addr_space.add_address(
-                0x18 + (idx * 8),
-                &part0_name,
-                vec![(0..32, &addr_name, 0..32, Flags::default().write())],
-            );
-            addr_space.add_address(
-                0x1c + (idx * 8),
-                &part1_name,
-                vec![(0..32, &addr_name, 32..64, Flags::default().write())],
-            );
-
-            module.add_output(memory_name, 64);
+        let addr_space = map.address_space(&axi4);
+        let outputs = map.outputs();
+        for (port, _, width) in &outputs {
+            module.add_output(port, *width);
+        }
+        for port in map.inputs() {
+            module.add_input(port, 1);
         }
-
-        module.add_output("ap_start", 1);
-        module.add_input("ap_done", 1);
-        module.add_output("timeout", 32);
 
         axi4.add_ports_to(&mut module);
 
@@ -207,69 +131,17 @@ impl ControlInterface for AxiInterface {
         addr_space.internal_registers(&mut module);
 
         // register logic
-        module.add_stmt(v::Parallel::Assign(
-            "ap_start".into(),
-            "int_ap_start".into(),
-        ));
-        module.add_stmt(v::Parallel::Assign(
-            "timeout".into(),
-            "int_timeout".into(),
-        ));
-        addr_space.register_logic(
-            &mut module,
-            axi4.write_data.handshake(),
-            "AP_CONTROL",
-            "waddr",
-            "wdata",
-        );
-        addr_space.register_logic(
-            &mut module,
-            axi4.write_data.handshake(),
-            "GIE",
-            "waddr",
-            "wdata",
-        );
-        addr_space.register_logic(
-            &mut module,
-            axi4.write_data.handshake(),
-            "IER",
-            "waddr",
-            "wdata",
-        );
-        addr_space.register_logic(
-            &mut module,
-            axi4.write_data.handshake(),
-            "ISR",
-            "waddr",
-            "wdata",
-        );
-        addr_space.register_logic(
-            &mut module,
-            axi4.write_data.handshake(),
-            "TIMEOUT",
-            "waddr",
-            "wdata",
-        );
-
-        for memory in memories {
-            let part0_name = format!("{}_0", memory);
-            let part1_name = format!("{}_1", memory);
-            let addr_name = format!("addr_{}", memory);
+        for (port, register, _) in &outputs {
             module.add_stmt(v::Parallel::Assign(
-                memory.as_str().into(),
-                addr_name.into(),
+                (*port).into(),
+                (*register).into(),
             ));
+        }
+        for reg in &map.registers {
             addr_space.register_logic(
                 &mut module,
                 axi4.write_data.handshake(),
-                &part0_name,
-                "waddr",
-                "wdata",
-            );
-            addr_space.register_logic(
-                &mut module,
-                axi4.write_data.handshake(),
-                &part1_name,
+                &reg.name,
                 "waddr",
                 "wdata",
             );
@@ -3,6 +3,8 @@ use vast::v05::ast as v;
 use super::{
     axi::{AxiChannel, AxiInterface, ChannelDirection},
     fsm,
+    params::ExternalMemory,
+    utils::zero_extend,
 };
 use crate::utils;
 use std::rc::Rc;
@@ -17,9 +19,10 @@ pub trait MemoryInterface {
     ) -> Self;
     fn memory_module(
         name: &str,
+        bram_name: &str,
         bus_data_width: u64,
         bus_addr_width: u64,
-        data_width: u64,
+        memory: &ExternalMemory,
     ) -> v::Module;
 }
 
@@ -96,20 +99,23 @@ impl MemoryInterface for AxiInterface {
 
     fn memory_module(
         name: &str,
+        bram_name: &str,
         bus_data_width: u64,
         bus_addr_width: u64,
-        // address_width: u64,
-        data_width: u64,
+        memory: &ExternalMemory,
     ) -> v::Module {
         let mut module = v::Module::new(name);
-        let memory_size = 32;
-        let memory_size_bits: u64 = utils::math::bits_needed_for(memory_size); // TODO make memory size parametric
+        let data_width = memory.width;
+        let memory_size = memory.size;
+        // the offset counters need to be able to represent `memory_size`.
+        let offset_size_bits = utils::math::bits_needed_for(memory_size + 1);
 
         module.add_input("ACLK", 1);
         module.add_input("ARESET", 1);
 
         // add axi interface ports
-        let axi4 = AxiInterface::memory_channels(bus_addr_width, 512, "");
+        let axi4 =
+            AxiInterface::memory_channels(bus_addr_width, bus_data_width, "");
         axi4.add_ports_to(&mut module);
 
         module.add_input("BASE_ADDRESS", bus_addr_width);
@@ -121,150 +127,401 @@ impl MemoryInterface for AxiInterface {
         // BRAM interface
         module.add_input("WRITE_DATA", data_width);
         module.add_output("READ_DATA", data_width);
-        module.add_input("ADDR", memory_size_bits);
+        module.add_input("ADDR", memory.idx_size);
         module.add_input("WE", 1);
         module.add_output("DONE", 1);
+        if memory.seq {
+            module.add_input("READ_EN", 1);
+            module.add_output("READ_DONE", 1);
+        }
+        // The second port of a dual-port memory is only used by the kernel.
+        if memory.dual {
+            module.add_input("WRITE_DATA_B", data_width);
+            module.add_output("READ_DATA_B", data_width);
+            module.add_input("ADDR_B", memory.idx_size);
+            module.add_input("WE_B", 1);
+            module.add_output("DONE_B", 1);
+            module.add_input("READ_EN_B", 1);
+            module.add_output("READ_DONE_B", 1);
+        }
 
         // internal signals
+        module.add_decl(v::Decl::new_reg("copy_addr_offset", offset_size_bits));
+        module.add_decl(v::Decl::new_reg("send_addr_offset", offset_size_bits));
         module.add_decl(v::Decl::new_wire("copy_done", 1));
         module.add_stmt(v::Parallel::Assign(
             "copy_done".into(),
             v::Expr::new_eq("copy_addr_offset", memory_size as i32),
         ));
-        module.add_decl(v::Decl::new_wire("send_done", 1));
+        // every element has been read from the bram
+        module.add_decl(v::Decl::new_wire("send_read_done", 1));
         module.add_stmt(v::Parallel::Assign(
-            "send_done".into(),
+            "send_read_done".into(),
             v::Expr::new_eq("send_addr_offset", memory_size as i32),
         ));
+        module.add_decl(v::Decl::new_wire("send_done", 1));
 
         // module mode fsm
         let mode_fsm = module_mode_fsm(&mut module);
-        // module.add_stmt(v::Parallel::Assign(
-        //     "SEND_TO_HOST_DONE".into(),
-        //     v::Expr::new_mux(mode_fsm.state_is("send"), "send_done", 0),
-        // ));
-
-        // count the number of read transactions we've received
-        module.add_decl(v::Decl::new_reg(
-            "read_txn_count",
-            utils::math::bits_needed_for(bus_data_width / data_width),
-        ));
-        module.add_stmt(super::utils::cond_non_blk_assign(
-            "ACLK",
-            "read_txn_count",
-            vec![
-                (Some("ARESET".into()), 0.into()),
-                (
-                    Some(axi4.read_data.handshake()),
-                    v::Expr::new_add("read_txn_count", 1),
+
+        let burst = Burst::new(bus_data_width, bus_addr_width, memory);
+
+        // read bursts copy data from the host into the bram
+        let read_fsm = fsm::LinearFsm::new("r", "ACLK", "ARESET")
+            .state(
+                "idle",
+                &[],
+                v::Expr::new_logical_and(
+                    mode_fsm.state_is("copy"),
+                    v::Expr::new_not("copy_done"),
                 ),
-            ],
-        ));
+            )
+            .state(
+                "addr",
+                &[axi4.read_address.ready().into()],
+                axi4.read_address.valid(),
+            )
+            .state(
+                "data",
+                &[],
+                v::Expr::new_bit_and(
+                    axi4.read_data.handshake(),
+                    axi4.read_data.get("LAST"),
+                ),
+            );
+        read_fsm.emit(&mut module);
+        burst.address(&mut module, &axi4.read_address, "copy");
 
-        // bram reading / writing logic
-        bram_logic(&axi4, &mut module, &mode_fsm, "read_txn_count".into());
+        // the elements in a beat are written into the bram one per cycle and
+        // the beat is only accepted once its last element has been written.
+        module.add_decl(v::Decl::new_wire("copy_write", 1));
         module.add_stmt(v::Parallel::Assign(
-            "READ_DATA".into(),
-            "bram_read_data".into(),
+            "copy_write".into(),
+            v::Expr::new_logical_and(
+                v::Expr::new_logical_and(
+                    read_fsm.state_is("data"),
+                    axi4.read_data.valid(),
+                ),
+                v::Expr::new_not("copy_done"),
+            ),
+        ));
+        burst.lane(&mut module, "copy");
+        module.add_stmt(v::Parallel::Assign(
+            axi4.read_data.ready().into(),
+            v::Expr::new_logical_and("copy_write", "copy_beat_end"),
         ));
-
-        let offset_size_bits = memory_size_bits + 1;
-
-        // synchronise channels
-        let read_controller = axi4
-            .read_address
-            .then(&axi4.read_data)
-            .prefix("r")
-            .trigger(mode_fsm.next_state_is("copy"));
-        read_controller.emit(&mut module);
-
-        // increment copy address offset
-        module.add_decl(v::Decl::new_reg("copy_addr_offset", offset_size_bits));
         incr_addr(
             &mut module,
             mode_fsm.state_is("copy"),
             "copy_addr_offset",
-            axi4.read_data.handshake(),
+            "copy_write".into(),
         );
 
-        // addresses are byte addressed which means addresses are computed as
-        // base + (offset << shift_by)
-        let shift_by = 2;
-        let burst_size: i32 = utils::math::bits_needed_for(32 / 8) as i32;
-
-        module.add_stmt(axi4.read_address.assign("ID", 0));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr("copy_addr_offset");
-        concat.add_expr(v::Expr::new_repeat(
-            bus_addr_width - offset_size_bits,
-            v::Expr::new_ulit_bin(1, "0"),
+        // bram reading / writing logic
+        bram_logic(&axi4, &mut module, &mode_fsm, bram_name, memory);
+        if memory.seq {
+            read_register(&mut module, memory, "");
+        } else {
+            module.add_stmt(v::Parallel::Assign(
+                "READ_DATA".into(),
+                "bram_read_data".into(),
+            ));
+        }
+        if memory.dual {
+            read_register(&mut module, memory, "_B");
+        }
+
+        // write bursts send data from the bram to the host
+        let write_fsm = fsm::LinearFsm::new("w", "ACLK", "ARESET")
+            .state(
+                "idle",
+                &[],
+                v::Expr::new_logical_and(
+                    mode_fsm.state_is("send"),
+                    v::Expr::new_not("send_read_done"),
+                ),
+            )
+            .state(
+                "addr",
+                &[axi4.write_address.ready().into()],
+                axi4.write_address.valid(),
+            )
+            .state(
+                "data",
+                &[],
+                v::Expr::new_bit_and(
+                    axi4.write_data.handshake(),
+                    axi4.write_data.get("LAST"),
+                ),
+            )
+            .state(
+                "resp",
+                &[axi4.write_response.ready().into()],
+                axi4.write_response.valid(),
+            );
+        write_fsm.emit(&mut module);
+        burst.address(&mut module, &axi4.write_address, "send");
+        module.add_stmt(v::Parallel::Assign(
+            "send_done".into(),
+            v::Expr::new_logical_and(
+                "send_read_done",
+                write_fsm.state_is("idle"),
+            ),
         ));
-        module.add_stmt(axi4.read_address.assign(
-            "ADDR",
-            v::Expr::new_add(
-                "BASE_ADDRESS",
-                v::Expr::new_shift_left(concat, shift_by),
+
+        // elements are read from the bram one per cycle and packed into a
+        // beat which is sent once it is full.
+        module.add_decl(v::Decl::new_reg("write_buffer", bus_data_width));
+        module.add_decl(v::Decl::new_reg("write_strb", bus_data_width / 8));
+        module.add_decl(v::Decl::new_reg("write_full", 1));
+        module.add_decl(v::Decl::new_wire("send_read", 1));
+        module.add_stmt(v::Parallel::Assign(
+            "send_read".into(),
+            v::Expr::new_logical_and(
+                v::Expr::new_logical_and(
+                    write_fsm.state_is("data"),
+                    v::Expr::new_not("write_full"),
+                ),
+                v::Expr::new_not("send_read_done"),
             ),
         ));
-        module.add_stmt(axi4.read_address.assign("LEN", 0));
-        module.add_stmt(axi4.read_address.assign("SIZE", burst_size));
-
-        let write_controller = axi4
-            .write_address
-            .then(&axi4.write_data)
-            .then(&axi4.write_response)
-            .prefix("w")
-            .trigger(mode_fsm.next_state_is("send"));
-        write_controller.emit(&mut module);
-
-        // increment send address offset
-        module.add_decl(v::Decl::new_reg("send_addr_offset", offset_size_bits));
+        burst.lane(&mut module, "send");
         incr_addr(
             &mut module,
             mode_fsm.state_is("send"),
             "send_addr_offset",
-            axi4.write_response.handshake(),
+            "send_read".into(),
+        );
+        pack_logic(&axi4, &mut module, memory);
+        module.add_stmt(v::Parallel::Assign(
+            axi4.write_data.ready().into(),
+            v::Expr::new_logical_and(write_fsm.state_is("data"), "write_full"),
+        ));
+        module.add_stmt(axi4.write_data.assign("ID", 0));
+        module.add_stmt(axi4.write_data.assign("DATA", "write_buffer"));
+        module.add_stmt(axi4.write_data.assign("STRB", "write_strb"));
+
+        // count the beats in the current write burst to generate `LAST`
+        module.add_decl(v::Decl::new_reg("write_len", 8));
+        module.add_decl(v::Decl::new_reg("write_beat", 8));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            "write_len",
+            vec![(
+                Some(axi4.write_address.handshake()),
+                axi4.write_address.get("LEN").into(),
+            )],
+        ));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            "write_beat",
+            vec![
+                (Some(axi4.write_address.handshake()), 0.into()),
+                (
+                    Some(axi4.write_data.handshake()),
+                    v::Expr::new_add("write_beat", 1),
+                ),
+            ],
+        ));
+        module.add_stmt(
+            axi4.write_data
+                .assign("LAST", v::Expr::new_eq("write_beat", "write_len")),
         );
 
-        module.add_stmt(axi4.write_address.assign("ID", 0));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr("send_addr_offset");
-        concat.add_expr(v::Expr::new_repeat(
-            bus_addr_width - offset_size_bits,
-            v::Expr::new_ulit_bin(1, "0"),
+        module
+    }
+}
+
+/// Parameters of the INCR bursts used to transfer a memory. Bursts always
+/// use the full width of the data bus and each beat carries `lanes` elements.
+/// Base addresses must be aligned to the width of the data bus. Bursts are
+/// shortened so that they do not cross a 4KB boundary.
+struct Burst {
+    /// Number of elements in the memory.
+    size: u64,
+    /// Width of the offset counters.
+    offset_width: u64,
+    /// Number of elements packed into each beat.
+    lanes: u64,
+    /// log2 of `lanes`.
+    lane_bits: u64,
+    /// log2 of the number of bytes used by each element.
+    element_shift: u64,
+    /// log2 of the number of bytes in each beat.
+    beat_shift: u64,
+    /// Maximum number of beats in an INCR burst.
+    max_beats: u64,
+    /// Width of the address bus.
+    bus_addr_width: u64,
+}
+
+impl Burst {
+    fn new(
+        bus_data_width: u64,
+        bus_addr_width: u64,
+        memory: &ExternalMemory,
+    ) -> Self {
+        let lanes = bus_data_width / memory.lane_width();
+        let beat_bytes = bus_data_width / 8;
+        Burst {
+            size: memory.size,
+            offset_width: utils::math::bits_needed_for(memory.size + 1),
+            lanes,
+            lane_bits: u64::from(lanes.trailing_zeros()),
+            element_shift: u64::from(memory.bytes().trailing_zeros()),
+            beat_shift: u64::from(beat_bytes.trailing_zeros()),
+            max_beats: 256,
+            bus_addr_width,
+        }
+    }
+
+    /// Drive the address channel `channel` to request a burst starting at
+    /// the element `{prefix}_addr_offset` that transfers as many of the
+    /// remaining elements as possible without crossing a 4KB boundary.
+    fn address(
+        &self,
+        module: &mut v::Module,
+        channel: &AxiChannel,
+        prefix: &str,
+    ) {
+        let offset = format!("{}_addr_offset", prefix);
+        let remaining = format!("{}_remaining", prefix);
+        let beats_left = format!("{}_beats_left", prefix);
+
+        // number of beats needed for the remaining elements
+        module.add_decl(v::Decl::new_wire(&remaining, self.offset_width));
+        module.add_stmt(v::Parallel::Assign(
+            remaining.as_str().into(),
+            v::Expr::new_sub(self.size as i32, offset.as_str()),
         ));
-        module.add_stmt(axi4.write_address.assign(
-            "ADDR",
+        if self.lane_bits == 0 {
+            module.add_decl(v::Decl::new_wire(&beats_left, self.offset_width));
+            module.add_stmt(v::Parallel::Assign(
+                beats_left.as_str().into(),
+                remaining.as_str().into(),
+            ));
+        } else {
+            let padded = format!("{}_remaining_padded", prefix);
+            let padded_width = self.offset_width.max(self.lane_bits) + 1;
+            module.add_decl(v::Decl::new_wire(&padded, padded_width));
+            module.add_stmt(v::Parallel::Assign(
+                padded.as_str().into(),
+                v::Expr::new_add(remaining.as_str(), (self.lanes - 1) as i32),
+            ));
+            module.add_decl(v::Decl::new_wire(
+                &beats_left,
+                padded_width - self.lane_bits,
+            ));
+            module.add_stmt(v::Parallel::Assign(
+                beats_left.as_str().into(),
+                v::Expr::new_slice(
+                    &padded,
+                    padded_width as i32 - 1,
+                    self.lane_bits as i32,
+                ),
+            ));
+        }
+
+        let addr = format!("{}_burst_addr", prefix);
+        module.add_decl(v::Decl::new_wire(&addr, self.bus_addr_width));
+        module.add_stmt(v::Parallel::Assign(
+            addr.as_str().into(),
             v::Expr::new_add(
                 "BASE_ADDRESS",
-                v::Expr::new_shift_left(concat, shift_by),
+                byte_address(
+                    &offset,
+                    self.offset_width,
+                    self.element_shift,
+                    self.bus_addr_width,
+                ),
             ),
         ));
-        module.add_stmt(axi4.write_address.assign("LEN", 0));
-        module.add_stmt(axi4.write_address.assign("SIZE", burst_size));
 
-        // write data channel
-        module.add_stmt(axi4.write_data.assign("ID", 0));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr("bram_read_data");
-        concat.add_expr(v::Expr::new_repeat(
-            (bus_data_width / data_width) - 1,
-            "bram_read_data",
+        // number of beats before the burst would cross a 4KB boundary
+        let boundary = format!("{}_boundary_beats", prefix);
+        let page_bits = 12 - self.beat_shift;
+        module.add_decl(v::Decl::new_wire(&boundary, page_bits + 1));
+        module.add_stmt(v::Parallel::Assign(
+            boundary.as_str().into(),
+            v::Expr::new_sub(
+                1 << page_bits,
+                v::Expr::new_slice(&addr, 11, self.beat_shift as i32),
+            ),
         ));
-        module.add_stmt(axi4.write_data.assign("DATA", concat));
-        let mut concat = v::ExprConcat::default();
-        concat.add_expr(v::Expr::new_ulit_hex(4, "F"));
-        concat.add_expr(v::Expr::new_repeat(
-            (bus_data_width / (8 * 4)) - 1,
-            v::Expr::new_ulit_hex(4, "F"),
+        let beats = format!("{}_burst_beats", prefix);
+        module.add_decl(v::Decl::new_wire(&beats, page_bits + 1));
+        module.add_stmt(v::Parallel::Assign(
+            beats.as_str().into(),
+            v::Expr::new_mux(
+                v::Expr::new_gt(beats_left.as_str(), boundary.as_str()),
+                boundary.as_str(),
+                beats_left.as_str(),
+            ),
         ));
-        module.add_stmt(axi4.write_data.assign("STRB", concat));
-        module.add_stmt(axi4.write_data.assign("LAST", 1));
 
-        module
+        module.add_stmt(channel.assign("ID", 0));
+        module.add_stmt(channel.assign("ADDR", addr.as_str()));
+        // a 4KB page only holds more beats than a burst with narrow buses
+        let len = if (1 << page_bits) > self.max_beats {
+            v::Expr::new_mux(
+                v::Expr::new_gt(beats.as_str(), self.max_beats as i32),
+                (self.max_beats - 1) as i32,
+                v::Expr::new_sub(beats.as_str(), 1),
+            )
+        } else {
+            v::Expr::new_sub(beats.as_str(), 1)
+        };
+        module.add_stmt(channel.assign("LEN", len));
+        module.add_stmt(channel.assign("SIZE", self.beat_shift as i32));
+    }
+
+    /// Define `{prefix}_lane`, the lane of the beat holding the element
+    /// `{prefix}_addr_offset`, and `{prefix}_beat_end` which is true when it
+    /// is the last element of its beat.
+    fn lane(&self, module: &mut v::Module, prefix: &str) {
+        let offset = format!("{}_addr_offset", prefix);
+        let lane = format!("{}_lane", prefix);
+        let beat_end = format!("{}_beat_end", prefix);
+        module.add_decl(v::Decl::new_wire(&lane, self.lane_bits.max(1)));
+        module.add_decl(v::Decl::new_wire(&beat_end, 1));
+        if self.lane_bits == 0 {
+            module
+                .add_stmt(v::Parallel::Assign(lane.as_str().into(), 0.into()));
+            module.add_stmt(v::Parallel::Assign(
+                beat_end.as_str().into(),
+                1.into(),
+            ));
+        } else {
+            let msb = self.lane_bits.min(self.offset_width) as i32 - 1;
+            module.add_stmt(v::Parallel::Assign(
+                lane.as_str().into(),
+                v::Expr::new_slice(&offset, msb, 0),
+            ));
+            module.add_stmt(v::Parallel::Assign(
+                beat_end.as_str().into(),
+                v::Expr::new_logical_or(
+                    v::Expr::new_eq(lane.as_str(), (self.lanes - 1) as i32),
+                    v::Expr::new_eq(offset.as_str(), (self.size - 1) as i32),
+                ),
+            ));
+        }
     }
 }
 
+/// The `target` bit byte address of the element at offset `name`, which is
+/// `name << shift_by`. The shift is generated as a concatenation to avoid
+/// precedence issues when the address is added to the base address.
+fn byte_address(name: &str, width: u64, shift_by: u64, target: u64) -> v::Expr {
+    if shift_by == 0 {
+        return zero_extend(name, width, target);
+    }
+    let mut concat = v::ExprConcat::default();
+    concat
+        .add_expr(v::Expr::new_repeat(shift_by, v::Expr::new_ulit_bin(1, "0")));
+    concat.add_expr(zero_extend(name, width, target - shift_by));
+    concat.into()
+}
+
 fn module_mode_fsm(module: &mut v::Module) -> fsm::LinearFsm {
     // states:
     //  0: idle, start when COPY_TO_HOST
@@ -285,33 +542,44 @@ fn bram_logic(
     axi4: &AxiInterface,
     module: &mut v::Module,
     mode_fsm: &fsm::LinearFsm,
-    txn_count: v::Expr,
+    bram_name: &str,
+    memory: &ExternalMemory,
 ) {
-    module.add_decl(v::Decl::new_wire("bram_addr", 5));
-    module.add_decl(v::Decl::new_wire("bram_write_data", 32));
+    module.add_decl(v::Decl::new_wire("bram_addr", memory.idx_size));
+    module.add_decl(v::Decl::new_wire("bram_write_data", memory.width));
     module.add_decl(v::Decl::new_wire("bram_we", 1));
-    module.add_decl(v::Decl::new_wire("bram_read_data", 32));
+    module.add_decl(v::Decl::new_wire("bram_read_data", memory.width));
     module.add_decl(v::Decl::new_wire("bram_done", 1));
 
-    let mut ram_instance = v::Instance::new("bram", "SINGLE_PORT_BRAM");
+    let mut ram_instance = v::Instance::new("bram", bram_name);
     ram_instance.connect_ref("ACLK", "ACLK");
     ram_instance.connect_ref("ADDR", "bram_addr");
     ram_instance.connect_ref("Din", "bram_write_data");
     ram_instance.connect_ref("WE", "bram_we");
     ram_instance.connect_ref("Dout", "bram_read_data");
     ram_instance.connect_ref("Done", "bram_done");
+    if memory.dual {
+        module.add_decl(v::Decl::new_wire("bram_read_data_b", memory.width));
+        ram_instance.connect_ref("ADDR_B", "ADDR_B");
+        ram_instance.connect_ref("Din_B", "WRITE_DATA_B");
+        ram_instance.connect(
+            "WE_B",
+            v::Expr::new_logical_and(mode_fsm.state_is("bram"), "WE_B"),
+        );
+        ram_instance.connect_ref("Dout_B", "bram_read_data_b");
+        ram_instance.connect_ref("Done_B", "DONE_B");
+    }
     module.add_instance(ram_instance);
     module.add_stmt(v::Parallel::Assign("DONE".into(), "bram_done".into()));
 
     // bram address logic
-    let copy_address = v::Expr::new_slice("copy_addr_offset", 4, 0);
+    let offset_width = utils::math::bits_needed_for(memory.size + 1);
+    let addr_msb = memory.idx_size.min(offset_width) as i32 - 1;
+    let copy_address = v::Expr::new_slice("copy_addr_offset", addr_msb, 0);
     let bram_address: v::Expr = "ADDR".into();
-    let send_address = v::Expr::new_slice("send_addr_offset", 4, 0);
+    let send_address = v::Expr::new_slice("send_addr_offset", addr_msb, 0);
     let mux_address = v::Expr::new_mux(
-        v::Expr::new_logical_and(
-            axi4.read_data.handshake(),
-            mode_fsm.state_is("copy"),
-        ),
+        "copy_write",
         copy_address,
         v::Expr::new_mux(
             mode_fsm.state_is("bram"),
@@ -325,10 +593,7 @@ fn bram_logic(
     let copy_we: v::Expr = 1.into();
     let bram_we: v::Expr = "WE".into();
     let mux_we = v::Expr::new_mux(
-        v::Expr::new_logical_and(
-            axi4.read_data.handshake(),
-            mode_fsm.state_is("copy"),
-        ),
+        "copy_write",
         copy_we,
         v::Expr::new_mux(mode_fsm.state_is("bram"), bram_we, 0),
     );
@@ -337,21 +602,106 @@ fn bram_logic(
     // bram write data
     let copy_data: v::Expr = v::Expr::new_index_slice(
         &axi4.read_data.get("DATA"),
-        v::Expr::new_mul(txn_count, 32),
-        32, /* bram data width */
+        v::Expr::new_mul("copy_lane", memory.lane_width() as i32),
+        memory.width as u32,
     );
     let bram_data: v::Expr = "WRITE_DATA".into();
     let mux_data = v::Expr::new_mux(
-        v::Expr::new_logical_and(
-            axi4.read_data.handshake(),
-            mode_fsm.state_is("copy"),
-        ),
+        "copy_write",
         copy_data,
         v::Expr::new_mux(mode_fsm.state_is("bram"), bram_data, 0),
     );
     module.add_stmt(v::Parallel::Assign("bram_write_data".into(), mux_data));
 }
 
+/// Register the data read from the bram for memories with registered reads.
+/// `READ_DATA` is updated and `READ_DONE` is high in the cycle after
+/// `READ_EN` is asserted. `port` is appended to the names of the ports, so
+/// `_B` registers the reads of the second port of a dual-port memory.
+fn read_register(module: &mut v::Module, memory: &ExternalMemory, port: &str) {
+    let suffix = port.to_lowercase();
+    let read_en = format!("READ_EN{}", port);
+    let read_data = format!("read_data_reg{}", suffix);
+    let read_done = format!("read_done_reg{}", suffix);
+    module.add_decl(v::Decl::new_reg(&read_data, memory.width));
+    module.add_decl(v::Decl::new_reg(&read_done, 1));
+    module.add_stmt(super::utils::cond_non_blk_assign(
+        "ACLK",
+        read_data.as_str(),
+        vec![
+            (
+                Some(read_en.as_str().into()),
+                format!("bram_read_data{}", suffix).as_str().into(),
+            ),
+            (None, read_data.as_str().into()),
+        ],
+    ));
+    module.add_stmt(super::utils::cond_non_blk_assign(
+        "ACLK",
+        read_done.as_str(),
+        vec![
+            (Some("ARESET".into()), 0.into()),
+            (None, read_en.as_str().into()),
+        ],
+    ));
+    module.add_stmt(v::Parallel::Assign(
+        format!("READ_DATA{}", port).as_str().into(),
+        read_data.as_str().into(),
+    ));
+    module.add_stmt(v::Parallel::Assign(
+        format!("READ_DONE{}", port).as_str().into(),
+        read_done.as_str().into(),
+    ));
+}
+
+/// Pack the elements read from the bram into `write_buffer`. `write_strb`
+/// enables the lanes that have been filled so that a partially filled final
+/// beat does not overwrite host memory past the end of the memory.
+fn pack_logic(
+    axi4: &AxiInterface,
+    module: &mut v::Module,
+    memory: &ExternalMemory,
+) {
+    let lane_width = memory.lane_width();
+    let mut always = v::ParallelProcess::new_always();
+    always.set_event(v::Sequential::new_posedge("ACLK"));
+
+    let mut reset_if = v::SequentialIfElse::new("ARESET");
+    reset_if.add_seq(v::Sequential::new_nonblk_assign("write_full", 0));
+    reset_if.add_seq(v::Sequential::new_nonblk_assign("write_strb", 0));
+
+    let mut read_if = v::SequentialIfElse::new("send_read");
+    read_if.add_seq(v::Sequential::new_nonblk_assign(
+        v::Expr::new_index_slice(
+            "write_buffer",
+            v::Expr::new_mul("send_lane", lane_width as i32),
+            lane_width as u32,
+        ),
+        zero_extend("bram_read_data", memory.width, lane_width),
+    ));
+    read_if.add_seq(v::Sequential::new_nonblk_assign(
+        v::Expr::new_index_slice(
+            "write_strb",
+            v::Expr::new_mul("send_lane", memory.bytes() as i32),
+            memory.bytes() as u32,
+        ),
+        v::Expr::new_repeat(memory.bytes(), v::Expr::new_ulit_bin(1, "1")),
+    ));
+    read_if.add_seq(v::Sequential::new_nonblk_assign(
+        "write_full",
+        "send_beat_end",
+    ));
+
+    let mut sent_if = v::SequentialIfElse::new(axi4.write_data.handshake());
+    sent_if.add_seq(v::Sequential::new_nonblk_assign("write_full", 0));
+    sent_if.add_seq(v::Sequential::new_nonblk_assign("write_strb", 0));
+
+    read_if.set_else(sent_if);
+    reset_if.set_else(read_if);
+    always.add_seq(reset_if);
+    module.add_stmt(always);
+}
+
 fn incr_addr(
     module: &mut v::Module,
     mode_condition: v::Expr,
@@ -376,39 +726,66 @@ fn incr_addr(
     module.add_stmt(always);
 }
 
-pub fn bram(data_width: u64, size: u64, addr_width: u64) -> v::Module {
-    let mut module = v::Module::new("SINGLE_PORT_BRAM");
+/// The on-chip memory storing `memory`. Dual-port memories get a second
+/// port whose signals end in `_B`; when both ports write to the same address
+/// in the same cycle, the write from the second port takes effect.
+pub fn bram(name: &str, memory: &ExternalMemory) -> v::Module {
+    let data_width = memory.width;
+    let mut ports = vec![""];
+    if memory.dual {
+        ports.push("_B");
+    }
+
+    let mut module = v::Module::new(name);
     module.add_input("ACLK", 1);
-    module.add_input("ADDR", addr_width);
-    module.add_input("Din", data_width);
-    module.add_input("WE", 1);
-    module.add_output("Dout", data_width);
-    module.add_output("Done", 1);
+    for port in &ports {
+        module.add_input(&format!("ADDR{}", port), memory.idx_size);
+        module.add_input(&format!("Din{}", port), data_width);
+        module.add_input(&format!("WE{}", port), 1);
+        module.add_output(&format!("Dout{}", port), data_width);
+        module.add_output(&format!("Done{}", port), 1);
+    }
 
     let mut attr = v::Attribute::default();
     attr.add_stmt("ram_style", "block");
     module.add_decl(v::Decl::AttributeDecl(
         attr,
-        Rc::new(v::Decl::new_array("ram_core", data_width, size)),
+        Rc::new(v::Decl::new_array("ram_core", data_width, memory.size)),
     ));
 
-    module.add_stmt(super::utils::cond_non_blk_assign(
-        "ACLK",
-        v::Expr::new_index_expr("ram_core", "ADDR"),
-        vec![(Some("WE".into()), "Din".into())],
-    ));
+    // The writes of both ports are in the same process so that the second
+    // port takes priority.
+    let mut write = v::ParallelProcess::new_always();
+    write.set_event(v::Sequential::new_posedge("ACLK"));
+    for port in &ports {
+        let mut write_if = v::SequentialIfElse::new(format!("WE{}", port));
+        write_if.add_seq(v::Sequential::new_nonblk_assign(
+            v::Expr::new_index_expr("ram_core", format!("ADDR{}", port)),
+            format!("Din{}", port),
+        ));
+        write.add_seq(write_if);
+    }
+    module.add_stmt(write);
 
-    module.add_decl(v::Decl::new_reg("done_reg", 1));
-    module.add_stmt(super::utils::cond_non_blk_assign(
-        "ACLK",
-        "done_reg",
-        vec![(Some("WE".into()), 1.into()), (None, 0.into())],
-    ));
+    for port in &ports {
+        let we = format!("WE{}", port);
+        let done_reg = format!("done_reg{}", port.to_lowercase());
+        module.add_decl(v::Decl::new_reg(&done_reg, 1));
+        module.add_stmt(super::utils::cond_non_blk_assign(
+            "ACLK",
+            done_reg.as_str(),
+            vec![(Some(we.as_str().into()), 1.into()), (None, 0.into())],
+        ));
+        module.add_stmt(v::Parallel::Assign(
+            format!("Done{}", port).as_str().into(),
+            done_reg.as_str().into(),
+        ));
 
-    module.add_stmt(v::Parallel::Assign(
-        "Dout".into(),
-        v::Expr::new_index_expr("ram_core", "ADDR"),
-    ));
+        module.add_stmt(v::Parallel::Assign(
+            format!("Dout{}", port).as_str().into(),
+            v::Expr::new_index_expr("ram_core", format!("ADDR{}", port)),
+        ));
+    }
 
     module
 }
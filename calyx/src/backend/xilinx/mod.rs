@@ -1,12 +1,19 @@
-//! Backend for generating synthesiable code for Xilinx FPGAs
+//! Backend for generating synthesiable code for Xilinx FPGAs and standalone
+//! AXI IP.
 mod axi;
 mod axi_address_space;
+mod axi_wrapper;
 mod control_axi;
 mod fsm;
 mod memory_axi;
+mod params;
+mod register_map;
 mod toplevel;
 mod utils;
 mod xml;
 
+pub use axi_wrapper::{
+    AxiWrapperBackend, AxiWrapperHeaderBackend, AxiWrapperJsonBackend,
+};
 pub use toplevel::XilinxInterfaceBackend;
 pub use xml::XilinxXmlBackend;
@@ -0,0 +1,426 @@
+//! Parameters of the Xilinx interface derived from the toplevel component.
+use crate::{
+    errors::{Error, FutilResult},
+    ir,
+};
+
+/// Width of the data bus of the AXI-Lite control interface. Xilinx requires
+/// the control interface of kernels to be 32 bits wide.
+pub(crate) const CONTROL_DATA_WIDTH: u64 = 32;
+
+/// Divide `value` by `div`, rounding up.
+pub(crate) fn div_ceil(value: u64, div: u64) -> u64 {
+    value / div + u64::from(value % div != 0)
+}
+
+/// Widths of the AXI interfaces of the kernel.
+/// The defaults can be overridden using attributes on the toplevel component:
+/// ```text
+/// component main<"control_addr_width"=12, "bus_data_width"=512, "bus_addr_width"=64>() -> () { ... }
+/// ```
+pub(crate) struct AxiConfig {
+    /// Width of the address bus of the AXI-Lite control interface.
+    pub control_addr_width: u64,
+    /// Width of the data bus of the AXI memory interfaces.
+    pub bus_data_width: u64,
+    /// Width of the address bus of the AXI memory interfaces.
+    pub bus_addr_width: u64,
+}
+
+impl AxiConfig {
+    fn from_attributes(comp: &ir::Component) -> FutilResult<Self> {
+        let get = |attr: &str, default: u64| {
+            comp.attributes.get(attr).copied().unwrap_or(default)
+        };
+        let config = AxiConfig {
+            control_addr_width: get("control_addr_width", 12),
+            bus_data_width: get("bus_data_width", 512),
+            bus_addr_width: get("bus_addr_width", 64),
+        };
+        if !config.bus_data_width.is_power_of_two()
+            || !(32..=1024).contains(&config.bus_data_width)
+        {
+            return Err(Error::Misc(format!(
+                "bus_data_width must be a power of two between 32 and 1024, found {}",
+                config.bus_data_width
+            )));
+        }
+        if config.control_addr_width == 0 || config.control_addr_width > 32 {
+            return Err(Error::Misc(format!(
+                "control_addr_width must be between 1 and 32, found {}",
+                config.control_addr_width
+            )));
+        }
+        if config.bus_addr_width == 0 || config.bus_addr_width > 64 {
+            return Err(Error::Misc(format!(
+                "bus_addr_width must be between 1 and 64, found {}",
+                config.bus_addr_width
+            )));
+        }
+        Ok(config)
+    }
+
+    /// Number of control registers used to store the base address of a
+    /// memory.
+    pub fn base_addr_registers(&self) -> u64 {
+        div_ceil(self.bus_addr_width, CONTROL_DATA_WIDTH)
+    }
+
+    /// Number of bytes in the control address space used by the base address
+    /// of a memory.
+    pub fn base_addr_bytes(&self) -> u64 {
+        self.base_addr_registers() * (CONTROL_DATA_WIDTH / 8)
+    }
+}
+
+/// Conventions followed by the generated interface.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Convention {
+    /// Kernels for the Vitis flow. The control interface follows the
+    /// `ap_ctrl_hs` protocol and has a timeout register.
+    Vitis,
+    /// Standalone IP for any SoC with an AXI-Lite control interface.
+    Generic,
+}
+
+impl Convention {
+    /// Offset of the first memory base address in the control interface.
+    fn registers_start(&self) -> u64 {
+        match self {
+            Convention::Vitis => 0x18,
+            Convention::Generic => 0x10,
+        }
+    }
+
+    /// Name of the clock port of the toplevel module.
+    pub fn clock(&self) -> &'static str {
+        match self {
+            Convention::Vitis => "ap_clk",
+            Convention::Generic => "ACLK",
+        }
+    }
+
+    /// Name of the active-low reset port of the toplevel module.
+    pub fn reset_n(&self) -> &'static str {
+        match self {
+            Convention::Vitis => "ap_rst_n",
+            Convention::Generic => "ARESETn",
+        }
+    }
+
+    /// Name of the signal that starts the kernel.
+    pub fn start(&self) -> &'static str {
+        match self {
+            Convention::Vitis => "ap_start",
+            Convention::Generic => "start",
+        }
+    }
+
+    /// Name of the signal that is high when the kernel is done.
+    pub fn done(&self) -> &'static str {
+        match self {
+            Convention::Vitis => "ap_done",
+            Convention::Generic => "done",
+        }
+    }
+}
+
+/// An `@external` memory in the toplevel component.
+pub(crate) struct ExternalMemory {
+    /// Name of the memory cell.
+    pub name: String,
+    /// Width of each element.
+    pub width: u64,
+    /// Number of elements.
+    pub size: u64,
+    /// Width of the address port.
+    pub idx_size: u64,
+    /// True for a `seq_mem_d1` or `dp_mem_d1`, which have registered reads
+    /// requested with `read_en`.
+    pub seq: bool,
+    /// True for a `dp_mem_d1`, which has a second port whose signals end in
+    /// `_b`.
+    pub dual: bool,
+}
+
+impl ExternalMemory {
+    /// Number of bytes used to store each element in host memory. Elements
+    /// are stored in the smallest power of two number of bytes that fits
+    /// them.
+    pub fn bytes(&self) -> u64 {
+        div_ceil(self.width, 8).next_power_of_two()
+    }
+
+    /// Width of the bus lane used for each element.
+    pub fn lane_width(&self) -> u64 {
+        self.bytes() * 8
+    }
+}
+
+/// An input port of the toplevel component marked with `@arg`. Its value is
+/// provided by the host through a register in the control interface.
+pub(crate) struct ScalarArg {
+    /// Name of the port.
+    pub name: String,
+    /// Width of the port.
+    pub width: u64,
+}
+
+impl ScalarArg {
+    /// Number of control registers used to store the argument.
+    pub fn registers(&self) -> u64 {
+        div_ceil(self.width, CONTROL_DATA_WIDTH)
+    }
+
+    /// Number of bytes used by the argument on the host.
+    pub fn bytes(&self) -> u64 {
+        self.registers() * (CONTROL_DATA_WIDTH / 8)
+    }
+}
+
+/// A data port of the toplevel component marked with `@stream`, exposed as
+/// an AXI4-Stream interface. The handshake uses the ports `{name}_valid` and
+/// `{name}_ready` and the optional `{name}_last` port marks the end of a
+/// packet.
+pub(crate) struct StreamPort {
+    /// Name of the data port.
+    pub name: String,
+    /// Width of the data port.
+    pub width: u64,
+    /// True if data flows into the kernel.
+    pub input: bool,
+    /// True if the component has a `{name}_last` port.
+    pub last: bool,
+}
+
+impl StreamPort {
+    /// Width of `TDATA`, which must be a whole number of bytes.
+    pub fn tdata_width(&self) -> u64 {
+        div_ceil(self.width, 8) * 8
+    }
+
+    /// Name of the AXI4-Stream interface.
+    pub fn interface(&self) -> String {
+        if self.input {
+            format!("s_axis_{}", self.name)
+        } else {
+            format!("m_axis_{}", self.name)
+        }
+    }
+}
+
+/// The toplevel component and the parameters derived from it.
+pub(crate) struct Kernel<'a> {
+    pub toplevel: &'a ir::Component,
+    pub convention: Convention,
+    pub config: AxiConfig,
+    pub memories: Vec<ExternalMemory>,
+    pub args: Vec<ScalarArg>,
+    pub streams: Vec<StreamPort>,
+}
+
+impl<'a> Kernel<'a> {
+    pub fn new(
+        prog: &'a ir::Context,
+        convention: Convention,
+    ) -> FutilResult<Self> {
+        let toplevel = prog
+            .components
+            .iter()
+            .find(|comp| comp.attributes.has("toplevel") || comp.name == "main")
+            .ok_or_else(|| Error::Misc("no toplevel".to_string()))?;
+        let config = AxiConfig::from_attributes(toplevel)?;
+        let memories = toplevel
+            .cells
+            .iter()
+            .filter(|cell_ref| {
+                matches!(cell_ref.borrow().get_attribute("external"), Some(&1))
+            })
+            .map(|cell_ref| external_memory(&cell_ref.borrow(), &config))
+            .collect::<FutilResult<Vec<_>>>()?;
+        let args = toplevel
+            .signature
+            .borrow()
+            .ports
+            .iter()
+            .filter(|port| port.borrow().attributes.has("arg"))
+            .map(|port| scalar_arg(&port.borrow()))
+            .collect::<FutilResult<Vec<_>>>()?;
+        let sig = toplevel.signature.borrow();
+        let streams = sig
+            .ports
+            .iter()
+            .filter(|port| port.borrow().attributes.has("stream"))
+            .map(|port| stream_port(&sig, &port.borrow()))
+            .collect::<FutilResult<Vec<_>>>()?;
+        drop(sig);
+        let kernel = Kernel {
+            toplevel,
+            convention,
+            config,
+            memories,
+            args,
+            streams,
+        };
+        let control_bytes = kernel.arg_offset(kernel.args.len());
+        if control_bytes > 1 << kernel.config.control_addr_width {
+            return Err(Error::Misc(format!(
+                "The control interface needs {:#x} bytes of address space but control_addr_width is {}",
+                control_bytes, kernel.config.control_addr_width
+            )));
+        }
+        Ok(kernel)
+    }
+
+    /// Name of the module `base` of the interface. Modules of generic IP are
+    /// prefixed with the name of the kernel to avoid clashes in the SoC.
+    pub fn module_name(&self, base: &str) -> String {
+        match self.convention {
+            Convention::Vitis => base.to_string(),
+            Convention::Generic => format!("{}_{}", self.toplevel.name, base),
+        }
+    }
+
+    /// Offset in the control address space of the base address of the
+    /// `idx`th memory.
+    pub fn base_addr_offset(&self, idx: usize) -> u64 {
+        self.convention.registers_start()
+            + idx as u64 * self.config.base_addr_bytes()
+    }
+
+    /// Offset in the control address space of the `idx`th scalar argument,
+    /// or the end of the control address space when `idx` is the number of
+    /// arguments. Scalar arguments are placed after the base addresses of
+    /// the memories and are aligned to their size.
+    pub fn arg_offset(&self, idx: usize) -> u64 {
+        let mut offset = self.base_addr_offset(self.memories.len());
+        for arg in &self.args[..idx] {
+            offset = round_up(offset, arg.bytes()) + arg.bytes();
+        }
+        match self.args.get(idx) {
+            Some(arg) => round_up(offset, arg.bytes()),
+            None => offset,
+        }
+    }
+}
+
+/// Round `value` up to the next multiple of `align`.
+fn round_up(value: u64, align: u64) -> u64 {
+    div_ceil(value, align) * align
+}
+
+/// Read a scalar argument from a port of the toplevel signature.
+fn scalar_arg(port: &ir::Port) -> FutilResult<ScalarArg> {
+    // Inputs of the component are outputs of its signature cell.
+    if port.direction != ir::Direction::Output {
+        return Err(Error::Misc(format!(
+            "Port `{}` is marked with @arg but is not an input of the toplevel component",
+            port.name
+        )));
+    }
+    if port.width > 64 {
+        return Err(Error::Misc(format!(
+            "Argument `{}` has width {} but scalar arguments can be at most 64 bits wide",
+            port.name, port.width
+        )));
+    }
+    Ok(ScalarArg {
+        name: port.name.to_string(),
+        width: port.width,
+    })
+}
+
+/// Read the parameters of an `@external` memory.
+fn external_memory(
+    cell: &ir::Cell,
+    config: &AxiConfig,
+) -> FutilResult<ExternalMemory> {
+    let typ = cell.type_name().map(|name| name.to_string());
+    let (seq, dual) = match typ.as_deref() {
+        Some("std_mem_d1") => (false, false),
+        Some("seq_mem_d1") => (true, false),
+        Some("dp_mem_d1") => (true, true),
+        _ => {
+            return Err(Error::Misc(format!(
+                "External memory `{}` has type {}. The Xilinx interface only supports std_mem_d1, seq_mem_d1, and dp_mem_d1.",
+                cell.name(),
+                typ.unwrap_or_else(|| "constant".to_string())
+            )))
+        }
+    };
+    let param = |name: &str| {
+        cell.get_parameter(name).ok_or_else(|| {
+            Error::Impossible(format!(
+                "{} `{}` has no parameter {}",
+                typ.as_deref().unwrap_or_default(),
+                cell.name(),
+                name
+            ))
+        })
+    };
+    let mem = ExternalMemory {
+        name: cell.name().to_string(),
+        width: param("WIDTH")?,
+        size: param("SIZE")?,
+        idx_size: param("IDX_SIZE")?,
+        seq,
+        dual,
+    };
+    if mem.lane_width() > config.bus_data_width {
+        return Err(Error::Misc(format!(
+            "External memory `{}` has width {} which is wider than the bus ({} bits)",
+            mem.name, mem.width, config.bus_data_width
+        )));
+    }
+    Ok(mem)
+}
+
+/// Read a stream from a data port of the toplevel signature and check that
+/// its handshake ports exist.
+fn stream_port(sig: &ir::Cell, port: &ir::Port) -> FutilResult<StreamPort> {
+    // Inputs of the component are outputs of its signature cell. `valid` and
+    // `last` flow in the same direction as the data and `ready` in the
+    // opposite direction.
+    let input = port.direction == ir::Direction::Output;
+    let with_data = port.direction.clone();
+    let against_data = port.direction.reverse();
+    let control = |suffix: &str, direction: &ir::Direction| {
+        let name = format!("{}_{}", port.name, suffix);
+        match sig.find(&name) {
+            Some(p) => {
+                let p = p.borrow();
+                if p.width == 1 && &p.direction == direction {
+                    Ok(true)
+                } else {
+                    Err(Error::Misc(format!(
+                        "Stream `{}` requires `{}` to be a 1-bit {} port",
+                        port.name,
+                        name,
+                        if direction == &ir::Direction::Output {
+                            "input"
+                        } else {
+                            "output"
+                        }
+                    )))
+                }
+            }
+            None => Ok(false),
+        }
+    };
+    for (suffix, direction) in
+        &[("valid", &with_data), ("ready", &against_data)]
+    {
+        if !control(suffix, direction)? {
+            return Err(Error::Misc(format!(
+                "Stream `{}` requires a `{}_{}` port",
+                port.name, port.name, suffix
+            )));
+        }
+    }
+    Ok(StreamPort {
+        name: port.name.to_string(),
+        width: port.width,
+        input,
+        last: control("last", &with_data)?,
+    })
+}
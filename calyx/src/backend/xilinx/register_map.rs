@@ -0,0 +1,438 @@
+//! Register map of the AXI-Lite control interface.
+//!
+//! The register map describes every register of the control interface. It is
+//! used to generate the control module and to document the interface for
+//! software using C headers and JSON.
+use super::axi::AxiInterface;
+use super::axi_address_space::{AddressSpace, Flags};
+use super::params::{div_ceil, Convention, Kernel, CONTROL_DATA_WIDTH};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// How the hardware interacts with a field.
+#[derive(Clone, Debug)]
+pub(crate) enum Behavior {
+    /// Written by the host. When `cleared_by` is set, the field is cleared
+    /// when the input with that name is high.
+    Write { cleared_by: Option<String> },
+    /// Set when the input `set_by` is high and cleared when the register is
+    /// read.
+    Status { set_by: String },
+}
+
+/// A range of bits in a register.
+#[derive(Serialize)]
+pub(crate) struct Field {
+    /// Name of the field.
+    pub name: String,
+    /// Least significant bit of the field in the register.
+    pub lsb: u64,
+    /// Width of the field.
+    pub width: u64,
+    /// Either `rw` or `ro`.
+    pub access: &'static str,
+    /// Documentation for the field.
+    pub description: String,
+    /// Internal register storing the field.
+    #[serde(skip)]
+    pub register: String,
+    /// Bit of the internal register stored in `lsb`.
+    #[serde(skip)]
+    pub register_lsb: u64,
+    #[serde(skip)]
+    pub behavior: Behavior,
+    /// Output port of the control module exposing the internal register.
+    #[serde(skip)]
+    pub port: Option<String>,
+}
+
+impl Field {
+    /// A field written by the host stored in `register`.
+    fn new<S: ToString>(
+        name: S,
+        lsb: u64,
+        width: u64,
+        register: S,
+        description: S,
+    ) -> Self {
+        Field {
+            name: name.to_string(),
+            lsb,
+            width,
+            access: "rw",
+            description: description.to_string(),
+            register: register.to_string(),
+            register_lsb: 0,
+            behavior: Behavior::Write { cleared_by: None },
+            port: None,
+        }
+    }
+
+    /// Builder style method setting the bit of the internal register stored
+    /// in the least significant bit of this field.
+    fn register_lsb(mut self, lsb: u64) -> Self {
+        self.register_lsb = lsb;
+        self
+    }
+
+    /// Builder style method exposing the internal register on `port`.
+    fn port<S: ToString>(mut self, port: S) -> Self {
+        self.port = Some(port.to_string());
+        self
+    }
+
+    /// Builder style method clearing this field when `input` is high.
+    fn cleared_by<S: ToString>(mut self, input: S) -> Self {
+        self.behavior = Behavior::Write {
+            cleared_by: Some(input.to_string()),
+        };
+        self
+    }
+
+    /// Builder style method making this a read-only field that is set by
+    /// `input` and cleared on read.
+    fn status<S: ToString>(mut self, input: S) -> Self {
+        self.behavior = Behavior::Status {
+            set_by: input.to_string(),
+        };
+        self.access = "ro";
+        self
+    }
+}
+
+/// A register in the control interface.
+#[derive(Serialize)]
+pub(crate) struct Register {
+    /// Name of the register.
+    pub name: String,
+    /// Byte offset of the register.
+    pub offset: u64,
+    /// Fields of the register.
+    pub fields: Vec<Field>,
+}
+
+impl Register {
+    fn new<S: ToString>(name: S, offset: u64, fields: Vec<Field>) -> Self {
+        Register {
+            name: name.to_string(),
+            offset,
+            fields,
+        }
+    }
+}
+
+/// The registers of a control interface.
+#[derive(Serialize)]
+pub(crate) struct RegisterMap {
+    /// Name of the kernel.
+    pub name: String,
+    /// Width of the address bus of the control interface.
+    pub address_width: u64,
+    /// Width of the data bus of the control interface.
+    pub data_width: u64,
+    /// The registers ordered by offset.
+    pub registers: Vec<Register>,
+}
+
+impl RegisterMap {
+    /// The register map of the control interface of `kernel`.
+    pub fn new(kernel: &Kernel) -> Self {
+        let mut registers = match kernel.convention {
+            Convention::Vitis => vec![
+                Register::new(
+                    "AP_CONTROL",
+                    0x0,
+                    vec![
+                        Field::new(
+                            "AP_START",
+                            0,
+                            1,
+                            "int_ap_start",
+                            "Start the kernel. Cleared when the kernel is done.",
+                        )
+                        .port("ap_start")
+                        .cleared_by("ap_done"),
+                        Field::new(
+                            "AP_DONE",
+                            1,
+                            1,
+                            "int_ap_done",
+                            "The kernel is done. Cleared on read.",
+                        )
+                        .status("ap_done"),
+                    ],
+                ),
+                Register::new(
+                    "GIE",
+                    0x4,
+                    vec![Field::new(
+                        "GIE",
+                        0,
+                        1,
+                        "int_gie",
+                        "Global interrupt enable.",
+                    )],
+                ),
+                Register::new(
+                    "IER",
+                    0x8,
+                    vec![Field::new(
+                        "IER",
+                        0,
+                        2,
+                        "int_ier",
+                        "Interrupt enable.",
+                    )],
+                ),
+                Register::new(
+                    "ISR",
+                    0xc,
+                    vec![
+                        Field::new(
+                            "ISR_DONE",
+                            0,
+                            1,
+                            "int_isr_done",
+                            "Done interrupt status.",
+                        ),
+                        Field::new(
+                            "ISR_READY",
+                            1,
+                            1,
+                            "int_isr_ready",
+                            "Ready interrupt status.",
+                        ),
+                    ],
+                ),
+                Register::new(
+                    "TIMEOUT",
+                    0x10,
+                    vec![Field::new(
+                        "TIMEOUT",
+                        0,
+                        32,
+                        "int_timeout",
+                        "Number of cycles after which the kernel is stopped.",
+                    )
+                    .port("timeout")],
+                ),
+            ],
+            Convention::Generic => vec![Register::new(
+                "CTRL",
+                0x0,
+                vec![
+                    Field::new(
+                        "START",
+                        0,
+                        1,
+                        "int_start",
+                        "Write 1 to start the kernel. Cleared when the kernel is done.",
+                    )
+                    .port("start")
+                    .cleared_by("done"),
+                    Field::new(
+                        "DONE",
+                        1,
+                        1,
+                        "int_done",
+                        "The kernel is done. Cleared on read.",
+                    )
+                    .status("done"),
+                ],
+            )],
+        };
+
+        // the base address of each memory and the scalar arguments are split
+        // across several registers.
+        for (idx, memory) in kernel.memories.iter().enumerate() {
+            registers.extend(wide_register(
+                &format!("{}_ADDR", memory.name),
+                kernel.base_addr_offset(idx),
+                &format!("addr_{}", memory.name),
+                &memory.name,
+                kernel.config.bus_addr_width,
+                &format!("Base address of the memory `{}`", memory.name),
+            ));
+        }
+        for (idx, arg) in kernel.args.iter().enumerate() {
+            registers.extend(wide_register(
+                &format!("{}_ARG", arg.name),
+                kernel.arg_offset(idx),
+                &format!("int_arg_{}", arg.name),
+                &format!("arg_{}", arg.name),
+                arg.width,
+                &format!("Value of the argument `{}`", arg.name),
+            ));
+        }
+
+        RegisterMap {
+            name: kernel.toplevel.name.to_string(),
+            address_width: kernel.config.control_addr_width,
+            data_width: CONTROL_DATA_WIDTH,
+            registers,
+        }
+    }
+
+    /// The address space implementing this register map.
+    pub fn address_space(&self, axi: &AxiInterface) -> AddressSpace {
+        let mut space = AddressSpace::new(self.address_width, self.data_width);
+        for reg in &self.registers {
+            let meanings = reg
+                .fields
+                .iter()
+                .map(|field| {
+                    let flags = match &field.behavior {
+                        Behavior::Write { cleared_by: None } => {
+                            Flags::default().write()
+                        }
+                        Behavior::Write {
+                            cleared_by: Some(input),
+                        } => Flags::default().write().clear_on_handshake(input),
+                        Behavior::Status { set_by } => Flags::default()
+                            .read(set_by)
+                            .clear_on_read(axi.read_data.clone(), "raddr"),
+                    };
+                    let (lsb, reg_lsb) =
+                        (field.lsb as usize, field.register_lsb as usize);
+                    let width = field.width as usize;
+                    (
+                        lsb..lsb + width,
+                        field.register.as_str(),
+                        reg_lsb..reg_lsb + width,
+                        flags,
+                    )
+                })
+                .collect();
+            space.add_address(reg.offset as usize, &reg.name, meanings);
+        }
+        space
+    }
+
+    /// The internal registers exposed on output ports as tuples of the
+    /// port, the internal register, and its width.
+    pub fn outputs(&self) -> Vec<(&str, &str, u64)> {
+        let mut outputs: Vec<(&str, &str, u64)> = vec![];
+        for field in self.fields() {
+            if let Some(port) = &field.port {
+                let width = field.register_lsb + field.width;
+                match outputs.iter_mut().find(|(p, _, _)| p == port) {
+                    Some((_, _, w)) => *w = (*w).max(width),
+                    None => outputs.push((port, &field.register, width)),
+                }
+            }
+        }
+        outputs
+    }
+
+    /// The 1-bit inputs used to set or clear fields.
+    pub fn inputs(&self) -> Vec<&str> {
+        let mut inputs: Vec<&str> = vec![];
+        for field in self.fields() {
+            let input = match &field.behavior {
+                Behavior::Write { cleared_by } => cleared_by.as_deref(),
+                Behavior::Status { set_by } => Some(set_by.as_str()),
+            };
+            if let Some(input) = input {
+                if !inputs.contains(&input) {
+                    inputs.push(input);
+                }
+            }
+        }
+        inputs
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.registers.iter().flat_map(|reg| reg.fields.iter())
+    }
+
+    /// A C header defining the offset of every register and the position of
+    /// its fields.
+    pub fn c_header(&self) -> String {
+        let prefix = c_identifier(&self.name);
+        let mut out = String::new();
+        writeln!(
+            out,
+            "// Register map of the `{}` AXI-Lite control interface.",
+            self.name
+        )
+        .unwrap();
+        writeln!(out, "// Generated by the Calyx compiler.").unwrap();
+        writeln!(out, "#ifndef {}_REGS_H", prefix).unwrap();
+        writeln!(out, "#define {}_REGS_H", prefix).unwrap();
+        for reg in &self.registers {
+            let reg_name = format!("{}_{}", prefix, c_identifier(&reg.name));
+            writeln!(out).unwrap();
+            writeln!(out, "#define {} {:#04x}", reg_name, reg.offset).unwrap();
+            for field in &reg.fields {
+                let field_name =
+                    format!("{}_{}", reg_name, c_identifier(&field.name));
+                let mask = (((1u64 << field.width) - 1) << field.lsb) as u32;
+                writeln!(out, "// {} ({})", field.description, field.access)
+                    .unwrap();
+                writeln!(out, "#define {}_SHIFT {}", field_name, field.lsb)
+                    .unwrap();
+                writeln!(out, "#define {}_MASK {:#010x}", field_name, mask)
+                    .unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "#endif // {}_REGS_H", prefix).unwrap();
+        out
+    }
+}
+
+/// Registers called `{name}_{part}` storing the `width` bit internal register
+/// `register` starting at `offset`. Registers wider than the data bus are
+/// split across consecutive addresses.
+fn wide_register(
+    name: &str,
+    offset: u64,
+    register: &str,
+    port: &str,
+    width: u64,
+    description: &str,
+) -> Vec<Register> {
+    let parts = div_ceil(width, CONTROL_DATA_WIDTH);
+    (0..parts)
+        .map(|part| {
+            let lsb = part * CONTROL_DATA_WIDTH;
+            let field_width = CONTROL_DATA_WIDTH.min(width - lsb);
+            let description = if parts == 1 {
+                format!("{}.", description)
+            } else {
+                format!(
+                    "{} (bits {} to {}).",
+                    description,
+                    lsb,
+                    lsb + field_width - 1
+                )
+            };
+            Register::new(
+                format!("{}_{}", name, part),
+                offset + part * CONTROL_DATA_WIDTH / 8,
+                vec![Field::new(
+                    "VALUE".to_string(),
+                    0,
+                    field_width,
+                    register.to_string(),
+                    description,
+                )
+                .register_lsb(lsb)
+                .port(port)],
+            )
+        })
+        .collect()
+}
+
+/// Convert `name` into an upper case C identifier.
+fn c_identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -1,12 +1,16 @@
 use super::{
-    axi, control_axi::ControlInterface, fsm, memory_axi::bram,
-    memory_axi::MemoryInterface, utils,
-};
-use crate::{
-    backend::traits::Backend,
-    errors::{Error, FutilResult},
-    ir,
+    axi,
+    control_axi::ControlInterface,
+    fsm,
+    memory_axi::bram,
+    memory_axi::MemoryInterface,
+    params::{
+        Convention, ExternalMemory, Kernel, StreamPort, CONTROL_DATA_WIDTH,
+    },
+    register_map::RegisterMap,
+    utils,
 };
+use crate::{backend::traits::Backend, errors::FutilResult, ir};
 use vast::v05::ast as v;
 
 /// A backend that generates the Xilinx interfacing for a Calyx program.
@@ -33,67 +37,79 @@ impl Backend for XilinxInterfaceBackend {
         prog: &ir::Context,
         file: &mut crate::utils::OutputFile,
     ) -> FutilResult<()> {
-        let toplevel = prog
-            .components
-            .iter()
-            .find(|comp| comp.attributes.has("toplevel") || comp.name == "main")
-            .ok_or_else(|| Error::Misc("no toplevel".to_string()))?;
-        let memories = external_memories(toplevel);
+        let kernel = Kernel::new(prog, Convention::Vitis)?;
+        write_modules(file, interface_modules(&kernel))
+    }
+}
 
-        let mut modules = vec![
-            top_level(12, 32, &memories),
-            bram(32, 32, 5),
-            axi::AxiInterface::control_module("Control_axi", 12, 32, &memories),
-        ];
+/// All the modules implementing the interface of `kernel`.
+pub(crate) fn interface_modules(kernel: &Kernel) -> Vec<v::Module> {
+    let config = &kernel.config;
+    let mut modules = vec![
+        top_level(kernel),
+        axi::AxiInterface::control_module(
+            &kernel.module_name("Control_axi"),
+            &RegisterMap::new(kernel),
+        ),
+    ];
 
-        for (i, _mem) in memories.iter().enumerate() {
-            modules.push(axi::AxiInterface::memory_module(
-                &format!("Memory_controller_axi_{}", i),
-                512,
-                64,
-                32,
-            ))
-        }
+    for (i, mem) in kernel.memories.iter().enumerate() {
+        let bram_name = if mem.dual {
+            kernel.module_name(&format!("DUAL_PORT_BRAM_{}", i))
+        } else {
+            kernel.module_name(&format!("SINGLE_PORT_BRAM_{}", i))
+        };
+        modules.push(bram(&bram_name, mem));
+        modules.push(axi::AxiInterface::memory_module(
+            &kernel.module_name(&format!("Memory_controller_axi_{}", i)),
+            &bram_name,
+            config.bus_data_width,
+            config.bus_addr_width,
+            mem,
+        ))
+    }
+    modules
+}
 
-        let module_string = modules
-            .into_iter()
-            .map(|x| x.to_string())
-            .collect::<Vec<String>>()
-            .join("\n");
+/// Write `modules` to `file` as a single Verilog file.
+pub(crate) fn write_modules(
+    file: &mut crate::utils::OutputFile,
+    modules: Vec<v::Module>,
+) -> FutilResult<()> {
+    let module_string = modules
+        .into_iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
 
-        write!(
-            file.get_write(),
-            r#"`default_nettype none
+    write!(
+        file.get_write(),
+        r#"`default_nettype none
 /* verilator lint_off DECLFILENAME */
 {}`default_nettype wire"#,
-            module_string,
-        )?;
-
-        Ok(())
-    }
-}
+        module_string,
+    )?;
 
-fn external_memories(comp: &ir::Component) -> Vec<String> {
-    // find external memories
-    comp.cells
-        .iter()
-        .filter(|cell_ref| {
-            matches!(cell_ref.borrow().get_attribute("external"), Some(&1))
-        })
-        .map(|cell_ref| cell_ref.borrow().name().to_string())
-        .collect()
+    Ok(())
 }
 
-fn top_level(
-    address_width: u64,
-    data_width: u64,
-    memories: &[String],
-) -> v::Module {
-    let mut module = v::Module::new("Toplevel");
+fn top_level(kernel: &Kernel) -> v::Module {
+    let config = &kernel.config;
+    let memories = &kernel.memories;
+    let address_width = config.control_addr_width;
+    let data_width = CONTROL_DATA_WIDTH;
+    let convention = kernel.convention;
+    let (clk, start, done) =
+        (convention.clock(), convention.start(), convention.done());
+    let name = match convention {
+        Convention::Vitis => "Toplevel".to_string(),
+        Convention::Generic => kernel.module_name("axi"),
+    };
+    let mut module = v::Module::new(&name);
 
     // add system signals
-    module.add_input("ap_clk", 1);
-    module.add_input("ap_rst_n", 1);
+    module.add_input(clk, 1);
+    module.add_input(convention.reset_n(), 1);
     // module.add_output("ap_interrupt", 1);
 
     // axi control signals
@@ -106,38 +122,78 @@ fn top_level(
 
     // add an axi interface for each external memory
     for (idx, _mem) in memories.iter().enumerate() {
-        axi::AxiInterface::memory_channels(64, 512, &format!("m{}_axi_", idx))
-            .add_ports_to(&mut module);
+        axi::AxiInterface::memory_channels(
+            config.bus_addr_width,
+            config.bus_data_width,
+            &format!("m{}_axi_", idx),
+        )
+        .add_ports_to(&mut module);
     }
 
+    // add an axi stream interface for each stream
+    let streams: Vec<(&StreamPort, axi::AxiChannel)> = kernel
+        .streams
+        .iter()
+        .map(|stream| {
+            let direction = if stream.input {
+                axi::ChannelDirection::Recv
+            } else {
+                axi::ChannelDirection::Send
+            };
+            let channel = axi::AxiChannel::stream(
+                &format!("{}_", stream.interface()),
+                direction,
+                stream.tdata_width(),
+            );
+            channel.add_ports_to(&mut module);
+            (stream, channel)
+        })
+        .collect();
+
     // wires
-    module.add_stmt(v::Decl::new_wire("ap_start", 1));
-    module.add_stmt(v::Decl::new_wire("ap_done", 1));
-    module.add_stmt(v::Decl::new_wire("timeout", 32));
+    module.add_stmt(v::Decl::new_wire(start, 1));
+    module.add_stmt(v::Decl::new_wire(done, 1));
+    if convention == Convention::Vitis {
+        module.add_stmt(v::Decl::new_wire("timeout", 32));
+    }
     for mem in memories {
-        module.add_stmt(v::Decl::new_wire(mem, 64));
+        module.add_stmt(v::Decl::new_wire(&mem.name, config.bus_addr_width));
+    }
+    for arg in &kernel.args {
+        module.add_stmt(v::Decl::new_wire(
+            &format!("arg_{}", arg.name),
+            arg.width,
+        ));
     }
 
     // reset
     module.add_stmt(v::Decl::new_wire("reset", 1));
     module.add_stmt(v::Parallel::Assign(
         "reset".into(),
-        v::Expr::new_not("ap_rst_n"),
+        v::Expr::new_not(convention.reset_n()),
     ));
 
     // instantiate control interface
     let base_control_axi_interface =
         axi::AxiInterface::control_channels(address_width, data_width, "");
-    let mut control_instance =
-        v::Instance::new("inst_control_axi", "Control_axi");
-    control_instance.connect("ACLK", "ap_clk");
+    let mut control_instance = v::Instance::new(
+        "inst_control_axi",
+        &kernel.module_name("Control_axi"),
+    );
+    control_instance.connect("ACLK", clk);
     control_instance.connect("ARESET", "reset");
     for mem in memories {
-        control_instance.connect_ref(mem, mem);
+        control_instance.connect_ref(&mem.name, &mem.name);
+    }
+    for arg in &kernel.args {
+        let arg_name = format!("arg_{}", arg.name);
+        control_instance.connect_ref(&arg_name, &arg_name);
+    }
+    control_instance.connect(start, start);
+    control_instance.connect(done, done);
+    if convention == Convention::Vitis {
+        control_instance.connect("timeout", "timeout");
     }
-    control_instance.connect("ap_start", "ap_start");
-    control_instance.connect("ap_done", "ap_done");
-    control_instance.connect("timeout", "timeout");
 
     for port in base_control_axi_interface.ports() {
         control_instance.connect_ref(&port, &format!("s_axi_control_{}", port));
@@ -145,34 +201,27 @@ fn top_level(
     module.add_instance(control_instance);
 
     // and some wires for each memory
-    for mem in memories {
+    for ExternalMemory { name: mem, .. } in memories {
         module.add_decl(v::Decl::new_wire(&format!("{}_copy", mem), 1));
         module.add_decl(v::Decl::new_wire(&format!("{}_copy_done", mem), 1));
         module.add_decl(v::Decl::new_wire(&format!("{}_send", mem), 1));
         module.add_decl(v::Decl::new_wire(&format!("{}_send_done", mem), 1));
     }
-    host_transfer_fsm(&mut module, memories);
+    host_transfer_fsm(&mut module, memories, clk, start);
 
     // instantiate memory controllers
-    let base_master_axi_interface =
-        axi::AxiInterface::memory_channels(64, 32, "");
-    for (idx, mem) in memories.iter().enumerate() {
-        let write_data = format!("{}_write_data", mem);
-        let read_data = format!("{}_read_data", mem);
-        let addr0 = format!("{}_addr0", mem);
-        let write_en = format!("{}_write_en", mem);
-        let done = format!("{}_done", mem);
-        module.add_decl(v::Decl::new_wire(&write_data, data_width));
-        module.add_decl(v::Decl::new_wire(&read_data, data_width));
-        module.add_decl(v::Decl::new_wire(&addr0, 5));
-        module.add_decl(v::Decl::new_wire(&write_en, 1));
-        module.add_decl(v::Decl::new_wire(&done, 1));
-
+    let base_master_axi_interface = axi::AxiInterface::memory_channels(
+        config.bus_addr_width,
+        config.bus_data_width,
+        "",
+    );
+    for (idx, memory) in memories.iter().enumerate() {
+        let mem = &memory.name;
         let mut memory_instance = v::Instance::new(
             &format!("inst_mem_controller_axi_{}", idx),
-            &format!("Memory_controller_axi_{}", idx),
+            &kernel.module_name(&format!("Memory_controller_axi_{}", idx)),
         );
-        memory_instance.connect("ACLK", "ap_clk");
+        memory_instance.connect("ACLK", clk);
         memory_instance.connect(
             "ARESET",
             v::Expr::new_logical_or("reset", "memories_sent"),
@@ -189,75 +238,142 @@ fn top_level(
         memory_instance
             .connect_ref("SEND_TO_HOST_DONE", &format!("{}_send_done", mem));
 
-        memory_instance.connect_ref("WRITE_DATA", &write_data);
-        memory_instance.connect_ref("READ_DATA", &read_data);
-        memory_instance.connect_ref("ADDR", &addr0);
-        memory_instance.connect_ref("WE", &write_en);
-        memory_instance.connect_ref("DONE", &done);
+        for (signal, port, width) in memory_ports(memory) {
+            module.add_decl(v::Decl::new_wire(&signal, width));
+            memory_instance.connect_ref(&port, &signal);
+        }
         module.add_instance(memory_instance);
     }
 
     // instantiate kernel
-    let mut kernel_instance = v::Instance::new("kernel_inst", "main");
+    let mut kernel_instance =
+        v::Instance::new("kernel_inst", kernel.toplevel.name.as_ref());
     module.add_decl(v::Decl::new_wire("kernel_start", 1));
     module.add_decl(v::Decl::new_wire("kernel_done", 1));
-    kernel_instance.connect_ref("clk", "ap_clk");
+    kernel_instance.connect_ref("clk", clk);
     kernel_instance.connect_ref("go", "kernel_start");
     kernel_instance
         .connect("reset", v::Expr::new_logical_or("reset", "memories_sent"));
     kernel_instance.connect_ref("done", "kernel_done");
-    for mem in memories {
-        let read_data = format!("{}_read_data", mem);
-        let done = format!("{}_done", mem);
-        let addr0 = format!("{}_addr0", mem);
-        let write_data = format!("{}_write_data", mem);
-        let write_en = format!("{}_write_en", mem);
-        let clk = format!("{}_clk", mem);
-        kernel_instance.connect_ref(&read_data, &read_data);
-        kernel_instance.connect_ref(&done, &done);
-        kernel_instance.connect_ref(&addr0, &addr0);
-        kernel_instance.connect_ref(&write_data, &write_data);
-        kernel_instance.connect_ref(&write_en, &write_en);
-        kernel_instance.connect_ref(&clk, "");
+    for memory in memories {
+        let mem = &memory.name;
+        for (signal, _, _) in memory_ports(memory) {
+            kernel_instance.connect_ref(&signal, &signal);
+        }
+        kernel_instance.connect_ref(&format!("{}_clk", mem), "");
+        if memory.seq {
+            kernel_instance.connect_ref(&format!("{}_reset", mem), "");
+        }
+    }
+    for arg in &kernel.args {
+        kernel_instance.connect_ref(&arg.name, &format!("arg_{}", arg.name));
+    }
+    for (stream, channel) in &streams {
+        connect_stream(&mut module, &mut kernel_instance, stream, channel);
     }
     module.add_instance(kernel_instance);
 
-    // add timeout counter
-    module.add_decl(v::Decl::new_reg("counter", 32));
-    module.add_stmt(utils::cond_non_blk_assign(
-        "ap_clk",
-        "counter",
-        vec![
-            (
-                Some("ap_start".into()),
-                v::Expr::new_add("counter", v::Expr::new_ulit_dec(32, "1")),
-            ),
-            (None, v::Expr::new_ulit_dec(32, "0")),
-        ],
-    ));
+    let memories_sent = v::Expr::new_eq(
+        "memories_sent",
+        v::Expr::new_ulit_bin(memories.len().max(1) as u32, "1"),
+    );
+    match convention {
+        Convention::Vitis => {
+            // add timeout counter
+            module.add_decl(v::Decl::new_reg("counter", 32));
+            module.add_stmt(utils::cond_non_blk_assign(
+                clk,
+                "counter",
+                vec![
+                    (
+                        Some(start.into()),
+                        v::Expr::new_add(
+                            "counter",
+                            v::Expr::new_ulit_dec(32, "1"),
+                        ),
+                    ),
+                    (None, v::Expr::new_ulit_dec(32, "0")),
+                ],
+            ));
 
-    // done signal
-    module.add_stmt(v::Parallel::Assign(
-        "ap_done".into(),
-        v::Expr::new_logical_or(
-            v::Expr::new_gt("counter", "timeout"),
-            v::Expr::new_eq(
-                "memories_sent",
-                v::Expr::new_ulit_bin(memories.len() as u32, "1"),
-            ),
-        ),
-    ));
+            // done signal
+            module.add_stmt(v::Parallel::Assign(
+                done.into(),
+                v::Expr::new_logical_or(
+                    v::Expr::new_gt("counter", "timeout"),
+                    memories_sent,
+                ),
+            ));
+        }
+        Convention::Generic => {
+            module.add_stmt(v::Parallel::Assign(done.into(), memories_sent));
+        }
+    }
 
     module
 }
 
-fn host_transfer_fsm(module: &mut v::Module, memories: &[String]) {
+/// Connect the ports of `stream` on the kernel to its AXI4-Stream channel.
+fn connect_stream(
+    module: &mut v::Module,
+    kernel_instance: &mut v::Instance,
+    stream: &StreamPort,
+    channel: &axi::AxiChannel,
+) {
+    let data = channel.get("DATA");
+    let last = channel.get("LAST");
+    let valid = format!("{}VALID", channel.prefix);
+    let ready = format!("{}READY", channel.prefix);
+    kernel_instance.connect_ref(&format!("{}_valid", stream.name), &valid);
+    kernel_instance.connect_ref(&format!("{}_ready", stream.name), &ready);
+    if stream.input {
+        if stream.width == stream.tdata_width() {
+            kernel_instance.connect_ref(&stream.name, &data);
+        } else {
+            kernel_instance.connect(
+                &stream.name,
+                v::Expr::new_slice(&data, stream.width as i32 - 1, 0),
+            );
+        }
+        if stream.last {
+            kernel_instance
+                .connect_ref(&format!("{}_last", stream.name), &last);
+        }
+    } else {
+        let wire = format!("{}_data", stream.interface());
+        module.add_decl(v::Decl::new_wire(&wire, stream.width));
+        kernel_instance.connect_ref(&stream.name, &wire);
+        module.add_stmt(channel.assign(
+            "DATA",
+            utils::zero_extend(&wire, stream.width, stream.tdata_width()),
+        ));
+        if stream.last {
+            kernel_instance
+                .connect_ref(&format!("{}_last", stream.name), &last);
+        } else {
+            module.add_stmt(channel.assign("LAST", 0));
+        }
+    }
+}
+
+fn host_transfer_fsm(
+    module: &mut v::Module,
+    memories: &[ExternalMemory],
+    clk: &str,
+    start: &str,
+) {
+    let memories: Vec<&str> =
+        memories.iter().map(|mem| mem.name.as_str()).collect();
     module.add_decl(v::Decl::new_wire("memories_copied", 1));
-    module.add_decl(v::Decl::new_reg("memories_sent", memories.len() as u64));
+    // kernels without memories (e.g. streaming kernels) skip the transfers.
+    module.add_decl(v::Decl::new_reg(
+        "memories_sent",
+        memories.len().max(1) as u64,
+    ));
     module.add_stmt(v::Parallel::Assign(
         "memories_copied".into(),
         if memories.is_empty() {
-            panic!("Need some memories")
+            v::Expr::new_ulit_bin(1, "1")
         } else if memories.len() == 1 {
             format!("{}_copy_done", memories[0]).into()
         } else {
@@ -277,16 +393,18 @@ fn host_transfer_fsm(module: &mut v::Module, memories: &[String]) {
         .iter()
         .map(|mem| format!("{}_send", mem).into())
         .collect();
-    let fsm = fsm::LinearFsm::new("host_txn_", "ap_clk", "reset")
-        .state("idle", &[], "ap_start") // idle state
+    let fsm = fsm::LinearFsm::new("host_txn_", clk, "reset")
+        .state("idle", &[], start) // idle state
         .state("copy", &copy_start_assigns, "memories_copied") // copy memory state
         .state("run_kernel", &["kernel_start".into()], "kernel_done") // run kernel state
         .state("send", &send_start_assigns, "memories_sent"); // send memory to host state
 
     let mut parallel = v::ParallelProcess::new_always();
-    parallel.set_event(v::Sequential::new_posedge("ap_clk"));
+    parallel.set_event(v::Sequential::new_posedge(clk));
     let mut ifelse = v::SequentialIfElse::new(fsm.state_is("send"));
-    if memories.len() == 1 {
+    if memories.is_empty() {
+        ifelse.add_seq(v::Sequential::new_nonblk_assign("memories_sent", 1));
+    } else if memories.len() == 1 {
         ifelse.add_seq(v::Sequential::new_nonblk_assign(
             "memories_sent",
             format!("{}_send_done", memories[0]),
@@ -304,3 +422,37 @@ fn host_transfer_fsm(module: &mut v::Module, memories: &[String]) {
     module.add_stmt(parallel);
     fsm.emit(module);
 }
+
+/// The ports of `memory` in the kernel, each with the port of the memory
+/// controller it is connected to and its width. The ports of the kernel and
+/// the wires connecting them have the same name.
+fn memory_ports(memory: &ExternalMemory) -> Vec<(String, String, u64)> {
+    let banks: &[(&str, &str)] = if memory.dual {
+        &[("_a", ""), ("_b", "_B")]
+    } else {
+        &[("", "")]
+    };
+    let done = if memory.seq { "write_done" } else { "done" };
+    let mut ports = vec![];
+    for (suffix, port) in banks {
+        let mut signals = vec![
+            ("write_data", "WRITE_DATA", memory.width),
+            ("read_data", "READ_DATA", memory.width),
+            ("addr0", "ADDR", memory.idx_size),
+            ("write_en", "WE", 1),
+            (done, "DONE", 1),
+        ];
+        if memory.seq {
+            signals.push(("read_en", "READ_EN", 1));
+            signals.push(("read_done", "READ_DONE", 1));
+        }
+        ports.extend(signals.into_iter().map(|(signal, ctrl, width)| {
+            (
+                format!("{}_{}{}", memory.name, signal, suffix),
+                format!("{}{}", ctrl, port),
+                width,
+            )
+        }));
+    }
+    ports
+}
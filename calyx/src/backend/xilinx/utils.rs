@@ -47,3 +47,18 @@ where
             .collect(),
     )
 }
+
+/// Zero extend the `width` bit signal `name` to `target` bits.
+pub fn zero_extend(name: &str, width: u64, target: u64) -> v::Expr {
+    if width >= target {
+        return name.into();
+    }
+    // concatenations are emitted in reverse order of insertion.
+    let mut concat = v::ExprConcat::default();
+    concat.add_expr(name);
+    concat.add_expr(v::Expr::new_repeat(
+        target - width,
+        v::Expr::new_ulit_bin(1, "0"),
+    ));
+    concat.into()
+}
@@ -1,8 +1,5 @@
-use crate::{
-    backend::traits::Backend,
-    errors::{Error, FutilResult},
-    ir,
-};
+use super::params::{Convention, Kernel as KernelParams, CONTROL_DATA_WIDTH};
+use crate::{backend::traits::Backend, errors::FutilResult, ir};
 use serde::Serialize;
 
 /// Backend that generates XML that Xilinx needs to define the address
@@ -50,10 +47,12 @@ impl<'a> From<Vec<Port<'a>>> for Ports<'a> {
 struct Port<'a> {
     name: &'a str,
     mode: &'a str,
-    range: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<&'a str>,
     data_width: u64,
     port_type: &'a str,
-    base: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base: Option<&'a str>,
 }
 
 #[derive(Serialize)]
@@ -101,19 +100,23 @@ impl Backend for XilinxXmlBackend {
         prog: &ir::Context,
         file: &mut crate::utils::OutputFile,
     ) -> FutilResult<()> {
-        let toplevel = prog
-            .components
-            .iter()
-            .find(|comp| comp.attributes.has("toplevel"))
-            .ok_or_else(|| Error::Misc("no toplevel".to_string()))?;
-
+        let kernel = KernelParams::new(prog, Convention::Vitis)?;
+        let KernelParams {
+            config,
+            memories,
+            args: scalars,
+            streams,
+            ..
+        } = &kernel;
+
+        let control_range = format!("{:#x}", 1u64 << config.control_addr_width);
         let mut ports = vec![Port {
             name: "s_axi_control",
             mode: "slave",
-            range: "0x1000",
-            data_width: 32,
+            range: Some(&control_range),
+            data_width: CONTROL_DATA_WIDTH,
             port_type: "addressable",
-            base: "0x0",
+            base: Some("0x0"),
         }];
 
         let mut args = vec![Arg {
@@ -128,45 +131,90 @@ impl Backend for XilinxXmlBackend {
             host_size: "0x4",
         }];
 
-        let memories: Vec<(String, String)> = toplevel
-            .cells
-            .iter()
-            .filter(|cell_ref| {
-                matches!(cell_ref.borrow().get_attribute("external"), Some(&1))
-            })
-            .enumerate()
-            .map(|(i, cell_ref)| {
-                (cell_ref.borrow().name().to_string(), format!("m{}_axi", i))
-            })
-            .collect();
         // make the lifetime of the &str long enough
-        let memories_ref: Vec<(&str, &str)> = memories
-            .iter()
-            .map(|(x, y)| (x.as_ref(), y.as_ref()))
-            .collect();
+        let axi_names: Vec<String> =
+            (0..memories.len()).map(|i| format!("m{}_axi", i)).collect();
         let offsets: Vec<String> = (0..memories.len())
-            .map(|i| format!("{:#x}", 0x18 + (8 * i)))
+            .map(|i| format!("{:#x}", kernel.base_addr_offset(i)))
             .collect();
+        let memory_range =
+            format!("{:#X}", u64::MAX >> (64 - config.bus_addr_width));
+        let base_addr_size = format!("{:#x}", config.base_addr_bytes());
 
-        for (i, (name, axi_name)) in memories_ref.iter().enumerate() {
+        for (i, mem) in memories.iter().enumerate() {
             ports.push(Port {
-                name: axi_name,
+                name: &axi_names[i],
                 mode: "master",
-                range: "0xFFFFFFFFFFFFFFFF",
-                data_width: 64,
+                range: Some(&memory_range),
+                data_width: config.bus_data_width,
                 port_type: "addressable",
-                base: "0x0",
+                base: Some("0x0"),
             });
             args.push(Arg {
-                name,
+                name: &mem.name,
                 address_qualifier: 1,
                 id: (i + 1) as u64,
-                port: axi_name,
-                size: "0x8",
+                port: &axi_names[i],
+                size: &base_addr_size,
                 offset: &offsets[i],
                 typ: "int*",
                 host_offset: "0x0",
-                host_size: "0x8",
+                host_size: &base_addr_size,
+            });
+        }
+        let scalar_offsets: Vec<String> = (0..scalars.len())
+            .map(|i| format!("{:#x}", kernel.arg_offset(i)))
+            .collect();
+        let scalar_sizes: Vec<String> = scalars
+            .iter()
+            .map(|arg| format!("{:#x}", arg.bytes()))
+            .collect();
+        for (i, arg) in scalars.iter().enumerate() {
+            args.push(Arg {
+                name: &arg.name,
+                address_qualifier: 0,
+                id: (memories.len() + i + 1) as u64,
+                port: "s_axi_control",
+                size: &scalar_sizes[i],
+                offset: &scalar_offsets[i],
+                typ: if arg.width > 32 { "ulong" } else { "uint" },
+                host_offset: "0x0",
+                host_size: &scalar_sizes[i],
+            });
+        }
+
+        // streams are not part of the control interface
+        let stream_names: Vec<String> =
+            streams.iter().map(|stream| stream.interface()).collect();
+        let stream_types: Vec<String> = streams
+            .iter()
+            .map(|stream| {
+                format!("stream<ap_axiu<{}, 0, 0, 0>>&", stream.tdata_width())
+            })
+            .collect();
+        for (i, stream) in streams.iter().enumerate() {
+            ports.push(Port {
+                name: &stream_names[i],
+                mode: if stream.input {
+                    "read_only"
+                } else {
+                    "write_only"
+                },
+                range: None,
+                data_width: stream.tdata_width(),
+                port_type: "stream",
+                base: None,
+            });
+            args.push(Arg {
+                name: &stream.name,
+                address_qualifier: 4,
+                id: (memories.len() + scalars.len() + i + 1) as u64,
+                port: &stream_names[i],
+                size: "0x0",
+                offset: "0x0",
+                typ: &stream_types[i],
+                host_offset: "0x0",
+                host_size: "0x0",
             });
         }
 
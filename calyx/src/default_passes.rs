@@ -1,11 +1,12 @@
 //! Defines the default passes available to [PassManager].
 use crate::passes::{
-    ClkInsertion, CollapseControl, CompileControl, CompileEmpty, CompileInvoke,
-    ComponentInterface, DeadCellRemoval, Externalize, GoInsertion,
-    GuardCanonical, InferStaticTiming, Inliner, MergeAssign, MinimizeRegs,
-    Papercut, ParToSeq, RegisterUnsharing, ResetInsertion, ResourceSharing,
-    SimplifyGuards, StaticTiming, SynthesisPapercut, TopDownCompileControl,
-    WellFormed,
+    ClkInsertion, CollapseControl, CombLoops, CompileControl, CompileEmpty,
+    CompileInvoke, CompileRef, ComponentInterface, DeadCellRemoval,
+    Externalize, GoInsertion, GuardCanonical, InferStaticTiming, Inliner,
+    MemoryBanking, MergeAssign, MinimizeMems, MinimizeRegs, MultipleDrivers,
+    Papercut, ParRaces, ParToSeq, RegisterUnsharing, ResetInsertion,
+    ResourceSharing, SimplifyGuards, StaticTiming, SynthesisPapercut,
+    TopDownCompileControl, WellFormed,
 };
 use crate::{
     errors::FutilResult,
@@ -24,6 +25,7 @@ impl PassManager {
         register_pass!(pm, StaticTiming);
         register_pass!(pm, CompileControl);
         register_pass!(pm, CompileInvoke);
+        register_pass!(pm, CompileRef);
         register_pass!(pm, GoInsertion);
         register_pass!(pm, ComponentInterface);
         register_pass!(pm, Inliner);
@@ -36,6 +38,8 @@ impl PassManager {
         register_pass!(pm, ResourceSharing);
         register_pass!(pm, DeadCellRemoval);
         register_pass!(pm, MinimizeRegs);
+        register_pass!(pm, MinimizeMems);
+        register_pass!(pm, MemoryBanking);
         register_pass!(pm, InferStaticTiming);
         register_pass!(pm, SimplifyGuards);
         register_pass!(pm, MergeAssign);
@@ -44,12 +48,21 @@ impl PassManager {
         register_pass!(pm, RegisterUnsharing);
         register_pass!(pm, GuardCanonical);
         register_pass!(pm, ParToSeq);
+        register_pass!(pm, ParRaces);
+        register_pass!(pm, CombLoops);
+        register_pass!(pm, MultipleDrivers);
 
-        register_alias!(pm, "validate", [WellFormed, Papercut, GuardCanonical]);
+        register_alias!(
+            pm,
+            "validate",
+            [WellFormed, Papercut, CombLoops, GuardCanonical]
+        );
         register_alias!(
             pm,
             "pre-opt",
             [
+                CompileRef,
+                MemoryBanking,
                 InferStaticTiming,
                 CollapseControl,
                 ResourceSharing,
@@ -3,9 +3,14 @@
 use crate::frontend::{ast, parser};
 use crate::ir;
 use petgraph::stable_graph::NodeIndex;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::iter::repeat;
 use std::rc::Rc;
 
+/// The name used for programs read from the standard input.
+pub const STDIN: &str = "<stdin>";
+
 /// Standard error type for Calyx errors.
 #[allow(clippy::large_enum_variant)]
 pub enum Error {
@@ -46,6 +51,9 @@ pub enum Error {
     /// Papercut error: signals a commonly made mistake in Calyx program.
     Papercut(String, ir::Id),
 
+    /// Groups that may run in parallel access the same cell.
+    DataRace(String, ir::Id),
+
     /// Group "static" latency annotation differed from inferred latency.
     ImpossibleLatencyAnnotation(String, u64, u64),
 
@@ -56,6 +64,152 @@ pub enum Error {
     /// A miscellaneous error. Should be replaced with a more precise error.
     #[allow(unused)]
     Misc(String),
+
+    /// A collection of independent errors, sorted by source position.
+    Multiple(Vec<Error>),
+}
+
+impl Error {
+    /// Combine a list of errors into a single error.
+    /// Nested [Error::Multiple] are flattened and the errors are sorted by
+    /// the file and the position in it that they refer to. Errors without a
+    /// position are reported last in the order they were generated.
+    /// Errors with the same position and message are only reported once.
+    ///
+    /// # Panics
+    /// Panics if `errors` is empty.
+    pub fn combine(errors: Vec<Error>) -> Error {
+        let mut flat: Vec<Error> = Vec::with_capacity(errors.len());
+        for err in errors {
+            match err {
+                Error::Multiple(errs) => flat.extend(errs),
+                err => flat.push(err),
+            }
+        }
+        // Remove errors reported more than once.
+        let mut seen = HashSet::new();
+        flat.retain(|err| {
+            let location = err
+                .location()
+                .map(|(file, start, end)| (file.to_string(), start, end));
+            seen.insert((location, err.message()))
+        });
+        // Errors without a location are sorted after all the others.
+        flat.sort_by(|a, b| match (a.location(), b.location()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        assert!(!flat.is_empty(), "Attempted to combine zero errors");
+        if flat.len() == 1 {
+            flat.pop().unwrap()
+        } else {
+            Error::Multiple(flat)
+        }
+    }
+
+    /// Collect the results from `results`. If any of them failed, returns
+    /// all the errors combined using [Error::combine].
+    pub fn collect<T, I>(results: I) -> FutilResult<Vec<T>>
+    where
+        I: IntoIterator<Item = FutilResult<T>>,
+    {
+        let mut oks = vec![];
+        let mut errs = vec![];
+        for res in results {
+            match res {
+                Ok(v) => oks.push(v),
+                Err(e) => errs.push(e),
+            }
+        }
+        if errs.is_empty() {
+            Ok(oks)
+        } else {
+            Err(Error::combine(errs))
+        }
+    }
+
+    /// The offset into the source program that this error refers to, if
+    /// known.
+    pub fn position(&self) -> Option<usize> {
+        self.location().map(|(_, start, _)| start)
+    }
+
+    /// The file and the byte range in it that this error refers to, if
+    /// known. Programs that are not read from a file use [STDIN].
+    pub fn location(&self) -> Option<(&str, usize, usize)> {
+        use Error::*;
+        match self {
+            ParseError(err) => {
+                let file = err.path().unwrap_or(STDIN);
+                match err.location {
+                    pest::error::InputLocation::Pos(pos) => {
+                        Some((file, pos, pos))
+                    }
+                    pest::error::InputLocation::Span((start, end)) => {
+                        Some((file, start, end))
+                    }
+                }
+            }
+            Multiple(errs) => errs.iter().find_map(|e| e.location()),
+            _ => self.span().map(|span| (span.file(), span.start, span.end)),
+        }
+    }
+
+    /// The span of the identifier that this error refers to, if known.
+    /// Parse errors do not have a span; their location is stored in the
+    /// underlying parser error.
+    pub fn span(&self) -> Option<&Span> {
+        use Error::*;
+        let id = match self {
+            ReservedName(id)
+            | Undefined(id, _)
+            | AlreadyBound(id, _)
+            | UnusedGroup(id)
+            | SignatureResolutionFailed(id, _)
+            | Papercut(_, id)
+            | DataRace(_, id) => id,
+            MismatchedPortWidths(port, ..) => port.port_name(),
+            Multiple(errs) => return errs.iter().find_map(|e| e.span()),
+            _ => return None,
+        };
+        id.span()
+    }
+
+    /// A short description of the error that does not include the source
+    /// program. Used by tools that report the location of errors separately.
+    pub fn message(&self) -> String {
+        use Error::*;
+        match self {
+            Papercut(msg, _) => format!("[Papercut] {}", msg),
+            DataRace(msg, _) => format!("[Data race] {}", msg),
+            UnusedGroup(_) => "Group not used in control".to_string(),
+            AlreadyBound(_, bound_by) => {
+                format!("Name already bound by {}", bound_by)
+            }
+            ReservedName(name) => format!("Use of reserved keyword: {}", name),
+            Undefined(name, typ) => {
+                format!("Undefined {} name: {}", typ, name)
+            }
+            ParseError(err) => err.variant.message().to_string(),
+            MismatchedPortWidths(_, w1, port2, w2) => format!(
+                "This port has width: {} which doesn't match the width of '{}': {}",
+                w1,
+                port2.port_name(),
+                w2
+            ),
+            SignatureResolutionFailed(_, param_name) => {
+                format!("Failed to resolve: {}", param_name)
+            }
+            Multiple(errs) => errs
+                .iter()
+                .map(|e| e.message())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => format!("{:?}", self),
+        }
+    }
 }
 
 /// Convience wrapper to represent success or meaningul compiler error.
@@ -65,6 +219,8 @@ pub type FutilResult<T> = std::result::Result<T, Error>;
 /// Used for reporting location-based errors.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Span {
+    /// Name of the file containing the input program.
+    file: Rc<str>,
     /// Reference to input program source.
     input: Rc<str>,
     /// The start of the span.
@@ -74,16 +230,42 @@ pub struct Span {
 }
 
 impl Span {
-    /// Create a new `Error::Span` from a `pest::Span` and
-    /// the input string.
-    pub fn new(span: pest::Span, input: Rc<str>) -> Span {
+    /// Create a new `Error::Span` from a `pest::Span`, the name of the
+    /// input file, and the input string.
+    pub fn new(span: pest::Span, file: Rc<str>, input: Rc<str>) -> Span {
         Span {
+            file,
             input,
             start: span.start(),
             end: span.end(),
         }
     }
 
+    /// The name of the file this span refers to.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// The program this span refers to.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The byte offset of the start of the span.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The byte offset one past the end of the span.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The line the span starts on, counting from 1.
+    pub fn line(&self) -> usize {
+        self.input[..self.start].matches('\n').count() + 1
+    }
+
     /// Format this Span with a the error message `err_msg`
     pub fn format(&self, err_msg: &str) -> String {
         let lines = self.input.split('\n');
@@ -117,9 +299,12 @@ impl std::fmt::Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Error::*;
         match self {
-            Papercut(msg, id) => {
-                write!(f, "{}", id.fmt_err(&("[Papercut] ".to_string() + msg)))
-            }
+            Papercut(_, id)
+            | DataRace(_, id)
+            | UnusedGroup(id)
+            | AlreadyBound(id, _)
+            | ReservedName(id)
+            | Undefined(id, _) => write!(f, "{}", id.fmt_err(&self.message())),
             ImpossibleLatencyAnnotation(grp_name, ann_val, inferred_val) => {
                 let msg1 = format!("Annotated latency: {}", ann_val);
                 let msg2 = format!("Inferred latency: {}", inferred_val);
@@ -131,29 +316,6 @@ impl std::fmt::Debug for Error {
                     msg2
                 )
             }
-            UnusedGroup(name) => {
-                write!(
-                    f,
-                    "{}",
-                    name.fmt_err("Group not used in control")
-                )
-            }
-            AlreadyBound(name, bound_by) => {
-                let msg = format!("Name already bound by {}", bound_by.to_string());
-                write!(f, "{}", name.fmt_err(&msg))
-            }
-            ReservedName(name) => {
-                let msg = format!("Use of reserved keyword: {}", name.to_string());
-                write!(f, "{}", name.fmt_err(&msg))
-            }
-            Undefined(name, typ) => {
-                let msg = format!("Undefined {} name: {}", typ, name.to_string());
-                write!(
-                    f,
-                    "{}",
-                    name.fmt_err(&msg)
-                )
-            }
             UnknownPass(pass, known_passes) => {
                 write!(
                     f,
@@ -170,7 +332,7 @@ impl std::fmt::Debug for Error {
                 let msg2 = format!("This port has width: {}", w2);
                 write!(f, "{}\nwhich doesn't match the width of '{}':{}",
                        port1.port_name().fmt_err(&msg1),
-                       port2.port_name().to_string(),
+                       port2.port_name(),
                        port2.port_name().fmt_err(&msg2))
             }
             SignatureResolutionFailed(id, param_name) => {
@@ -182,7 +344,11 @@ impl std::fmt::Debug for Error {
             NotSubcomponent => write!(f, "Not a subcomponent"),
             Misc(msg) => write!(f, "{}", msg),
             Impossible(msg) => write!(f, "Impossible: {}\nThis error should never occur. Report report this as a bug.", msg),
-            MissingImplementation(name, id) => write!(f, "Mising {} implementation for `{}`", name, id.to_string())
+            MissingImplementation(name, id) => write!(f, "Mising {} implementation for `{}`", name, id.to_string()),
+            Multiple(errs) => {
+                let msgs: Vec<_> = errs.iter().map(|e| format!("{:?}", e)).collect();
+                write!(f, "{}", msgs.join("\nError: "))
+            }
         }
     }
 }
@@ -3,6 +3,7 @@ use super::parser;
 use crate::errors::{Error, FutilResult, Span};
 use crate::ir;
 use atty::Stream;
+use std::collections::{HashMap, HashSet};
 use std::io::stdin;
 use std::path::{Path, PathBuf};
 
@@ -39,11 +40,25 @@ pub struct NamespaceDef {
     pub externs: Vec<(String, Vec<ir::Primitive>)>,
 }
 
+/// Environment variable with additional library paths. The paths are
+/// separated in the same way as `PATH` and searched after the paths passed
+/// to [NamespaceDef::new].
+pub const LIB_PATH_ENV: &str = "CALYX_LIB_PATH";
+
 impl NamespaceDef {
     /// Parse the program and all of its transitive dependencies to build
     /// a whole program context.
-    pub fn new(file: &Option<PathBuf>, lib_path: &Path) -> FutilResult<Self> {
-        let mut namespace = match file {
+    ///
+    /// Imports and externs in `file` are searched for in `lib_paths` followed
+    /// by the paths in [LIB_PATH_ENV]. Imports and externs in imported files
+    /// are first resolved relative to the importing file.
+    /// Each file is only imported once. Import cycles and definitions with
+    /// the same name in different files are reported as errors.
+    pub fn new(
+        file: &Option<PathBuf>,
+        lib_paths: &[PathBuf],
+    ) -> FutilResult<Self> {
+        let namespace = match file {
             Some(file) => parser::FutilParser::parse_file(&file),
             None => {
                 if atty::isnt(Stream::Stdin) {
@@ -55,45 +70,169 @@ impl NamespaceDef {
                 }
             }
         }?;
+        namespace.with_imports(file, lib_paths)
+    }
+
+    /// Load the transitive imports of `self`, which was parsed from `file`,
+    /// into the namespace. Imports are resolved and checked in the same way
+    /// as [NamespaceDef::new], which makes it possible to load the imports of
+    /// a program that has not been saved to `file`.
+    pub fn with_imports(
+        self,
+        file: &Option<PathBuf>,
+        lib_paths: &[PathBuf],
+    ) -> FutilResult<Self> {
+        let mut lib_paths = lib_paths.to_vec();
+        if let Some(paths) = std::env::var_os(LIB_PATH_ENV) {
+            lib_paths.extend(std::env::split_paths(&paths));
+        }
+
+        let mut imports = self.imports.clone();
+        let mut seen = HashSet::new();
+        imports.retain(|import| seen.insert(import.clone()));
+
+        let mut loader = Loader {
+            lib_paths,
+            imported: HashSet::new(),
+            stack: vec![],
+            defined: HashMap::new(),
+            namespace: NamespaceDef {
+                imports,
+                components: vec![],
+                externs: vec![],
+            },
+            errors: vec![],
+        };
+        let root = file.clone().unwrap_or_else(|| PathBuf::from("<stdin>"));
+        loader.load(self, root, None)?;
+
+        if loader.errors.is_empty() {
+            Ok(loader.namespace)
+        } else {
+            Err(Error::combine(loader.errors))
+        }
+    }
+}
+
+/// Loads the transitive imports of a program into a single namespace.
+struct Loader {
+    /// Paths searched for imports.
+    lib_paths: Vec<PathBuf>,
+    /// Canonical paths of all the files that have been imported.
+    imported: HashSet<PathBuf>,
+    /// The chain of imports that led to the file currently being loaded.
+    /// Each file is stored with its canonical path and the path used to
+    /// import it.
+    stack: Vec<(PathBuf, PathBuf)>,
+    /// The file defining each component and primitive.
+    defined: HashMap<ir::Id, PathBuf>,
+    /// The combined namespace.
+    namespace: NamespaceDef,
+    /// Components and primitives defined in more than one file.
+    errors: Vec<Error>,
+}
+
+impl Loader {
+    /// Resolve `path` relative to `dir` if provided and then relative to the
+    /// library paths. Returns `None` if no such file exists.
+    fn resolve(&self, path: &str, dir: Option<&Path>) -> Option<PathBuf> {
+        dir.into_iter()
+            .chain(self.lib_paths.iter().map(PathBuf::as_path))
+            .map(|base| base.join(path))
+            .find(|full| full.exists())
+    }
 
-        namespace.externs.iter_mut().for_each(|(path, _)| {
-            *path = lib_path.join(path.clone()).to_string_lossy().to_string();
-        });
-
-        // Parse all transitive dependencies
-        let mut deps: Vec<PathBuf> = namespace
-            .imports
-            .clone()
-            .into_iter()
-            .map(|f| lib_path.join(f))
-            .collect();
-
-        while let Some(path) = deps.pop() {
-            let mut ns = parser::FutilParser::parse_file(&path)?;
-            namespace.components.append(&mut ns.components);
-
-            let parent = match path.parent() {
-                Some(a) => a,
-                None => Path::new("."),
-            };
-
-            namespace.externs.append(
-                &mut ns
-                    .externs
-                    .into_iter()
-                    .map(|(path, exts)| {
-                        (parent.join(path).to_string_lossy().to_string(), exts)
-                    })
-                    .collect(),
-            );
-
-            // All imports are relative to the file being currently parsed.
-            deps.append(
-                &mut ns.imports.into_iter().map(|f| parent.join(f)).collect(),
-            );
+    /// Record that `name` is defined in `file`. Returns false if `name` is
+    /// already defined in another file. Names defined more than once in the
+    /// same file are reported when the IR is built.
+    fn define(&mut self, name: &ir::Id, file: &Path) -> bool {
+        match self.defined.get(name) {
+            Some(prev) if prev != file => {
+                self.errors.push(Error::AlreadyBound(
+                    name.clone(),
+                    format!("component or primitive in {}", prev.display()),
+                ));
+                false
+            }
+            Some(_) => true,
+            None => {
+                self.defined.insert(name.clone(), file.to_path_buf());
+                true
+            }
+        }
+    }
+
+    /// Add the definitions in `ns`, which was parsed from `file`, and load
+    /// its imports. `dir` is the directory that relative paths in `ns` are
+    /// resolved against.
+    fn load(
+        &mut self,
+        ns: NamespaceDef,
+        file: PathBuf,
+        dir: Option<&Path>,
+    ) -> FutilResult<()> {
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.clone());
+        self.imported.insert(canonical.clone());
+        self.stack.push((canonical, file.clone()));
+
+        for (path, mut prims) in ns.externs {
+            let full = self.resolve(&path, dir).unwrap_or_else(|| {
+                dir.or_else(|| self.lib_paths.first().map(PathBuf::as_path))
+                    .unwrap_or_else(|| Path::new("."))
+                    .join(&path)
+            });
+            prims.retain(|prim| self.define(&prim.name, &file));
+            self.namespace
+                .externs
+                .push((full.to_string_lossy().to_string(), prims));
+        }
+        for comp in ns.components {
+            if self.define(&comp.name, &file) {
+                self.namespace.components.push(comp);
+            }
+        }
+
+        for import in ns.imports {
+            let path = self.resolve(&import, dir).ok_or_else(|| {
+                Error::InvalidFile(format!(
+                    "Could not find import `{}` in {}. Searched: {}",
+                    import,
+                    file.display(),
+                    dir.into_iter()
+                        .chain(self.lib_paths.iter().map(PathBuf::as_path))
+                        .map(|p| p.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })?;
+            let canonical =
+                path.canonicalize().unwrap_or_else(|_| path.clone());
+            if let Some(pos) =
+                self.stack.iter().position(|(c, _)| *c == canonical)
+            {
+                let cycle = self.stack[pos..]
+                    .iter()
+                    .map(|(_, f)| f.display().to_string())
+                    .chain(Some(path.display().to_string()))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(Error::InvalidFile(format!(
+                    "Import cycle: {}",
+                    cycle
+                )));
+            }
+            if self.imported.contains(&canonical) {
+                continue;
+            }
+            let imported = parser::FutilParser::parse_file(&path)?;
+            let parent = path
+                .parent()
+                .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+            self.load(imported, path, Some(&parent))?;
         }
 
-        Ok(namespace)
+        self.stack.pop();
+        Ok(())
     }
 }
 
@@ -224,6 +363,8 @@ pub struct Cell {
     pub prototype: Proto,
     /// Attributes attached to this cell definition
     pub attributes: ir::Attributes,
+    /// True if this cell is passed by reference from the caller.
+    pub reference: bool,
 }
 
 /// Methods for constructing the structure AST nodes.
@@ -234,6 +375,7 @@ impl Cell {
         proto: ir::Id,
         params: Vec<u64>,
         attributes: ir::Attributes,
+        reference: bool,
     ) -> Cell {
         Cell {
             name,
@@ -242,6 +384,7 @@ impl Cell {
                 params,
             },
             attributes,
+            reference,
         }
     }
 }
@@ -322,6 +465,8 @@ pub enum Control {
     Invoke {
         /// Name of the component to be invoked.
         comp: ir::Id,
+        /// Bindings for the `ref` cells of the invoked component.
+        ref_cells: Vec<(ir::Id, ir::Id)>,
         /// Input assignments
         inputs: Vec<(ir::Id, Atom)>,
         /// Output assignments
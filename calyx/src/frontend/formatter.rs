@@ -0,0 +1,651 @@
+//! Source formatter for Calyx programs.
+//!
+//! Unlike [`ir::IRPrinter`](crate::ir::IRPrinter), the formatter works on the
+//! frontend AST and retains the comments and blank lines in the source
+//! program. The AST nodes are printed in source order while a cursor walks
+//! over the source text. Comments encountered between two nodes are printed
+//! before the second node and comments on the same line as the end of a node
+//! are kept at the end of that line.
+use super::ast::{self, Atom, BitNum, GuardExpr, NumType};
+use super::parser::FutilParser;
+use crate::errors::FutilResult;
+use crate::ir::{self, IRPrinter};
+use std::collections::VecDeque;
+
+/// Number of spaces used for each level of indentation.
+const INDENT: usize = 2;
+
+/// A comment in the source program.
+struct Comment {
+    /// Byte offset of the start of the comment.
+    start: usize,
+    /// Byte offset one past the end of the comment.
+    end: usize,
+}
+
+/// Formats a Calyx program while preserving its comments.
+pub struct Formatter<'a> {
+    /// The program being formatted.
+    source: &'a str,
+    /// All the comments in the program ordered by position.
+    comments: Vec<Comment>,
+    /// Index of the first comment that has not been printed.
+    next_comment: usize,
+    /// Position in `source` up to which the program has been printed.
+    cursor: usize,
+    /// The formatted program.
+    out: String,
+    /// True if nothing has been printed since the start of the current block.
+    block_start: bool,
+}
+
+impl<'a> Formatter<'a> {
+    /// Format the Calyx program in `source`.
+    pub fn format(source: &'a str) -> FutilResult<String> {
+        let namespace = FutilParser::parse(source.as_bytes())?;
+        let mut fmt = Formatter {
+            source,
+            comments: find_comments(source),
+            next_comment: 0,
+            cursor: 0,
+            out: String::with_capacity(source.len()),
+            block_start: true,
+        };
+        fmt.file(namespace);
+        Ok(fmt.out)
+    }
+
+    // ==================== Source navigation ======================
+
+    /// Returns the position of the first character at or after `pos` that is
+    /// not whitespace or part of a comment.
+    fn skip_trivia(&self, mut pos: usize) -> usize {
+        loop {
+            let rest = &self.source[pos..];
+            let trimmed = rest.trim_start();
+            pos += rest.len() - trimmed.len();
+            match self.comments.iter().find(|c| c.start == pos) {
+                Some(c) => pos = c.end,
+                None => return pos,
+            }
+        }
+    }
+
+    /// Position of the next token after the cursor.
+    fn peek(&self) -> usize {
+        self.skip_trivia(self.cursor)
+    }
+
+    /// Returns true if the next token after the cursor is the keyword `kw`.
+    fn peek_keyword(&self, kw: &str) -> bool {
+        let rest = &self.source[self.peek()..];
+        rest.starts_with(kw)
+            && !rest[kw.len()..]
+                .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Returns the position of the next `ch` after the cursor that is not
+    /// part of a comment or a string literal.
+    fn find(&self, ch: char) -> usize {
+        let start = self.peek();
+        let mut pos = start;
+        let mut in_string = false;
+        for (idx, c) in self.source[start..].char_indices() {
+            let at = start + idx;
+            if at < pos {
+                continue;
+            }
+            if c == '"' {
+                in_string = !in_string;
+            } else if !in_string {
+                if c == ch {
+                    return at;
+                }
+                if let Some(comment) =
+                    self.comments.iter().find(|cm| cm.start == at)
+                {
+                    pos = comment.end;
+                }
+            }
+        }
+        panic!("Expected `{}` after position {}", ch, self.cursor)
+    }
+
+    /// Returns true if there is a blank line immediately before `pos`.
+    fn blank_line_before(&self, pos: usize) -> bool {
+        let prefix = &self.source[..pos];
+        let trimmed = prefix.trim_end();
+        prefix[trimmed.len()..].matches('\n').count() > 1
+    }
+
+    // ==================== Output ======================
+
+    /// Print the comments before the next token and start a new line with
+    /// `text`.
+    fn open_line(&mut self, indent: usize, text: &str) {
+        let next = self.peek();
+        self.leading_comments(indent, next);
+        if !self.block_start && self.blank_line_before(next) {
+            self.out.push('\n');
+        }
+        self.out.push_str(&" ".repeat(indent));
+        self.out.push_str(text);
+    }
+
+    /// Print all the comments that start before `pos` on their own lines.
+    fn leading_comments(&mut self, indent: usize, pos: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.start >= pos {
+                break;
+            }
+            let (start, end) = (comment.start, comment.end);
+            if !self.block_start && self.blank_line_before(start) {
+                self.out.push('\n');
+            }
+            self.out.push_str(&" ".repeat(indent));
+            self.out.push_str(self.source[start..end].trim_end());
+            self.out.push('\n');
+            self.next_comment += 1;
+            self.cursor = self.cursor.max(end);
+            self.block_start = false;
+        }
+    }
+
+    /// Move the cursor to `pos` and end the current line. A comment that
+    /// follows `pos` on the same source line is kept at the end of the line.
+    fn end_line(&mut self, pos: usize) {
+        self.cursor = pos;
+        if let Some(comment) = self.comments.get(self.next_comment) {
+            let between = &self.source[pos..comment.start];
+            if between.trim().is_empty() && !between.contains('\n') {
+                self.out.push(' ');
+                self.out.push_str(
+                    self.source[comment.start..comment.end].trim_end(),
+                );
+                self.cursor = comment.end;
+                self.next_comment += 1;
+            }
+        }
+        self.out.push('\n');
+        self.block_start = false;
+    }
+
+    /// Print a line containing `text` for a node that is terminated by `;`.
+    fn statement(&mut self, indent: usize, text: &str) {
+        self.open_line(indent, text);
+        let end = self.find(';') + 1;
+        self.end_line(end);
+    }
+
+    /// Print a line containing `text` for a node that opens a block.
+    fn open_block(&mut self, indent: usize, text: &str) {
+        self.open_line(indent, text);
+        let end = self.find('{') + 1;
+        self.end_line(end);
+        self.block_start = true;
+    }
+
+    /// Print the comments remaining in the current block followed by the
+    /// closing brace. Does not end the line.
+    fn close_brace(&mut self, indent: usize) {
+        let close = self.find('}');
+        self.leading_comments(indent + INDENT, close);
+        self.out.push_str(&" ".repeat(indent));
+        self.out.push('}');
+        self.cursor = close + 1;
+    }
+
+    /// If the block starting at the cursor contains no comments, print
+    /// `text` in place of the block and return true.
+    fn empty_block(&mut self, indent: usize, text: &str) -> bool {
+        let close = self.find('}');
+        if let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.start < close {
+                return false;
+            }
+        }
+        self.open_line(indent, text);
+        self.end_line(close + 1);
+        true
+    }
+
+    /// Close the current block.
+    fn close_block(&mut self, indent: usize) {
+        self.close_brace(indent);
+        self.end_line(self.cursor);
+    }
+
+    // ==================== AST nodes ======================
+
+    fn file(&mut self, namespace: ast::NamespaceDef) {
+        for import in &namespace.imports {
+            self.statement(0, &format!("import \"{}\";", import));
+        }
+
+        let mut externs: VecDeque<_> = namespace.externs.into();
+        let mut components: VecDeque<_> = namespace.components.into();
+        loop {
+            let item = if self.peek_keyword("extern") {
+                externs.pop_front().map(|ext| self.external(ext))
+            } else if self.peek_keyword("component") {
+                components.pop_front().map(|comp| self.component(comp))
+            } else {
+                None
+            };
+            if item.is_none() {
+                break;
+            }
+        }
+
+        // Print any remaining comments at the end of the file.
+        self.block_start = self.out.is_empty();
+        self.leading_comments(0, self.source.len());
+    }
+
+    /// Start a new top-level definition separated by a blank line.
+    fn top_level(&mut self) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.block_start = true;
+    }
+
+    fn external(&mut self, (path, prims): (String, Vec<ir::Primitive>)) {
+        self.top_level();
+        self.open_block(0, &format!("extern \"{}\" {{", path));
+        for prim in &prims {
+            let params = if prim.params.is_empty() {
+                "".to_string()
+            } else {
+                format!(
+                    "[{}]",
+                    prim.params
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            self.statement(
+                INDENT,
+                &format!(
+                    "primitive {}{}{}{};",
+                    prim.name,
+                    IRPrinter::format_attributes(&prim.attributes),
+                    params,
+                    signature(&prim.signature)
+                ),
+            );
+        }
+        self.close_block(0);
+    }
+
+    fn component(&mut self, comp: ast::ComponentDef) {
+        self.top_level();
+        self.open_block(
+            0,
+            &format!(
+                "component {}{}{} {{",
+                comp.name,
+                IRPrinter::format_attributes(&comp.attributes),
+                signature(&comp.signature)
+            ),
+        );
+
+        let inner = INDENT * 2;
+
+        // Cells
+        if !(comp.cells.is_empty() && self.empty_block(INDENT, "cells {}")) {
+            self.open_block(INDENT, "cells {");
+            for cell in &comp.cells {
+                self.statement(inner, &cell_str(cell));
+            }
+            self.close_block(INDENT);
+        }
+
+        // Wires
+        let no_wires =
+            comp.groups.is_empty() && comp.continuous_assignments.is_empty();
+        if !(no_wires && self.empty_block(INDENT, "wires {}")) {
+            self.open_block(INDENT, "wires {");
+            let mut groups: VecDeque<_> = comp.groups.into();
+            let mut wires: VecDeque<_> = comp.continuous_assignments.into();
+            loop {
+                if self.peek_keyword("group") {
+                    if let Some(group) = groups.pop_front() {
+                        self.group(group, inner);
+                        continue;
+                    }
+                }
+                match wires.pop_front() {
+                    Some(wire) => self.statement(inner, &wire_str(&wire)),
+                    None => break,
+                }
+            }
+            self.close_block(INDENT);
+        }
+
+        // Control
+        let no_control = matches!(comp.control, ast::Control::Empty {});
+        if !(no_control && self.empty_block(INDENT, "control {}")) {
+            self.open_block(INDENT, "control {");
+            self.control(comp.control, inner);
+            self.close_block(INDENT);
+        }
+
+        self.close_block(0);
+    }
+
+    fn group(&mut self, group: ast::Group, indent: usize) {
+        self.open_block(
+            indent,
+            &format!(
+                "group {}{} {{",
+                group.name,
+                IRPrinter::format_attributes(&group.attributes)
+            ),
+        );
+        for wire in &group.wires {
+            self.statement(indent + INDENT, &wire_str(wire));
+        }
+        self.close_block(indent);
+    }
+
+    fn control(&mut self, control: ast::Control, indent: usize) {
+        match control {
+            ast::Control::Enable { comp, attributes } => self.statement(
+                indent,
+                &format!("{}{};", at_attrs(&attributes), comp),
+            ),
+            ast::Control::Invoke {
+                comp,
+                ref_cells,
+                inputs,
+                outputs,
+                attributes,
+            } => {
+                let refs = if ref_cells.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(
+                        "[{}]",
+                        ref_cells
+                            .iter()
+                            .map(|(arg, cell)| format!("{}={}", arg, cell))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                };
+                let args = |args: &[(ir::Id, Atom)]| {
+                    args.iter()
+                        .map(|(arg, atom)| {
+                            format!("{} = {}", arg, atom_str(atom))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                self.statement(
+                    indent,
+                    &format!(
+                        "{}invoke {}{}({})({});",
+                        at_attrs(&attributes),
+                        comp,
+                        refs,
+                        args(&inputs),
+                        args(&outputs)
+                    ),
+                )
+            }
+            ast::Control::Seq { stmts, attributes } => {
+                self.open_block(
+                    indent,
+                    &format!("{}seq {{", at_attrs(&attributes)),
+                );
+                for stmt in stmts {
+                    self.control(stmt, indent + INDENT);
+                }
+                self.close_block(indent);
+            }
+            ast::Control::Par { stmts, attributes } => {
+                self.open_block(
+                    indent,
+                    &format!("{}par {{", at_attrs(&attributes)),
+                );
+                for stmt in stmts {
+                    self.control(stmt, indent + INDENT);
+                }
+                self.close_block(indent);
+            }
+            ast::Control::If { .. } => {
+                self.open_line(indent, "");
+                self.if_stmt(control, indent);
+            }
+            ast::Control::While {
+                port,
+                cond,
+                body,
+                attributes,
+            } => {
+                self.open_block(
+                    indent,
+                    &format!(
+                        "{}while {} with {} {{",
+                        at_attrs(&attributes),
+                        port_str(&port),
+                        cond
+                    ),
+                );
+                self.control(*body, indent + INDENT);
+                self.close_block(indent);
+            }
+            ast::Control::Empty {} => (),
+        }
+    }
+
+    /// Print an `if` statement starting on the current line.
+    fn if_stmt(&mut self, control: ast::Control, indent: usize) {
+        if let ast::Control::If {
+            port,
+            cond,
+            tbranch,
+            fbranch,
+            attributes,
+        } = control
+        {
+            self.out.push_str(&format!(
+                "{}if {} with {} {{",
+                at_attrs(&attributes),
+                port_str(&port),
+                cond
+            ));
+            let open = self.find('{') + 1;
+            self.end_line(open);
+            self.block_start = true;
+            self.control(*tbranch, indent + INDENT);
+            self.close_brace(indent);
+
+            match *fbranch {
+                ast::Control::Empty {} => self.end_line(self.cursor),
+                fbranch => {
+                    // Skip the `else` keyword.
+                    self.cursor = self.peek() + "else".len();
+                    if matches!(fbranch, ast::Control::If { .. })
+                        && self.peek_keyword("if")
+                    {
+                        self.out.push_str(" else ");
+                        self.if_stmt(fbranch, indent);
+                    } else {
+                        self.out.push_str(" else {");
+                        let open = self.find('{') + 1;
+                        self.end_line(open);
+                        self.block_start = true;
+                        self.control(fbranch, indent + INDENT);
+                        self.close_block(indent);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the comments in `source` ordered by position.
+fn find_comments(source: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut pos = 0;
+    while pos < source.len() {
+        let rest = &source[pos..];
+        if rest.starts_with("//") {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            comments.push(Comment {
+                start: pos,
+                end: pos + len,
+            });
+            pos += len;
+        } else if rest.starts_with("/*") {
+            let len = rest.find("*/").map_or(rest.len(), |e| e + 2);
+            comments.push(Comment {
+                start: pos,
+                end: pos + len,
+            });
+            pos += len;
+        } else if let Some(string) = rest.strip_prefix('"') {
+            pos += string.find('"').map_or(rest.len(), |e| e + 2);
+        } else {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    comments
+}
+
+/// Format attributes of the form `@static(1)` followed by a space.
+fn at_attrs(attrs: &ir::Attributes) -> String {
+    if attrs.is_empty() {
+        "".to_string()
+    } else {
+        format!("{} ", IRPrinter::format_at_attributes(attrs))
+    }
+}
+
+/// Format the signature of a component or a primitive.
+fn signature(ports: &[ir::PortDef]) -> String {
+    let fmt = |dir: ir::Direction| {
+        ports
+            .iter()
+            .filter(|pd| pd.direction == dir)
+            .map(|pd| {
+                let width = match &pd.width {
+                    ir::Width::Const { value } => value.to_string(),
+                    ir::Width::Param { value } => value.to_string(),
+                };
+                format!("{}{}: {}", at_attrs(&pd.attributes), pd.name, width)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!(
+        "({}) -> ({})",
+        fmt(ir::Direction::Input),
+        fmt(ir::Direction::Output)
+    )
+}
+
+fn cell_str(cell: &ast::Cell) -> String {
+    format!(
+        "{}{}{} = {}({});",
+        at_attrs(&cell.attributes),
+        if cell.reference { "ref " } else { "" },
+        cell.name,
+        cell.prototype.name,
+        cell.prototype
+            .params
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+fn wire_str(wire: &ast::Wire) -> String {
+    match &wire.src.guard {
+        Some(guard) => format!(
+            "{} = {} ? {};",
+            port_str(&wire.dest),
+            guard_str(guard),
+            atom_str(&wire.src.expr)
+        ),
+        None => {
+            format!("{} = {};", port_str(&wire.dest), atom_str(&wire.src.expr))
+        }
+    }
+}
+
+fn port_str(port: &ast::Port) -> String {
+    match port {
+        ast::Port::Comp { component, port } => {
+            format!("{}.{}", component, port)
+        }
+        ast::Port::This { port } => port.to_string(),
+        ast::Port::Hole { group, name } => format!("{}[{}]", group, name),
+    }
+}
+
+fn num_str(num: &BitNum) -> String {
+    match num.num_type {
+        NumType::Decimal => format!("{}'d{}", num.width, num.val),
+        NumType::Binary => format!("{}'b{:b}", num.width, num.val),
+        NumType::Octal => format!("{}'o{:o}", num.width, num.val),
+        NumType::Hex => format!("{}'x{:x}", num.width, num.val),
+    }
+}
+
+fn atom_str(atom: &Atom) -> String {
+    match atom {
+        Atom::Port(port) => port_str(port),
+        Atom::Num(num) => num_str(num),
+    }
+}
+
+/// Binding strength of a guard expression.
+fn precedence(guard: &GuardExpr) -> u8 {
+    match guard {
+        GuardExpr::Or(..) => 0,
+        GuardExpr::And(..) => 1,
+        _ => 2,
+    }
+}
+
+fn guard_str(guard: &GuardExpr) -> String {
+    let binop = |l: &GuardExpr, r: &GuardExpr, op: &str| {
+        let prec = precedence(guard);
+        let left = if precedence(l) < prec {
+            format!("({})", guard_str(l))
+        } else {
+            guard_str(l)
+        };
+        // Operators are left associative.
+        let right = if precedence(r) <= prec {
+            format!("({})", guard_str(r))
+        } else {
+            guard_str(r)
+        };
+        format!("{} {} {}", left, op, right)
+    };
+    let cmp = |l: &Atom, r: &Atom, op: &str| {
+        format!("{} {} {}", atom_str(l), op, atom_str(r))
+    };
+    match guard {
+        GuardExpr::Or(l, r) => binop(l, r, "|"),
+        GuardExpr::And(l, r) => binop(l, r, "&"),
+        GuardExpr::Not(inner) => match &**inner {
+            GuardExpr::Atom(atom) => format!("!{}", atom_str(atom)),
+            inner => format!("!({})", guard_str(inner)),
+        },
+        GuardExpr::Eq(l, r) => cmp(l, r, "=="),
+        GuardExpr::Neq(l, r) => cmp(l, r, "!="),
+        GuardExpr::Gt(l, r) => cmp(l, r, ">"),
+        GuardExpr::Lt(l, r) => cmp(l, r, "<"),
+        GuardExpr::Geq(l, r) => cmp(l, r, ">="),
+        GuardExpr::Leq(l, r) => cmp(l, r, "<="),
+        GuardExpr::Atom(atom) => atom_str(atom),
+    }
+}
@@ -5,6 +5,7 @@
 //! in the `ir` module.
 
 pub mod ast;
+pub mod formatter;
 pub mod parser;
 
 pub use ast::NamespaceDef;
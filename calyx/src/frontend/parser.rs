@@ -12,9 +12,15 @@ use std::path::Path;
 use std::rc::Rc;
 
 type ParseResult<T> = Result<T, Error<Rule>>;
-// user data is the input program so that we can create ir::Id's
-// that have a reference to the input string
-type Node<'i> = pest_consume::Node<'i, Rule, Rc<str>>;
+// user data is the name of the input file and the input program so that we
+// can create ir::Id's that have a reference to the input string
+type Node<'i> = pest_consume::Node<'i, Rule, (Rc<str>, Rc<str>)>;
+
+/// The span of `input` in the input program.
+fn span(input: &Node) -> Span {
+    let (file, program) = input.user_data();
+    Span::new(input.as_span(), Rc::clone(file), Rc::clone(program))
+}
 
 // include the grammar file so that Cargo knows to rebuild this file on grammar changes
 const _GRAMMAR: &str = include_str!("futil_syntax.pest");
@@ -47,14 +53,7 @@ impl FutilParser {
             ))
         })?;
         let string_content = std::str::from_utf8(content)?;
-        let inputs = FutilParser::parse_with_userdata(
-            Rule::file,
-            string_content,
-            Rc::from(string_content),
-        )
-        .map_err(|e| e.with_path(&path.to_string_lossy()))?;
-        let input = inputs.single()?;
-        Ok(FutilParser::file(input)?)
+        Self::parse_source(string_content, Some(path))
     }
 
     pub fn parse<R: Read>(mut r: R) -> FutilResult<ast::NamespaceDef> {
@@ -65,14 +64,138 @@ impl FutilParser {
                 err.to_string()
             ))
         })?;
-        let inputs = FutilParser::parse_with_userdata(
-            Rule::file,
-            &buf,
-            Rc::from(buf.as_str()),
-        )?;
-        let input = inputs.single()?;
-        Ok(FutilParser::file(input)?)
+        Self::parse_source(&buf, None)
+    }
+
+    /// Parse a complete Calyx program from `source`.
+    /// If parsing fails, each top-level component and extern definition is
+    /// parsed separately so that the errors in all of them are reported.
+    fn parse_source(
+        source: &str,
+        path: Option<&Path>,
+    ) -> FutilResult<ast::NamespaceDef> {
+        let file: Rc<str> = match path {
+            Some(p) => Rc::from(p.to_string_lossy()),
+            None => Rc::from(errors::STDIN),
+        };
+        let with_path = |e: Error<Rule>| match path {
+            Some(_) => e.with_path(&file),
+            None => e,
+        };
+        let parse = |src: &str| -> ParseResult<ast::NamespaceDef> {
+            let inputs = FutilParser::parse_with_userdata(
+                Rule::file,
+                src,
+                (Rc::clone(&file), Rc::from(src)),
+            )
+            .map_err(with_path)?;
+            let input = inputs.single()?;
+            FutilParser::file(input)
+        };
+
+        let err = match parse(source) {
+            Ok(namespace) => return Ok(namespace),
+            Err(err) => err,
+        };
+
+        let items = top_level_items(source);
+        let errs: Vec<errors::Error> = if items.len() > 1 {
+            (0..items.len())
+                .filter_map(|idx| {
+                    parse(&blank_other_items(source, &items, idx)).err()
+                })
+                .map(|e| e.into())
+                .collect()
+        } else {
+            vec![]
+        };
+
+        if errs.is_empty() {
+            Err(err.into())
+        } else {
+            Err(errors::Error::combine(errs))
+        }
+    }
+}
+
+/// Returns the byte ranges of the top-level `component` and `extern`
+/// definitions in `source`.
+///
+/// A definition starts with a `component` or `extern` keyword that is either
+/// outside of any braces or at the start of a line. The second case allows
+/// recovery from definitions with unbalanced braces.
+fn top_level_items(source: &str) -> Vec<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut starts = vec![];
+    let mut depth: usize = 0;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let rest = &source[idx..];
+        if rest.starts_with("//") {
+            idx += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        } else if rest.starts_with("/*") {
+            idx += rest.find("*/").map(|e| e + 2).unwrap_or(rest.len());
+            continue;
+        } else if let Some(string) = rest.strip_prefix('"') {
+            idx += string.find('"').map(|e| e + 2).unwrap_or(rest.len());
+            continue;
+        }
+        match bytes[idx] {
+            b'{' => depth += 1,
+            b'}' => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+        let line_start = idx == 0 || bytes[idx - 1] == b'\n';
+        let word_start = idx == 0 || !is_ident(bytes[idx - 1]);
+        if word_start && (depth == 0 || line_start) {
+            for keyword in &["component", "extern"] {
+                let end = idx + keyword.len();
+                if rest.starts_with(keyword)
+                    && matches!(bytes.get(end), Some(b) if !is_ident(*b))
+                {
+                    starts.push(idx);
+                    depth = 0;
+                }
+            }
+        }
+        idx += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| {
+            (*start, starts.get(i + 1).copied().unwrap_or(source.len()))
+        })
+        .collect()
+}
+
+/// Replace all the definitions in `items` other than the one at `keep` with
+/// whitespace. Newlines are retained so that error positions are unchanged.
+fn blank_other_items(
+    source: &str,
+    items: &[(usize, usize)],
+    keep: usize,
+) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut last = 0;
+    for (idx, (start, end)) in items.iter().enumerate() {
+        if idx == keep {
+            continue;
+        }
+        out.push_str(&source[last..*start]);
+        for c in source[*start..*end].chars() {
+            if c == '\n' {
+                out.push(c);
+            } else {
+                out.push_str(&" ".repeat(c.len_utf8()));
+            }
+        }
+        last = *end;
     }
+    out.push_str(&source[last..]);
+    out
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -91,12 +214,13 @@ impl FutilParser {
         Ok(())
     }
 
+    fn reference(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
     // ================ Literals =====================
     fn identifier(input: Node) -> ParseResult<ir::Id> {
-        Ok(ir::Id::new(
-            input.as_str(),
-            Some(Span::new(input.as_span(), Rc::clone(input.user_data()))),
-        ))
+        Ok(ir::Id::new(input.as_str(), Some(span(&input))))
     }
 
     fn bitwidth(input: Node) -> ParseResult<u64> {
@@ -135,37 +259,25 @@ impl FutilParser {
                     width,
                     num_type: NumType::Decimal,
                     val,
-                    span: Some(Span::new(
-                        input.as_span(),
-                        Rc::clone(input.user_data()),
-                    )),
+                    span: Some(span(&input)),
                 },
             [bitwidth(width), hex(val)] => BitNum {
                     width,
                     num_type: NumType::Hex,
                     val,
-                    span: Some(Span::new(
-                        input.as_span(),
-                        Rc::clone(input.user_data()),
-                    )),
+                    span: Some(span(&input)),
                 },
             [bitwidth(width), octal(val)] => BitNum {
                     width,
                     num_type: NumType::Octal,
                     val,
-                    span: Some(Span::new(
-                        input.as_span(),
-                        Rc::clone(input.user_data()),
-                    )),
+                    span: Some(span(&input)),
                 },
             [bitwidth(width), binary(val)] => BitNum {
                     width,
                     num_type: NumType::Binary,
                     val,
-                    span: Some(Span::new(
-                        input.as_span(),
-                        Rc::clone(input.user_data()),
-                    )),
+                    span: Some(span(&input)),
                 },
 
         );
@@ -339,7 +451,9 @@ impl FutilParser {
         Ok(match_nodes!(
             input.into_children();
             [at_attributes(attrs), identifier(id), identifier(prim), args(args)] =>
-            ast::Cell::from(id, prim, args, attrs)
+            ast::Cell::from(id, prim, args, attrs, false),
+            [at_attributes(attrs), reference(_), identifier(id), identifier(prim), args(args)] =>
+            ast::Cell::from(id, prim, args, attrs, true)
         ))
     }
 
@@ -533,12 +647,27 @@ impl FutilParser {
         ))
     }
 
+    fn invoke_ref_arg(input: Node) -> ParseResult<(ir::Id, ir::Id)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(name), identifier(cell)] => (name, cell)
+        ))
+    }
+
+    fn invoke_ref_args(input: Node) -> ParseResult<Vec<(ir::Id, ir::Id)>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [invoke_ref_arg(args)..] => args.collect()
+        ))
+    }
+
     fn invoke(input: Node) -> ParseResult<ast::Control> {
         Ok(match_nodes!(
             input.into_children();
-            [at_attributes(attrs), identifier(comp), invoke_args(inputs), invoke_args(outputs)] =>
+            [at_attributes(attrs), identifier(comp), invoke_ref_args(ref_cells), invoke_args(inputs), invoke_args(outputs)] =>
                 ast::Control::Invoke {
                     comp,
+                    ref_cells,
                     inputs,
                     outputs,
                     attributes: attrs
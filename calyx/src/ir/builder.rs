@@ -207,6 +207,7 @@ impl<'a> Builder<'a> {
             dst,
             src,
             guard: Box::new(guard),
+            origin: None,
         }
     }
 
@@ -297,6 +298,7 @@ impl<'a> Builder<'a> {
             // with_capacity(0) does not allocate space.
             // Same as HashMap::with_capacity
             attributes: ir::Attributes::default(),
+            reference: false,
         }));
         ports
             .into_iter()
@@ -7,12 +7,26 @@ use linked_hash_map::LinkedHashMap;
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::iter::Extend;
+use std::ops::RangeInclusive;
 use std::rc::Rc;
 
 /// The default name of the signature cell in a component.
 /// In general, this should not be used by anything.
 const THIS_ID: &str = "_this";
 
+/// The states of an FSM register generated when compiling a control program.
+/// Maps the values of the register back to the control program.
+#[derive(Debug, Clone)]
+pub struct FsmStates {
+    /// The register storing the state.
+    pub cell: Id,
+    /// The group implementing the FSM.
+    pub group: Id,
+    /// The control statements active in each range of states, ordered by
+    /// the first state in the range.
+    pub states: Vec<(RangeInclusive<u64>, Vec<String>)>,
+}
+
 /// In memory representation of a Component.
 #[derive(Debug)]
 pub struct Component {
@@ -31,6 +45,8 @@ pub struct Component {
     pub control: RRC<Control>,
     /// Attributes for this component
     pub attributes: Attributes,
+    /// The FSMs generated when compiling the control program.
+    pub fsm_states: Vec<FsmStates>,
 
     ///// Internal structures
     /// Namegenerator that contains the names currently defined in this
@@ -78,6 +94,7 @@ impl Component {
             control: Rc::new(RefCell::new(Control::empty())),
             namegen: utils::NameGenerator::with_prev_defined_names(port_names),
             attributes: Attributes::default(),
+            fsm_states: vec![],
         }
     }
 
@@ -55,6 +55,12 @@ pub struct Context {
     pub debug_mode: bool,
     /// Enables synthesis mode.
     pub synthesis_mode: bool,
+    /// Do not emit `initial` and `final` blocks.
+    pub disable_init: bool,
+    /// Annotate generated Verilog with the Calyx assignments it implements.
+    pub source_comments: bool,
+    /// Options for passes of the form `<pass>:<option>[=<value>]`.
+    pub extra_opts: Vec<String>,
     /// Original import statements.
     pub imports: Vec<String>,
 }
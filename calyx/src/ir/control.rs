@@ -62,12 +62,15 @@ pub struct Enable {
 }
 
 type PortMap = Vec<(Id, RRC<Port>)>;
+type CellMap = Vec<(Id, RRC<Cell>)>;
 
 /// Data for an `invoke` control statement.
 #[derive(Debug)]
 pub struct Invoke {
     /// Cell that is being invoked.
     pub comp: RRC<Cell>,
+    /// Mapping from name of `ref` cells in `comp` to the cell bound to it.
+    pub ref_cells: CellMap,
     /// Mapping from name of input ports in `comp` to the port connected to it.
     pub inputs: PortMap,
     /// Mapping from name of output ports in `comp` to the port connected to it.
@@ -160,6 +163,7 @@ impl Control {
     pub fn invoke(comp: RRC<Cell>, inputs: PortMap, outputs: PortMap) -> Self {
         Control::Invoke(Invoke {
             comp,
+            ref_cells: Vec::new(),
             inputs,
             outputs,
             attributes: Attributes::default(),
@@ -1,7 +1,7 @@
 use super::{
     Assignment, Attributes, Builder, CellType, Component, Context, Control,
-    Direction, GetAttributes, Guard, Id, LibrarySignatures, Port, PortDef,
-    Width, RRC,
+    Direction, GetAttributes, Guard, Id, Invoke, LibrarySignatures, Port,
+    PortDef, Width, RRC,
 };
 use crate::{
     errors::{Error, FutilResult},
@@ -28,35 +28,28 @@ struct SigCtx {
 fn check_signature(sig: &[PortDef]) -> FutilResult<()> {
     let mut inputs: HashSet<&Id> = HashSet::new();
     let mut outputs: HashSet<&Id> = HashSet::new();
+    let mut errors = vec![];
     for pd in sig {
         // check for uniqueness
-        match pd.direction {
-            Direction::Input => {
-                if !inputs.contains(&pd.name) {
-                    inputs.insert(&pd.name);
-                } else {
-                    return Err(Error::AlreadyBound(
-                        pd.name.clone(),
-                        "component".to_string(),
-                    ));
-                }
-            }
-            Direction::Output => {
-                if !outputs.contains(&pd.name) {
-                    outputs.insert(&pd.name);
-                } else {
-                    return Err(Error::AlreadyBound(
-                        pd.name.clone(),
-                        "component".to_string(),
-                    ));
-                }
-            }
+        let defined = match pd.direction {
+            Direction::Input => &mut inputs,
+            Direction::Output => &mut outputs,
             Direction::Inout => {
                 panic!("Components shouldn't have inout ports.")
             }
+        };
+        if !defined.insert(&pd.name) {
+            errors.push(Error::AlreadyBound(
+                pd.name.clone(),
+                "component".to_string(),
+            ));
         }
     }
-    Ok(())
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::combine(errors))
+    }
 }
 
 /// Definition of special interface ports.
@@ -68,7 +61,7 @@ const INTERFACE_PORTS: [(&str, u64, Direction); 4] = [
 ];
 
 /// Extend the signature with magical ports.
-fn extend_signature(sig: &mut Vec<PortDef>) {
+pub fn extend_signature(sig: &mut Vec<PortDef>) {
     let port_names: HashSet<_> =
         sig.iter().map(|pd| pd.name.to_string()).collect();
     let mut namegen = NameGenerator::with_prev_defined_names(port_names);
@@ -97,6 +90,7 @@ pub fn ast_to_ir(
     let mut all_names: HashSet<&Id> = HashSet::with_capacity(
         namespace.components.len() + namespace.externs.len(),
     );
+    let mut errors = vec![];
 
     let prim_names = namespace
         .externs
@@ -105,13 +99,12 @@ pub fn ast_to_ir(
     let comp_names = namespace.components.iter().map(|comp| &comp.name);
 
     for bound in prim_names.chain(comp_names) {
-        if all_names.contains(bound) {
-            return Err(Error::AlreadyBound(
+        if !all_names.insert(bound) {
+            errors.push(Error::AlreadyBound(
                 bound.clone(),
                 "component or primitive".to_string(),
             ));
         }
-        all_names.insert(bound);
     }
 
     // Build the signature context
@@ -122,7 +115,9 @@ pub fn ast_to_ir(
 
     // Add component signatures to context
     for comp in &mut namespace.components {
-        check_signature(&comp.signature)?;
+        if let Err(err) = check_signature(&comp.signature) {
+            errors.push(err);
+        }
         // extend the signature
         extend_signature(&mut comp.signature);
         sig_ctx
@@ -130,11 +125,21 @@ pub fn ast_to_ir(
             .insert(comp.name.clone(), comp.signature.clone());
     }
 
-    let comps = namespace
-        .components
-        .into_iter()
-        .map(|comp| build_component(comp, &sig_ctx))
-        .collect::<Result<_, _>>()?;
+    // Build all the components, collecting the errors from each of them.
+    let comps = Error::collect(
+        namespace
+            .components
+            .into_iter()
+            .map(|comp| build_component(comp, &sig_ctx)),
+    );
+    let comps = match comps {
+        Ok(comps) if errors.is_empty() => comps,
+        Ok(_) => return Err(Error::combine(errors)),
+        Err(err) => {
+            errors.push(err);
+            return Err(Error::combine(errors));
+        }
+    };
 
     Ok(Context {
         components: comps,
@@ -142,6 +147,9 @@ pub fn ast_to_ir(
         imports: namespace.imports,
         debug_mode,
         synthesis_mode,
+        disable_init: false,
+        source_comments: false,
+        extra_opts: vec![],
     })
 }
 
@@ -151,22 +159,22 @@ fn validate_component(
 ) -> FutilResult<()> {
     let mut cells = HashSet::new();
     let mut groups = HashSet::new();
+    let mut errors = vec![];
 
     for cell in &comp.cells {
-        if cells.contains(&cell.name) {
-            return Err(Error::AlreadyBound(
+        if !cells.insert(cell.name.clone()) {
+            errors.push(Error::AlreadyBound(
                 cell.name.clone(),
                 "cell".to_string(),
             ));
         }
-        cells.insert(cell.name.clone());
 
         let proto_name = &cell.prototype.name;
 
         if sig_ctx.lib.find_primitive(&proto_name).is_none()
             && !sig_ctx.comp_sigs.contains_key(&proto_name)
         {
-            return Err(Error::Undefined(
+            errors.push(Error::Undefined(
                 proto_name.clone(),
                 "primitive or component".to_string(),
             ));
@@ -176,15 +184,18 @@ fn validate_component(
     for group in &comp.groups {
         let name = &group.name;
         if groups.contains(name) {
-            return Err(Error::AlreadyBound(name.clone(), "group".to_string()));
-        }
-        if cells.contains(name) {
-            return Err(Error::AlreadyBound(name.clone(), "cell".to_string()));
+            errors.push(Error::AlreadyBound(name.clone(), "group".to_string()));
+        } else if cells.contains(name) {
+            errors.push(Error::AlreadyBound(name.clone(), "cell".to_string()));
         }
         groups.insert(name.clone());
     }
 
-    Ok(())
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::combine(errors))
+    }
 }
 
 /// Build an `ir::component::Component` using an `frontend::ast::ComponentDef`.
@@ -216,21 +227,36 @@ fn build_component(
         .into_iter()
         .for_each(|cell| add_cell(cell, &sig_ctx, &mut builder));
 
-    comp.groups
-        .into_iter()
-        .try_for_each(|g| add_group(g, &mut builder))?;
+    // Errors in the groups, continuous assignments, and control program are
+    // independent of each other so we report all of them.
+    let mut errors = vec![];
 
-    let continuous_assignments = comp
-        .continuous_assignments
-        .into_iter()
-        .map(|w| build_assignment(w, &mut builder))
-        .collect::<FutilResult<Vec<_>>>()?;
-    builder.component.continuous_assignments = continuous_assignments;
+    let groups = comp.groups.into_iter().map(|g| add_group(g, &mut builder));
+    if let Err(err) = Error::collect(groups) {
+        errors.push(err);
+    }
+
+    let continuous_assignments = Error::collect(
+        comp.continuous_assignments
+            .into_iter()
+            .map(|w| build_assignment(w, &mut builder)),
+    );
+    match continuous_assignments {
+        Ok(assigns) => builder.component.continuous_assignments = assigns,
+        Err(err) => errors.push(err),
+    }
 
     // Build the Control ast using ast::Control.
-    let control =
-        Rc::new(RefCell::new(build_control(comp.control, &mut builder)?));
-    builder.component.control = control;
+    match build_control(comp.control, &mut builder) {
+        Ok(control) => {
+            builder.component.control = Rc::new(RefCell::new(control))
+        }
+        Err(err) => errors.push(err),
+    }
+
+    if !errors.is_empty() {
+        return Err(Error::combine(errors));
+    }
 
     ir_component.attributes = comp.attributes;
 
@@ -271,7 +297,9 @@ fn add_cell(cell: ast::Cell, sig_ctx: &SigCtx, builder: &mut Builder) {
     };
 
     // Add attributes to the built cell
-    res.borrow_mut().attributes = cell.attributes;
+    let mut res = res.borrow_mut();
+    res.attributes = cell.attributes;
+    res.reference = cell.reference;
 }
 
 ///////////////// Group Construction /////////////////////////
@@ -282,10 +310,13 @@ fn add_group(group: ast::Group, builder: &mut Builder) -> FutilResult<()> {
     ir_group.borrow_mut().attributes = group.attributes;
 
     // Add assignemnts to the group
-    for wire in group.wires {
-        let assign = build_assignment(wire, builder)?;
-        ir_group.borrow_mut().assignments.push(assign)
-    }
+    let assigns = Error::collect(
+        group
+            .wires
+            .into_iter()
+            .map(|wire| build_assignment(wire, builder)),
+    )?;
+    ir_group.borrow_mut().assignments = assigns;
 
     Ok(())
 }
@@ -394,6 +425,7 @@ fn build_control(
         }
         ast::Control::Invoke {
             comp: component,
+            ref_cells,
             inputs,
             outputs,
             attributes,
@@ -411,27 +443,37 @@ fn build_control(
                 .into_iter()
                 .map(|(id, port)| atom_to_port(port, builder).map(|p| (id, p)))
                 .collect::<Result<_, _>>()?;
-            let mut inv = Control::invoke(cell, inps, outs);
-            *(inv.get_mut_attributes().unwrap()) = attributes;
-            inv
+            let refs = ref_cells
+                .into_iter()
+                .map(|(id, name)| {
+                    builder
+                        .component
+                        .find_cell(&name)
+                        .ok_or_else(|| {
+                            Error::Undefined(name.clone(), "cell".to_string())
+                        })
+                        .map(|c| (id, c))
+                })
+                .collect::<Result<_, _>>()?;
+            Control::Invoke(Invoke {
+                comp: cell,
+                ref_cells: refs,
+                inputs: inps,
+                outputs: outs,
+                attributes,
+            })
         }
         ast::Control::Seq { stmts, attributes } => {
-            let mut s = Control::seq(
-                stmts
-                    .into_iter()
-                    .map(|c| build_control(c, builder))
-                    .collect::<FutilResult<Vec<_>>>()?,
-            );
+            let mut s = Control::seq(Error::collect(
+                stmts.into_iter().map(|c| build_control(c, builder)),
+            )?);
             *(s.get_mut_attributes().unwrap()) = attributes;
             s
         }
         ast::Control::Par { stmts, attributes } => {
-            let mut p = Control::par(
-                stmts
-                    .into_iter()
-                    .map(|c| build_control(c, builder))
-                    .collect::<FutilResult<Vec<_>>>()?,
-            );
+            let mut p = Control::par(Error::collect(
+                stmts.into_iter().map(|c| build_control(c, builder)),
+            )?);
             *(p.get_mut_attributes().unwrap()) = attributes;
             p
         }
@@ -22,6 +22,11 @@ impl Id {
         }
     }
 
+    /// The location of this identifier in the source program, if known.
+    pub fn span(&self) -> Option<&Span> {
+        self.span.as_ref()
+    }
+
     pub fn fmt_err(&self, err_msg: &str) -> String {
         match &self.span {
             Some(span) => span.format(err_msg),
@@ -23,7 +23,7 @@ mod structure;
 pub use attribute::{Attributes, GetAttributes};
 pub use builder::Builder;
 pub use common::{RRC, WRC};
-pub use component::Component;
+pub use component::{Component, FsmStates};
 pub use context::{Context, LibrarySignatures};
 pub use control::{Control, Empty, Enable, If, Invoke, Par, Seq, While};
 pub use guard::Guard;
@@ -11,7 +11,7 @@ pub struct IRPrinter;
 impl IRPrinter {
     /// Format attributes of the form `@static(1)`.
     /// Returns the empty string if the `attrs` is empty.
-    fn format_at_attributes(attrs: &ir::Attributes) -> String {
+    pub(crate) fn format_at_attributes(attrs: &ir::Attributes) -> String {
         attrs
             .attrs
             .iter()
@@ -28,7 +28,7 @@ impl IRPrinter {
 
     /// Format attributes of the form `<"static"=1>`.
     /// Returns the empty string if the `attrs` is empty.
-    fn format_attributes(attrs: &ir::Attributes) -> String {
+    pub(crate) fn format_attributes(attrs: &ir::Attributes) -> String {
         if attrs.is_empty() {
             "".to_string()
         } else {
@@ -140,6 +140,9 @@ impl IRPrinter {
                         Self::format_at_attributes(&cell.attributes)
                     )?
                 }
+                if cell.is_reference() {
+                    write!(f, "ref ")?
+                }
                 write!(f, "{} = ", cell.name().id)?;
                 writeln!(
                     f,
@@ -161,6 +164,9 @@ impl IRPrinter {
                         Self::format_at_attributes(&cell.attributes)
                     )?
                 }
+                if cell.is_reference() {
+                    write!(f, "ref ")?
+                }
                 writeln!(f, "{} = {}();", cell.name().id, name)
             }
             ir::CellType::Constant { .. } => Ok(()),
@@ -218,6 +224,7 @@ impl IRPrinter {
             }
             ir::Control::Invoke(ir::Invoke {
                 comp,
+                ref_cells,
                 inputs,
                 outputs,
                 attributes,
@@ -225,7 +232,23 @@ impl IRPrinter {
                 if !attributes.is_empty() {
                     write!(f, "{} ", Self::format_at_attributes(&attributes))?
                 }
-                write!(f, "invoke {}(", comp.borrow().name())?;
+                write!(f, "invoke {}", comp.borrow().name())?;
+                if !ref_cells.is_empty() {
+                    write!(
+                        f,
+                        "[{}]",
+                        ref_cells
+                            .iter()
+                            .map(|(arg, cell)| format!(
+                                "{}={}",
+                                arg,
+                                cell.borrow().name()
+                            ))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )?;
+                }
+                write!(f, "(")?;
                 for (i, (arg, port)) in inputs.iter().enumerate() {
                     write!(
                         f,
@@ -370,7 +393,7 @@ impl IRPrinter {
     }
 
     /// Get the port access expression.
-    fn get_port_access(port: &ir::Port) -> String {
+    pub fn get_port_access(port: &ir::Port) -> String {
         match &port.parent {
             ir::PortParent::Cell(cell_wref) => {
                 let cell_ref =
@@ -131,6 +131,8 @@ pub struct Cell {
     pub prototype: CellType,
     /// Attributes for this group.
     pub(super) attributes: Attributes,
+    /// True if this is a reference cell bound by the caller of the component.
+    pub(super) reference: bool,
 }
 
 impl GetAttributes for Cell {
@@ -249,6 +251,17 @@ impl Cell {
     pub fn name(&self) -> &Id {
         &self.name
     }
+
+    /// Returns true if this cell is a `ref` cell that is bound by the
+    /// caller of the component.
+    pub fn is_reference(&self) -> bool {
+        self.reference
+    }
+
+    /// Mark this cell as a `ref` cell.
+    pub fn set_reference(&mut self, reference: bool) {
+        self.reference = reference;
+    }
 }
 
 /// Represents a guarded assignment in the program
@@ -262,6 +275,10 @@ pub struct Assignment {
 
     /// The guard for this assignment.
     pub guard: Box<Guard>,
+
+    /// The group this assignment was defined in. Set when groups are removed
+    /// by the `hole-inliner` pass.
+    pub origin: Option<Id>,
 }
 
 /// A Group of assignments that perform a logical action.
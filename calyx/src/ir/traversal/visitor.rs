@@ -3,8 +3,9 @@
 //! [`ir::Context`] to compile every [`ir::Component`] using the pass.
 use super::action::{Action, VisResult};
 use super::PostOrder;
-use crate::errors::FutilResult;
+use crate::errors::{Error, FutilResult};
 use crate::ir::{self, Component, Context, Control, LibrarySignatures};
+use std::collections::HashMap;
 use std::rc::Rc;
 
 /// Trait that describes named things. Calling [`do_pass`](Visitor::do_pass) and [`do_pass_default`](Visitor::do_pass_default).
@@ -18,6 +19,12 @@ pub trait Named {
 
     /// A short description of the pass.
     fn description() -> &'static str;
+
+    /// The options accepted by the pass and their descriptions. Options are
+    /// provided on the command line using `-x <pass>:<option>[=<value>]`.
+    fn opts() -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
 }
 
 /// Implementator of trait provide various logging methods.
@@ -56,12 +63,55 @@ where
 /// For most passes that don't need to use, this is just going to use the
 /// default() method.
 pub trait ConstructVisitor {
-    fn from(_ctx: &ir::Context) -> Self;
+    fn from(_ctx: &ir::Context) -> FutilResult<Self>
+    where
+        Self: Sized;
+
+    /// The options given to this pass in [`ir::Context::extra_opts`]. Options
+    /// given without a value are mapped to `"true"`.
+    /// Returns an error if an option is not listed in [Named::opts].
+    fn get_opts(ctx: &ir::Context) -> FutilResult<HashMap<&'static str, String>>
+    where
+        Self: Named,
+    {
+        let mut opts = HashMap::new();
+        for extra in &ctx.extra_opts {
+            let opt = match extra.strip_prefix(Self::name()) {
+                Some(rest) => match rest.strip_prefix(':') {
+                    Some(opt) => opt,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let (key, value) = match opt.split_once('=') {
+                Some((key, value)) => (key, value.to_string()),
+                None => (opt, "true".to_string()),
+            };
+            match Self::opts().iter().find(|(name, _)| *name == key) {
+                Some((name, _)) => {
+                    opts.insert(*name, value);
+                }
+                None => {
+                    return Err(Error::Misc(format!(
+                        "Unknown option {} for pass {}. Valid options: {}.",
+                        key,
+                        Self::name(),
+                        Self::opts()
+                            .iter()
+                            .map(|(name, _)| *name)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )))
+                }
+            }
+        }
+        Ok(opts)
+    }
 }
 
 impl<T: Default + Sized + Visitor> ConstructVisitor for T {
-    fn from(_ctx: &ir::Context) -> Self {
-        T::default()
+    fn from(_ctx: &ir::Context) -> FutilResult<Self> {
+        Ok(T::default())
     }
 }
 
@@ -85,6 +135,18 @@ pub trait Visitor {
         false
     }
 
+    /// Returns true if this pass should continue traversing the remaining
+    /// components after a component returns an error.
+    /// Used by validation passes to report all the independent errors in a
+    /// program at once.
+    #[inline(always)]
+    fn collect_errors() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
+
     /// Define the traversal over a component.
     /// Calls [Visitor::start], visits each control node, and finally calls
     /// [Visitor::finish].
@@ -125,24 +187,39 @@ pub trait Visitor {
         Self: Sized,
     {
         let signatures = &context.lib;
+        let mut errors = vec![];
+
+        // Traverse a component and record the error if this pass collects
+        // errors.
+        let mut upd = |comp: &mut Component| match self
+            .traverse_component(comp, signatures)
+        {
+            Err(err) if Self::collect_errors() => {
+                errors.push(err);
+                Ok(())
+            }
+            res => res,
+        };
 
         if Self::require_postorder() {
             // Temporarily take ownership of components from context.
             let comps = context.components.drain(..).collect();
             let mut po = PostOrder::new(comps);
-            po.apply_update(|comp| self.traverse_component(comp, signatures))?;
+            po.apply_update(&mut upd)?;
             context.components = po.take();
         } else {
             context
                 .components
                 // Mutably borrow the components in the context
                 .iter_mut()
-                .try_for_each(|comp| {
-                    self.traverse_component(comp, signatures)
-                })?;
+                .try_for_each(upd)?;
         }
 
-        Ok(())
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::combine(errors))
+        }
     }
 
     /// Build a [Default] implementation of this pass and call [`Visitor::do_pass`]
@@ -152,7 +229,7 @@ pub trait Visitor {
     where
         Self: ConstructVisitor + Sized,
     {
-        let mut visitor = Self::from(&*context);
+        let mut visitor = Self::from(&*context)?;
         visitor.do_pass(context)?;
         Ok(visitor)
     }
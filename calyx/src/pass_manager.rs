@@ -9,6 +9,9 @@ use std::collections::{HashMap, HashSet};
 /// Top-level type for all passes that transform an [ir::Context]
 pub type PassClosure = Box<dyn Fn(&mut ir::Context) -> FutilResult<()>>;
 
+/// Options accepted by a pass and their descriptions.
+pub type PassOpts = &'static [(&'static str, &'static str)];
+
 /// Structure that tracks all registered passes for the compiler.
 #[derive(Default)]
 pub struct PassManager {
@@ -17,6 +20,9 @@ pub struct PassManager {
 
     /// Tracks alias for groups of passes that run together.
     aliases: HashMap<String, Vec<String>>,
+
+    /// Options accepted by each pass.
+    opts: HashMap<String, PassOpts>,
 }
 
 impl PassManager {
@@ -37,6 +43,13 @@ impl PassManager {
         Ok(())
     }
 
+    /// Record the options accepted by the pass `name`.
+    pub fn add_opts(&mut self, name: String, opts: PassOpts) {
+        if !opts.is_empty() {
+            self.opts.insert(name, opts);
+        }
+    }
+
     /// Adds a new alias for groups of passes. An alias is a list of strings
     /// that represent valid pass names OR an alias.
     /// The passes and aliases are executed in the order of specification.
@@ -80,6 +93,11 @@ impl PassManager {
         pass_names.iter().for_each(|pass| {
             ret.push_str(&format!("- {}", pass));
             ret.push('\n');
+            for (opt, desc) in self.opts.get(*pass).into_iter().flat_map(|o| *o)
+            {
+                ret.push_str(&format!("    -x {}:{}: {}", pass, opt, desc));
+                ret.push('\n');
+            }
         });
 
         // Push all aliases
@@ -129,6 +147,30 @@ impl PassManager {
         (passes, excl_set)
     }
 
+    /// Check that every option given with `-x` is of the form
+    /// `<pass>:<option>` and names a registered pass. The options themselves
+    /// are validated by the passes that use them.
+    fn validate_opts(&self, extra_opts: &[String]) -> FutilResult<()> {
+        for opt in extra_opts {
+            match opt.split_once(':') {
+                Some((pass, _)) if self.passes.contains_key(pass) => (),
+                Some((pass, _)) => {
+                    return Err(Error::Misc(format!(
+                        "Unknown pass {} in option {}. Use --list-passes to see the available passes.",
+                        pass, opt
+                    )))
+                }
+                None => {
+                    return Err(Error::Misc(format!(
+                        "Malformed option {}. Options must be of the form <pass>:<option>.",
+                        opt
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Executes a given "plan" constructed using the incl and excl lists.
     pub fn execute_plan(
         &self,
@@ -136,6 +178,7 @@ impl PassManager {
         incl: &[String],
         excl: &[String],
     ) -> FutilResult<()> {
+        self.validate_opts(&ctx.extra_opts)?;
         let (passes, excl_set) = self.create_plan(incl, excl);
         for name in passes {
             if let Some(pass) = self.passes.get(&name) {
@@ -163,6 +206,7 @@ macro_rules! register_pass {
             $pass::do_pass_default(ir)?;
             Ok(())
         });
+        $manager.add_opts(name.clone(), $pass::opts());
         $manager.add_pass(name, pass_closure)?;
     };
 }
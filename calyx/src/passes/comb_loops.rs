@@ -0,0 +1,149 @@
+use crate::analysis::GraphAnalysis;
+use crate::errors::Error;
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, IRPrinter, LibrarySignatures, RRC};
+use itertools::Itertools;
+use std::rc::Rc;
+
+/// Reports combinational loops between the ports of a component.
+///
+/// The assignments of each group are checked together with the continuous
+/// assignments, which are active whenever the group is. The port graph built
+/// by [GraphAnalysis] from the assignments is extended with an edge from
+/// every input to every output of each combinational cell. Cells with a
+/// `clk` port, such as `std_reg` and the memories, are assumed to register
+/// their outputs and break loops.
+///
+/// Every elementary loop is reported as a path that starts and ends at the
+/// same port. At most [MAX_LOOPS] loops are listed for the continuous
+/// assignments and for each group, followed by the number of loops that were
+/// left out. Loops between groups that run in parallel are not reported.
+#[derive(Default)]
+pub struct CombLoops;
+
+/// The number of loops listed for the continuous assignments and for each
+/// group.
+const MAX_LOOPS: usize = 10;
+
+impl Named for CombLoops {
+    fn name() -> &'static str {
+        "comb-loops"
+    }
+
+    fn description() -> &'static str {
+        "report combinational loops between ports"
+    }
+}
+
+/// The edges from the inputs to the outputs of the combinational cell
+/// `cell`.
+fn comb_edges(cell: &ir::Cell) -> Vec<(RRC<ir::Port>, RRC<ir::Port>)> {
+    if cell
+        .ports
+        .iter()
+        .any(|port| port.borrow().attributes.has("clk"))
+    {
+        return vec![];
+    }
+    let (inputs, outputs): (Vec<_>, Vec<_>) = cell
+        .ports
+        .iter()
+        .partition(|port| port.borrow().direction == ir::Direction::Input);
+    inputs
+        .into_iter()
+        .cartesian_product(outputs)
+        .map(|(input, output)| (Rc::clone(input), Rc::clone(output)))
+        .collect()
+}
+
+impl Visitor for CombLoops {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let edges: Vec<_> = comp
+            .cells
+            .iter()
+            .flat_map(|cell| comb_edges(&cell.borrow()))
+            .collect();
+        // At most `limit` loops in `graph`, formatted as paths, and the
+        // total number of loops.
+        let loops = |graph: GraphAnalysis, limit: usize| {
+            let (cycles, total) = graph.add_edges(&edges).cycles(limit);
+            let paths: Vec<String> = cycles
+                .into_iter()
+                .map(|cycle| {
+                    cycle
+                        .iter()
+                        .chain(cycle.first())
+                        .map(|port| IRPrinter::get_port_access(&port.borrow()))
+                        .join(" -> ")
+                })
+                .sorted()
+                .collect();
+            (paths, total)
+        };
+        let more = |count: usize, location: String| {
+            Error::MalformedStructure(format!(
+                "... and {} more combinational loops in {}",
+                count, location
+            ))
+        };
+
+        let (continuous, continuous_total) = loops(
+            GraphAnalysis::default()
+                .add_assignments(&comp.continuous_assignments),
+            MAX_LOOPS,
+        );
+        let mut errors: Vec<_> = continuous
+            .iter()
+            .map(|path| {
+                Error::MalformedStructure(format!(
+                    "Combinational loop in component `{}`: {}",
+                    comp.name, path
+                ))
+            })
+            .collect();
+        if continuous_total > continuous.len() {
+            errors.push(more(
+                continuous_total - continuous.len(),
+                format!("component `{}`", comp.name),
+            ));
+        }
+        for group_ref in comp.groups.iter() {
+            let group = group_ref.borrow();
+            let graph = GraphAnalysis::from(&*group)
+                .add_assignments(&comp.continuous_assignments);
+            // The loops of the group include the loops in the continuous
+            // assignments, which are only reported once.
+            let (paths, total) = loops(graph, MAX_LOOPS + continuous.len());
+            let paths: Vec<_> = paths
+                .into_iter()
+                .filter(|path| !continuous.contains(path))
+                .take(MAX_LOOPS)
+                .collect();
+            let location = format!(
+                "group `{}` of component `{}`",
+                group.name(),
+                comp.name
+            );
+            errors.extend(paths.iter().map(|path| {
+                Error::MalformedStructure(format!(
+                    "Combinational loop in {}: {}",
+                    location, path
+                ))
+            }));
+            let group_total = total.saturating_sub(continuous_total);
+            if group_total > paths.len() {
+                errors.push(more(group_total - paths.len(), location));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Action::Stop)
+        } else {
+            Err(Error::combine(errors))
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use crate::errors::Error;
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, CloneName, LibrarySignatures, RRC, WRC};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// For each port on a `ref` cell: the name of the port on the cell, the name
+/// of the component port it is replaced with, its width, and its direction
+/// on the `ref` cell.
+type RefPorts = Vec<(ir::Id, ir::Id, u64, ir::Direction)>;
+
+/// Compiles away `ref` cells by turning each of their ports into a port on
+/// the component's signature and rewriting `invoke` statements to connect
+/// the bound cells to the new ports.
+///
+/// For example, the component:
+/// ```text
+/// component add_one() -> () {
+///   cells { ref r = std_reg(32); ... }
+///   wires { group incr { r.in = add.out; r.write_en = 1'd1; ... } }
+/// }
+/// ```
+/// is rewritten to have the ports `r_in`, `r_write_en`, `r_out`, and
+/// `r_done`, and `invoke a[r = x]()()` connects the ports of `x` to these
+/// ports.
+///
+/// Requires a post-order traversal so that callees are compiled before their
+/// callers.
+#[derive(Default)]
+pub struct CompileRef {
+    /// Mapping from component names to the ports generated for each of their
+    /// `ref` cells.
+    ref_ports: HashMap<ir::Id, Vec<(ir::Id, RefPorts)>>,
+    /// Mapping from the canonical name of a port on a `ref` cell in the
+    /// current component to the signature port that replaces it.
+    port_rewrites: HashMap<(ir::Id, ir::Id), RRC<ir::Port>>,
+}
+
+impl Named for CompileRef {
+    fn name() -> &'static str {
+        "compile-ref"
+    }
+
+    fn description() -> &'static str {
+        "Turns ref cells into component ports and binds them at invokes"
+    }
+}
+
+impl CompileRef {
+    /// Returns the port that replaces `port` if its parent is a `ref` cell.
+    fn rewrite(&self, port: &RRC<ir::Port>) -> Option<RRC<ir::Port>> {
+        let port = port.borrow();
+        if port.is_hole() {
+            return None;
+        }
+        self.port_rewrites.get(&port.canonical()).map(Rc::clone)
+    }
+
+    /// Rewrite all uses of ports on `ref` cells in `assigns`.
+    fn rewrite_assigns(&self, assigns: &mut Vec<ir::Assignment>) {
+        for assign in assigns {
+            if let Some(new_port) = self.rewrite(&assign.src) {
+                assign.src = new_port;
+            }
+            if let Some(new_port) = self.rewrite(&assign.dst) {
+                assign.dst = new_port;
+            }
+            assign
+                .guard
+                .for_each(&|port| self.rewrite(&port).map(ir::Guard::port));
+        }
+    }
+}
+
+/// Add a new port to `cell`.
+fn add_port(
+    cell: &RRC<ir::Cell>,
+    name: ir::Id,
+    width: u64,
+    direction: ir::Direction,
+) -> RRC<ir::Port> {
+    let port = Rc::new(RefCell::new(ir::Port {
+        name,
+        width,
+        direction,
+        parent: ir::PortParent::Cell(WRC::from(cell)),
+        attributes: ir::Attributes::default(),
+    }));
+    cell.borrow_mut().ports.push(Rc::clone(&port));
+    port
+}
+
+impl Visitor for CompileRef {
+    fn require_postorder() -> bool {
+        true
+    }
+
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        self.port_rewrites.clear();
+
+        // Add ports for `ref` cells of invoked components to their instances.
+        for cell in comp.cells.iter() {
+            let typ = match &cell.borrow().prototype {
+                ir::CellType::Component { name } => name.clone(),
+                _ => continue,
+            };
+            if let Some(refs) = self.ref_ports.get(&typ) {
+                for (name, width, dir) in refs.iter().flat_map(|(_, ports)| {
+                    ports.iter().map(|(_, n, w, d)| (n, w, d))
+                }) {
+                    add_port(cell, name.clone(), *width, dir.reverse());
+                }
+            }
+        }
+
+        // Replace ports on `ref` cells with ports on the signature.
+        let ref_cells = comp
+            .cells
+            .iter()
+            .filter(|cell| cell.borrow().is_reference())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut comp_refs = Vec::with_capacity(ref_cells.len());
+        for cell_ref in ref_cells {
+            let cell = cell_ref.borrow();
+            let mut ports = Vec::with_capacity(cell.ports.len());
+            for port_ref in &cell.ports {
+                let port = port_ref.borrow();
+                // The bound cell is clocked and reset by the caller.
+                if port.attributes.has("clk") || port.attributes.has("reset") {
+                    continue;
+                }
+                let name = comp.generate_name(format!(
+                    "{}_{}",
+                    cell.name(),
+                    port.name
+                ));
+                let sig_port = add_port(
+                    &comp.signature,
+                    name.clone(),
+                    port.width,
+                    port.direction.clone(),
+                );
+                self.port_rewrites.insert(port.canonical(), sig_port);
+                ports.push((
+                    port.name.clone(),
+                    name,
+                    port.width,
+                    port.direction.clone(),
+                ));
+            }
+            comp_refs.push((cell.clone_name(), ports));
+        }
+
+        for group in comp.groups.iter() {
+            self.rewrite_assigns(&mut group.borrow_mut().assignments);
+        }
+        self.rewrite_assigns(&mut comp.continuous_assignments);
+
+        self.ref_ports.insert(comp.name.clone(), comp_refs);
+
+        Ok(Action::Continue)
+    }
+
+    fn start_if(
+        &mut self,
+        s: &mut ir::If,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if let Some(new_port) = self.rewrite(&s.port) {
+            s.port = new_port;
+        }
+        Ok(Action::Continue)
+    }
+
+    fn start_while(
+        &mut self,
+        s: &mut ir::While,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        if let Some(new_port) = self.rewrite(&s.port) {
+            s.port = new_port;
+        }
+        Ok(Action::Continue)
+    }
+
+    fn invoke(
+        &mut self,
+        s: &mut ir::Invoke,
+        _comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        for (_, port) in s.inputs.iter_mut().chain(s.outputs.iter_mut()) {
+            if let Some(new_port) = self.rewrite(port) {
+                *port = new_port;
+            }
+        }
+
+        if s.ref_cells.is_empty() {
+            return Ok(Action::Continue);
+        }
+
+        let typ = s.comp.borrow().type_name().cloned().ok_or_else(|| {
+            Error::Impossible("Invoked cell has no type".to_string())
+        })?;
+        let refs = &self.ref_ports[&typ];
+        for (ref_name, bound_cell) in s.ref_cells.drain(..) {
+            let (_, ports) = refs
+                .iter()
+                .find(|(name, _)| *name == ref_name)
+                .ok_or_else(|| {
+                    Error::Undefined(ref_name.clone(), "ref cell".to_string())
+                })?;
+            for (port_name, sig_name, _, dir) in ports {
+                let bound_port = bound_cell.borrow().get(port_name);
+                let bound_port =
+                    self.rewrite(&bound_port).unwrap_or(bound_port);
+                match dir {
+                    // The invoked component drives the inputs of the cell.
+                    ir::Direction::Input => {
+                        s.outputs.push((sig_name.clone(), bound_port))
+                    }
+                    _ => s.inputs.push((sig_name.clone(), bound_port)),
+                }
+            }
+        }
+
+        Ok(Action::Continue)
+    }
+
+    fn finish(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        comp.cells.retain(|cell| !cell.borrow().is_reference());
+        Ok(Action::Continue)
+    }
+}
@@ -41,6 +41,9 @@ impl Visitor for DeadCellRemoval {
                 .map(|(_, port)| port.borrow().get_parent_name()),
         );
 
+        self.used_cells
+            .extend(s.ref_cells.iter().map(|(_, cell)| cell.clone_name()));
+
         self.used_cells.insert(s.comp.clone_name());
 
         Ok(Action::Continue)
@@ -67,9 +70,12 @@ impl Visitor for DeadCellRemoval {
                 .map(|c| c.clone_name()),
         );
 
-        // Remove cells that are not used.
-        comp.cells
-            .retain(|c| self.used_cells.contains(&c.borrow().name()));
+        // Remove cells that are not used. `ref` cells are part of the
+        // component's interface and are never removed.
+        comp.cells.retain(|c| {
+            let cell = c.borrow();
+            cell.is_reference() || self.used_cells.contains(cell.name())
+        });
 
         Ok(Action::Stop)
     }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use crate::analysis::{GraphAnalysis, ReadWriteSet};
-use crate::errors::Error;
+use crate::errors::{Error, FutilResult};
 use crate::ir::traversal::{
     Action, ConstructVisitor, Named, VisResult, Visitor,
 };
@@ -20,8 +20,9 @@ use std::{cmp, ops::Add, rc::Rc};
 /// pass will throw an error. If a group's `done` signal relies on signals
 /// that are not only `done` signals, this pass will ignore that group.
 pub struct InferStaticTiming {
-    /// primitive name -> (go signal, done signal, latency)
-    latency_data: HashMap<ir::Id, (ir::Id, ir::Id, u64)>,
+    /// primitive name -> (go signal, done signal, latency) for each pair of
+    /// go and done signals of the primitive
+    latency_data: HashMap<ir::Id, Vec<(ir::Id, ir::Id, u64)>>,
     /// static timing information for components
     comp_latency: HashMap<ir::Id, u64>,
 }
@@ -29,31 +30,40 @@ pub struct InferStaticTiming {
 // Override constructor to build latency_data information from the primitives
 // library.
 impl ConstructVisitor for InferStaticTiming {
-    fn from(ctx: &ir::Context) -> Self {
+    fn from(ctx: &ir::Context) -> FutilResult<Self> {
         let mut latency_data = HashMap::new();
         // XXX(rachit): This is unneccesarily rebuilt for every component
         // Build latency data by traversing primitive cells
         for prim in ctx.lib.sigs.values() {
             if let Some(time) = prim.attributes.get("static") {
-                let mut go_port = None;
-                let mut done_port = None;
+                // A go port is paired with the done port that has the same
+                // attribute value, e.g. `@go(2)` with `@done(2)`.
+                let mut go_ports = HashMap::new();
+                let mut done_ports = HashMap::new();
                 for port in &prim.signature {
-                    if port.attributes.has("go") {
-                        go_port = Some(port.name.clone());
+                    if let Some(idx) = port.attributes.get("go") {
+                        go_ports.insert(*idx, port.name.clone());
                     }
-                    if port.attributes.has("done") {
-                        done_port = Some(port.name.clone());
+                    if let Some(idx) = port.attributes.get("done") {
+                        done_ports.insert(*idx, port.name.clone());
                     }
                 }
-                if let (Some(go), Some(done)) = (go_port, done_port) {
-                    latency_data.insert(prim.name.clone(), (go, done, *time));
+                let pairs: Vec<_> = go_ports
+                    .into_iter()
+                    .sorted()
+                    .filter_map(|(idx, go)| {
+                        done_ports.remove(&idx).map(|done| (go, done, *time))
+                    })
+                    .collect();
+                if !pairs.is_empty() {
+                    latency_data.insert(prim.name.clone(), pairs);
                 }
             }
         }
-        InferStaticTiming {
+        Ok(InferStaticTiming {
             latency_data,
             comp_latency: HashMap::new(),
-        }
+        })
     }
 }
 
@@ -88,6 +98,25 @@ impl Named for InferStaticTiming {
 }
 
 impl InferStaticTiming {
+    /// The latency of the primitive `prim` when its go port `port` is
+    /// asserted.
+    fn go_latency(&self, prim: &ir::Id, port: &ir::Id) -> Option<u64> {
+        self.latency_data.get(prim).and_then(|pairs| {
+            pairs
+                .iter()
+                .find(|(go, _, _)| go == port)
+                .map(|(_, _, latency)| *latency)
+        })
+    }
+
+    /// Returns true if `port` is a done port of the primitive `prim`.
+    fn is_done(&self, prim: &ir::Id, port: &ir::Id) -> bool {
+        self.latency_data
+            .get(prim)
+            .map(|pairs| pairs.iter().any(|(_, done, _)| done == port))
+            .unwrap_or_default()
+    }
+
     /// Return true if the edge (`src`, `dst`) meet one these criteria, and false otherwise:
     ///   - `src` is an "out" port of a constant, and `dst` is a "go" port
     ///   - `src` is a "done" port, and `dst` is a "go" port
@@ -111,14 +140,10 @@ impl InferStaticTiming {
                     &dst_cell.upgrade().borrow().prototype,
                     &src_cell.upgrade().borrow().prototype,
                 ) {
-                    let data_dst = self.latency_data.get(dst_cell_prim_type);
-                    let data_src = self.latency_data.get(src_cell_prim_type);
-                    if let (Some((go_dst, _, _)), Some((_, done_src, _))) =
-                        (data_dst, data_src)
+                    if self.go_latency(dst_cell_prim_type, &dst.name).is_some()
+                        && self.is_done(src_cell_prim_type, &src.name)
                     {
-                        if dst.name == *go_dst && src.name == *done_src {
-                            return true;
-                        }
+                        return true;
                     }
                 }
 
@@ -133,11 +158,9 @@ impl InferStaticTiming {
                     &dst_cell.upgrade().borrow().prototype,
                     &src_cell.upgrade().borrow().prototype,
                 ) {
-                    let data = self.latency_data.get(dst_cell_prim_type);
-                    if let Some((go, _, _)) = data {
-                        if dst.name == *go {
-                            return true;
-                        }
+                    if self.go_latency(dst_cell_prim_type, &dst.name).is_some()
+                    {
+                        return true;
                     }
                 }
 
@@ -152,8 +175,8 @@ impl InferStaticTiming {
         }
     }
 
-    /// Return a Vec of edges (`a`, `b`), where `a` is a "go" port and `b`
-    /// is a "done" port, and `a` and `b` have the same parent cell.
+    /// Return a Vec of edges (`a`, `b`), where `a` is a "go" port written to
+    /// by the group and `b` is the matching "done" port of the same cell.
     fn find_go_done_edges(
         &self,
         group: &ir::Group,
@@ -166,14 +189,24 @@ impl InferStaticTiming {
                 name: cell_type, ..
             } = &cell.prototype
             {
-                if let Some((go, done, _)) = self.latency_data.get(cell_type) {
-                    let go_port =
-                        &cell.ports.iter().find(|p| p.borrow().name == *go);
+                for (go, done, _) in
+                    self.latency_data.get(cell_type).into_iter().flatten()
+                {
+                    // Primitives with several go signals, such as memories
+                    // with separate read and write ports, only contribute the
+                    // signals used by the group.
+                    let go_port = cell.ports.iter().find(|p| {
+                        p.borrow().name == *go
+                            && group
+                                .assignments
+                                .iter()
+                                .any(|asgn| Rc::ptr_eq(&asgn.dst, p))
+                    });
                     let done_port =
-                        &cell.ports.iter().find(|p| p.borrow().name == *done);
+                        cell.ports.iter().find(|p| p.borrow().name == *done);
 
                     if let (Some(g), Some(d)) = (go_port, done_port) {
-                        go_done_edges.push((Rc::clone(&g), Rc::clone(&d)));
+                        go_done_edges.push((Rc::clone(g), Rc::clone(d)));
                     }
                 }
             }
@@ -189,10 +222,8 @@ impl InferStaticTiming {
                 name: cell_type, ..
             } = &cell.upgrade().borrow().prototype
             {
-                if let Some((_, done, _)) = self.latency_data.get(cell_type) {
-                    if port.name == *done {
-                        return true;
-                    }
+                if self.is_done(cell_type, &port.name) {
+                    return true;
                 }
             }
 
@@ -234,18 +265,15 @@ impl InferStaticTiming {
                         name: cell_type, ..
                     } = &cell.upgrade().borrow().prototype
                     {
-                        if let Some((go, _, _)) =
-                            self.latency_data.get(cell_type)
+                        if self
+                            .go_latency(cell_type, &port.borrow().name)
+                            .is_some()
                         {
-                            if port.borrow().name == *go {
-                                for write_port in
-                                    graph.writes_to(&*port.borrow())
+                            for write_port in graph.writes_to(&port.borrow()) {
+                                if !self
+                                    .is_done_port_or_const(&write_port.borrow())
                                 {
-                                    if !self.is_done_port_or_const(
-                                        &*write_port.borrow(),
-                                    ) {
-                                        return true;
-                                    }
+                                    return true;
                                 }
                             }
                         }
@@ -342,11 +370,10 @@ impl InferStaticTiming {
                 if let ir::CellType::Primitive { name, .. } =
                     &cell.upgrade().borrow().prototype
                 {
-                    if let Some((go, _, latency)) = self.latency_data.get(name)
+                    if let Some(latency) =
+                        self.go_latency(name, &port.borrow().name)
                     {
-                        if port.borrow().name == go {
-                            latency_sum += latency;
-                        }
+                        latency_sum += latency;
                     }
                 }
             }
@@ -6,7 +6,7 @@ use crate::{
     structure,
 };
 use ir::RRC;
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::BTreeMap, rc::Rc};
 
 #[derive(Default)]
 /// Removes all groups and inlines reads and writes from holes.
@@ -27,7 +27,7 @@ impl Named for Inliner {
     }
 }
 
-type Store = HashMap<(ir::Id, ir::Id), (RRC<ir::Port>, ir::Guard)>;
+type Store = BTreeMap<(ir::Id, ir::Id), (RRC<ir::Port>, ir::Guard)>;
 
 /// Finds the 'fixed_point' of a map from Hole names to guards under the
 /// inlining operation. The map contains entries like:
@@ -150,12 +150,17 @@ impl Visitor for Inliner {
         }
 
         // map of holes to their guard expressions
-        let mut map: Store = HashMap::new();
+        let mut map: Store = BTreeMap::new();
         let mut assignments = vec![];
         for group in builder.component.groups.iter() {
-            // remove all assignments from group, taking ownership
+            // remove all assignments from group, taking ownership and
+            // remembering where they came from.
             let mut group = group.borrow_mut();
-            assignments.append(&mut group.assignments.drain(..).collect());
+            let name = group.name().clone();
+            assignments.extend(group.assignments.drain(..).map(|mut asgn| {
+                asgn.origin.get_or_insert_with(|| name.clone());
+                asgn
+            }));
         }
 
         // add the continuous assignment edges
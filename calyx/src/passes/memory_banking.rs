@@ -0,0 +1,687 @@
+use super::minimize_mems::size_params;
+use crate::errors::{Error, FutilResult};
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, GetAttributes, LibrarySignatures, RRC};
+use serde_json::{Map, Value};
+use std::{fs, path::Path, rc::Rc};
+
+/// Partitions memories into banks that can be accessed in parallel.
+///
+/// The first dimension of a memory with the `bank_cyclic(N)` attribute is
+/// distributed between `N` banks so that row `i` is stored in bank `i % N`.
+/// With the `bank_block(N)` attribute, the rows are instead split into `N`
+/// contiguous blocks.
+/// The number of banks must be a power of two that divides the size of the
+/// first dimension and, for block partitioning, the size of each block must
+/// also be a power of two.
+///
+/// Bank `k` of the memory `mem` is named `mem_bank{k}` and inherits the
+/// attributes of `mem`, so the banks of an `@external` memory are external.
+/// The `bank-data` subcommand of the compiler splits and merges data files to
+/// match.
+///
+/// Every group that assigns to `mem.addr0` computes the bank and the offset
+/// of the address and then:
+/// - drives the address and data ports of every bank,
+/// - only enables writes to the selected bank, and
+/// - reads `read_data` and `done` from the selected bank.
+///
+/// Memories used by the control program (for example, by an `invoke`) cannot
+/// be banked.
+#[derive(Default)]
+pub struct MemoryBanking;
+
+impl Named for MemoryBanking {
+    fn name() -> &'static str {
+        "memory-banking"
+    }
+
+    fn description() -> &'static str {
+        "partition memories with the bank_cyclic or bank_block attribute into banks"
+    }
+}
+
+/// How the rows of a memory are distributed between its banks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BankScheme {
+    /// Row `i` is stored in bank `i % banks`.
+    Cyclic,
+    /// The rows are split into `banks` contiguous blocks.
+    Block,
+}
+
+/// The layout of a memory partitioned by [MemoryBanking].
+#[derive(Clone, Debug)]
+pub struct BankedMemory {
+    /// Name of the memory.
+    pub name: ir::Id,
+    /// Number of banks.
+    pub banks: u64,
+    /// How the rows are distributed between the banks.
+    pub scheme: BankScheme,
+    /// Size of the first dimension of the memory.
+    pub rows: u64,
+    /// Number of elements in a row of the memory.
+    pub row_size: u64,
+}
+
+impl BankedMemory {
+    /// The layout requested by the attributes of `cell` or `None` if the cell
+    /// is not banked.
+    pub fn from_cell(cell: &ir::Cell) -> FutilResult<Option<Self>> {
+        let malformed = |msg: &str| {
+            Err(Error::MalformedStructure(format!(
+                "Cannot bank memory {}: {}",
+                cell.name(),
+                msg
+            )))
+        };
+        let (banks, scheme) = match (
+            cell.get_attribute("bank_cyclic"),
+            cell.get_attribute("bank_block"),
+        ) {
+            (None, None) => return Ok(None),
+            (Some(banks), None) => (*banks, BankScheme::Cyclic),
+            (None, Some(banks)) => (*banks, BankScheme::Block),
+            (Some(_), Some(_)) => {
+                return malformed("both bank_cyclic and bank_block are given")
+            }
+        };
+        let params = cell
+            .type_name()
+            .map_or(&[][..], |name| size_params(name.as_ref()));
+        if params.is_empty() {
+            return malformed("only std_mem_d1 to std_mem_d4 can be banked");
+        }
+        let sizes: Vec<u64> = params
+            .iter()
+            .map(|param| cell.get_parameter(param).unwrap())
+            .collect();
+        let rows = sizes[0];
+        if banks < 2 || !banks.is_power_of_two() {
+            return malformed("the number of banks must be a power of two");
+        }
+        // `banks` is a power of two, so it divides `rows` if the low bits of
+        // `rows` are zero.
+        if rows & (banks - 1) != 0 {
+            return malformed(
+                "the number of banks must divide the first dimension",
+            );
+        }
+        if scheme == BankScheme::Block && !(rows / banks).is_power_of_two() {
+            return malformed("the size of each block must be a power of two");
+        }
+        Ok(Some(BankedMemory {
+            name: cell.name().clone(),
+            banks,
+            scheme,
+            rows,
+            row_size: sizes[1..].iter().product(),
+        }))
+    }
+
+    /// Name of bank `bank`.
+    pub fn bank_name(&self, bank: u64) -> ir::Id {
+        format!("{}_bank{}", self.name, bank).into()
+    }
+
+    /// The bank that stores `row` and the index of the row in the bank.
+    pub fn locate(&self, row: u64) -> (u64, u64) {
+        match self.scheme {
+            BankScheme::Cyclic => (row % self.banks, row / self.banks),
+            BankScheme::Block => {
+                let block = self.rows / self.banks;
+                (row / block, row % block)
+            }
+        }
+    }
+
+    /// Distribute the `rows` of the memory between the banks.
+    pub fn split<T: Clone>(&self, rows: &[T]) -> FutilResult<Vec<Vec<T>>> {
+        if rows.len() as u64 != self.rows {
+            return Err(Error::Misc(format!(
+                "Memory {} has {} rows but the data has {}",
+                self.name,
+                self.rows,
+                rows.len()
+            )));
+        }
+        let mut banks = vec![vec![]; self.banks as usize];
+        for (row, val) in rows.iter().enumerate() {
+            let (bank, _) = self.locate(row as u64);
+            banks[bank as usize].push(val.clone());
+        }
+        Ok(banks)
+    }
+
+    /// The rows of the memory given the rows of each bank. The inverse of
+    /// [BankedMemory::split].
+    pub fn merge<T: Clone>(&self, banks: &[Vec<T>]) -> FutilResult<Vec<T>> {
+        (0..self.rows)
+            .map(|row| {
+                let (bank, offset) = self.locate(row);
+                banks
+                    .get(bank as usize)
+                    .and_then(|rows| rows.get(offset as usize))
+                    .cloned()
+                    .ok_or_else(|| {
+                        Error::Misc(format!(
+                            "Bank {} of memory {} has too few rows",
+                            bank, self.name
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Replace the entry for the memory in the JSON data file `data` with an
+    /// entry for each bank. Entries are either arrays or objects that store
+    /// the array in `data`.
+    pub fn split_json(&self, data: &mut Map<String, Value>) -> FutilResult<()> {
+        let entry = match data.remove(self.name.as_ref()) {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+        let banks = self.split(json_rows(&entry, &self.name)?)?;
+        for (bank, rows) in banks.into_iter().enumerate() {
+            let mut bank_entry = entry.clone();
+            *json_rows_mut(&mut bank_entry) = rows;
+            data.insert(self.bank_name(bank as u64).to_string(), bank_entry);
+        }
+        Ok(())
+    }
+
+    /// Replace the entries for the banks in the JSON data file `data` with an
+    /// entry for the memory. The inverse of [BankedMemory::split_json].
+    pub fn merge_json(&self, data: &mut Map<String, Value>) -> FutilResult<()> {
+        let entries = (0..self.banks)
+            .filter_map(|bank| data.remove(self.bank_name(bank).as_ref()))
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let banks = entries
+            .iter()
+            .map(|entry| json_rows(entry, &self.name).map(|rows| rows.to_vec()))
+            .collect::<FutilResult<Vec<_>>>()?;
+        let mut entry = entries[0].clone();
+        *json_rows_mut(&mut entry) = self.merge(&banks)?;
+        data.insert(self.name.to_string(), entry);
+        Ok(())
+    }
+
+    /// Split `<mem>.<ext>` in `dir`, which contains an element on each line,
+    /// into `<bank>.<ext>` for each bank.
+    pub fn split_dat(&self, dir: &Path, ext: &str) -> FutilResult<()> {
+        let rows =
+            read_rows(&dir.join(format!("{}.{}", self.name, ext)), self)?;
+        for (bank, rows) in self.split(&rows)?.into_iter().enumerate() {
+            let path =
+                dir.join(format!("{}.{}", self.bank_name(bank as u64), ext));
+            write_rows(&path, &rows)?;
+        }
+        Ok(())
+    }
+
+    /// Merge `<bank>.<ext>` in `dir` for each bank into `<mem>.<ext>`. The
+    /// inverse of [BankedMemory::split_dat].
+    pub fn merge_dat(&self, dir: &Path, ext: &str) -> FutilResult<()> {
+        let banks = (0..self.banks)
+            .map(|bank| {
+                let name = self.bank_name(bank);
+                read_rows(&dir.join(format!("{}.{}", name, ext)), self)
+            })
+            .collect::<FutilResult<Vec<_>>>()?;
+        let path = dir.join(format!("{}.{}", self.name, ext));
+        write_rows(&path, &self.merge(&banks)?)
+    }
+}
+
+/// The rows stored in the JSON data file entry `entry`.
+fn json_rows<'a>(entry: &'a Value, mem: &ir::Id) -> FutilResult<&'a [Value]> {
+    match entry.get("data").unwrap_or(entry) {
+        Value::Array(rows) => Ok(rows),
+        _ => Err(Error::Misc(format!(
+            "Data for memory {} is not an array",
+            mem
+        ))),
+    }
+}
+
+fn json_rows_mut(entry: &mut Value) -> &mut Vec<Value> {
+    let rows = if entry.get("data").is_some() {
+        &mut entry["data"]
+    } else {
+        entry
+    };
+    match rows {
+        Value::Array(rows) => rows,
+        _ => unreachable!("Data is not an array"),
+    }
+}
+
+/// Read the lines of `path` grouped into rows of `mem`.
+fn read_rows(path: &Path, mem: &BankedMemory) -> FutilResult<Vec<Vec<String>>> {
+    let contents = fs::read_to_string(path).map_err(|_| {
+        Error::InvalidFile(format!("Failed to read {}", path.display()))
+    })?;
+    Ok(contents
+        .lines()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .chunks(mem.row_size as usize)
+        .map(|row| row.to_vec())
+        .collect())
+}
+
+fn write_rows(path: &Path, rows: &[Vec<String>]) -> FutilResult<()> {
+    let contents: String = rows
+        .iter()
+        .flatten()
+        .map(|line| format!("{}\n", line))
+        .collect();
+    fs::write(path, contents).map_err(|_| {
+        Error::WriteError(format!("Failed to write {}", path.display()))
+    })
+}
+
+/// The value of `port` if it is a constant.
+fn constant(port: &RRC<ir::Port>) -> Option<u64> {
+    match &port.borrow().parent {
+        ir::PortParent::Cell(cell_wref) => {
+            match cell_wref.upgrade().borrow().prototype {
+                ir::CellType::Constant { val, .. } => Some(val),
+                _ => None,
+            }
+        }
+        ir::PortParent::Group(_) => None,
+    }
+}
+
+/// Returns an error if the control program `con` uses the memory `mem`.
+fn check_control(con: &ir::Control, mem: &ir::Id) -> FutilResult<()> {
+    let uses = |port: &RRC<ir::Port>| port.borrow().get_parent_name() == mem;
+    let used = match con {
+        ir::Control::Empty(_) | ir::Control::Enable(_) => false,
+        ir::Control::Invoke(invoke) => {
+            invoke
+                .inputs
+                .iter()
+                .chain(&invoke.outputs)
+                .any(|(_, port)| uses(port))
+                || invoke
+                    .ref_cells
+                    .iter()
+                    .any(|(_, cell)| cell.borrow().name() == mem)
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            return stmts.iter().try_for_each(|stmt| check_control(stmt, mem))
+        }
+        ir::Control::If(ir::If {
+            port,
+            tbranch,
+            fbranch,
+            ..
+        }) => {
+            check_control(tbranch, mem)?;
+            check_control(fbranch, mem)?;
+            uses(port)
+        }
+        ir::Control::While(ir::While { port, body, .. }) => {
+            check_control(body, mem)?;
+            uses(port)
+        }
+    };
+    if used {
+        Err(Error::MalformedControl(format!(
+            "Cannot bank memory {}: it is used by the control program",
+            mem
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Add the banks of `mem` to the component.
+fn add_banks(
+    builder: &mut ir::Builder,
+    mem: &ir::Cell,
+    layout: &BankedMemory,
+) -> FutilResult<Vec<RRC<ir::Cell>>> {
+    let (prim, param_binding) = match &mem.prototype {
+        ir::CellType::Primitive {
+            name,
+            param_binding,
+        } => (name, param_binding),
+        _ => unreachable!("Banked memory is not a primitive"),
+    };
+    let rows_param = size_params(prim.as_ref())[0];
+    let params: Vec<u64> = param_binding
+        .iter()
+        .map(|(param, val)| {
+            if param == rows_param {
+                layout.rows / layout.banks
+            } else {
+                *val
+            }
+        })
+        .collect();
+    let mut attributes = mem.get_attributes().unwrap().clone();
+    attributes.remove("bank_cyclic");
+    attributes.remove("bank_block");
+
+    (0..layout.banks)
+        .map(|bank| {
+            let name = layout.bank_name(bank);
+            if builder.component.find_cell(&name).is_some() {
+                return Err(Error::AlreadyBound(name, "cell".to_string()));
+            }
+            let cell = builder.add_primitive(name, prim, &params);
+            *cell.borrow_mut().get_mut_attributes().unwrap() =
+                attributes.clone();
+            Ok(cell)
+        })
+        .collect()
+}
+
+/// Rewrites the uses of a banked memory in a set of assignments.
+struct Rewriter<'a> {
+    mem: &'a RRC<ir::Cell>,
+    banks: &'a [RRC<ir::Cell>],
+    layout: &'a BankedMemory,
+}
+
+impl Rewriter<'_> {
+    fn is_mem(&self, port: &RRC<ir::Port>) -> bool {
+        match &port.borrow().parent {
+            ir::PortParent::Cell(cell_wref) => {
+                Rc::ptr_eq(&cell_wref.upgrade(), self.mem)
+            }
+            ir::PortParent::Group(_) => false,
+        }
+    }
+
+    fn uses_mem(&self, assign: &ir::Assignment) -> bool {
+        self.is_mem(&assign.dst)
+            || self.is_mem(&assign.src)
+            || assign
+                .guard
+                .all_ports()
+                .iter()
+                .any(|port| self.is_mem(port))
+    }
+
+    /// Adds the assignments computing the bank and the offset of `addr` to
+    /// `assigns` and returns the ports with the bank and the offset.
+    fn decompose(
+        &self,
+        builder: &mut ir::Builder,
+        addr: &RRC<ir::Port>,
+        guard: &ir::Guard,
+        assigns: &mut Vec<ir::Assignment>,
+    ) -> (RRC<ir::Port>, RRC<ir::Port>) {
+        let width = addr.borrow().width;
+        let sel_width = self.layout.banks.trailing_zeros() as u64;
+        let prefix = |suffix: &str| format!("{}_{}", self.layout.name, suffix);
+        let rsh = builder.add_primitive(prefix("rsh"), "std_rsh", &[width]);
+        let sel = builder.add_primitive(
+            prefix("sel"),
+            "std_slice",
+            &[width, sel_width],
+        );
+        // For cyclic partitioning, the low bits of the address select the
+        // bank and the high bits are the offset. For block partitioning, the
+        // high bits select the bank and the low bits are the offset.
+        let (amount, offset) = match self.layout.scheme {
+            BankScheme::Cyclic => {
+                (builder.add_constant(sel_width, width), None)
+            }
+            BankScheme::Block => {
+                let block = self.layout.rows / self.layout.banks;
+                let amount =
+                    builder.add_constant(block.trailing_zeros() as u64, width);
+                let and = builder.add_primitive(
+                    prefix("offset"),
+                    "std_and",
+                    &[width],
+                );
+                let mask = builder.add_constant(block - 1, width);
+                (amount, Some((and, mask)))
+            }
+        };
+
+        let mut assign = |dst: RRC<ir::Port>, src: RRC<ir::Port>| {
+            assigns.push(builder.build_assignment(dst, src, guard.clone()))
+        };
+        assign(rsh.borrow().get("left"), Rc::clone(addr));
+        assign(rsh.borrow().get("right"), amount.borrow().get("out"));
+        match offset {
+            None => {
+                assign(sel.borrow().get("in"), Rc::clone(addr));
+                (sel.borrow().get("out"), rsh.borrow().get("out"))
+            }
+            Some((and, mask)) => {
+                assign(sel.borrow().get("in"), rsh.borrow().get("out"));
+                assign(and.borrow().get("left"), Rc::clone(addr));
+                assign(and.borrow().get("right"), mask.borrow().get("out"));
+                (sel.borrow().get("out"), and.borrow().get("out"))
+            }
+        }
+    }
+
+    /// Rewrite the assignments that use the memory.
+    fn rewrite(
+        &self,
+        builder: &mut ir::Builder,
+        assigns: Vec<ir::Assignment>,
+    ) -> FutilResult<Vec<ir::Assignment>> {
+        let (uses, mut out): (Vec<_>, Vec<_>) =
+            assigns.into_iter().partition(|a| self.uses_mem(a));
+        if uses.is_empty() {
+            return Ok(out);
+        }
+        let (addrs, uses): (Vec<_>, Vec<_>) = uses.into_iter().partition(|a| {
+            self.is_mem(&a.dst) && a.dst.borrow().name == "addr0"
+        });
+
+        // The guard under which each bank is accessed.
+        let sel_width = self.layout.banks.trailing_zeros() as u64;
+        let mut selects: Vec<Option<ir::Guard>> =
+            vec![None; self.layout.banks as usize];
+        for assign in addrs {
+            let guard = *assign.guard.clone();
+            let offset = if let Some(addr) = constant(&assign.src) {
+                let (bank, offset) = self.layout.locate(addr);
+                if bank >= self.layout.banks {
+                    return Err(Error::MalformedStructure(format!(
+                        "Address {} is out of bounds for memory {}",
+                        addr, self.layout.name
+                    )));
+                }
+                select(&mut selects[bank as usize], guard.clone());
+                let width = assign.src.borrow().width;
+                builder.add_constant(offset, width).borrow().get("out")
+            } else {
+                let (sel, offset) =
+                    self.decompose(builder, &assign.src, &guard, &mut out);
+                for (bank, bank_sel) in selects.iter_mut().enumerate() {
+                    let idx = builder.add_constant(bank as u64, sel_width);
+                    let is_bank =
+                        ir::Guard::Eq(Rc::clone(&sel), idx.borrow().get("out"));
+                    select(bank_sel, guard.clone().and(is_bank));
+                }
+                offset
+            };
+            for bank in self.banks {
+                let mut bank_assign = assign.clone();
+                bank_assign.dst = bank.borrow().get("addr0");
+                bank_assign.src = Rc::clone(&offset);
+                out.push(bank_assign);
+            }
+        }
+
+        let no_address = || {
+            Error::MalformedStructure(format!(
+                "Memory {} is accessed without assigning to addr0",
+                self.layout.name
+            ))
+        };
+        for assign in uses {
+            if self.is_mem(&assign.dst) && self.is_mem(&assign.src) {
+                return Err(Error::MalformedStructure(format!(
+                    "Cannot bank memory {}: it is assigned to itself",
+                    self.layout.name
+                )));
+            }
+            let mut banked = vec![];
+            if self.is_mem(&assign.dst) {
+                let port = assign.dst.borrow().name.clone();
+                let enable = port == "write_en";
+                if enable && selects.iter().all(Option::is_none) {
+                    return Err(no_address());
+                }
+                for (bank, sel) in self.banks.iter().zip(&selects) {
+                    let mut bank_assign = assign.clone();
+                    bank_assign.dst = bank.borrow().get(&port);
+                    // Only the selected bank is written to.
+                    if enable {
+                        match sel {
+                            Some(sel) => {
+                                bank_assign.guard.update(|g| g.and(sel.clone()))
+                            }
+                            None => continue,
+                        }
+                    }
+                    banked.push(bank_assign);
+                }
+            } else if self.is_mem(&assign.src) {
+                if selects.iter().all(Option::is_none) {
+                    return Err(no_address());
+                }
+                let port = assign.src.borrow().name.clone();
+                for (bank, sel) in self.banks.iter().zip(&selects) {
+                    if let Some(sel) = sel {
+                        let mut bank_assign = assign.clone();
+                        bank_assign.src = bank.borrow().get(&port);
+                        bank_assign.guard.update(|g| g.and(sel.clone()));
+                        banked.push(bank_assign);
+                    }
+                }
+            } else {
+                banked.push(assign);
+            }
+            for mut bank_assign in banked {
+                let guard = std::mem::take(&mut *bank_assign.guard);
+                *bank_assign.guard = self.rewrite_guard(guard, &selects)?;
+                out.push(bank_assign);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Replaces the uses of an output port of the memory in `guard` with the
+    /// port of the selected bank.
+    fn rewrite_guard(
+        &self,
+        guard: ir::Guard,
+        selects: &[Option<ir::Guard>],
+    ) -> FutilResult<ir::Guard> {
+        Ok(match guard {
+            ir::Guard::And(l, r) => self
+                .rewrite_guard(*l, selects)?
+                .and(self.rewrite_guard(*r, selects)?),
+            ir::Guard::Or(l, r) => self
+                .rewrite_guard(*l, selects)?
+                .or(self.rewrite_guard(*r, selects)?),
+            ir::Guard::Not(g) => !self.rewrite_guard(*g, selects)?,
+            ir::Guard::Port(port) if self.is_mem(&port) => {
+                let name = port.borrow().name.clone();
+                self.banks
+                    .iter()
+                    .zip(selects)
+                    .filter_map(|(bank, sel)| {
+                        sel.clone().map(|sel| {
+                            sel.and(ir::Guard::port(bank.borrow().get(&name)))
+                        })
+                    })
+                    .reduce(|acc, g| acc.or(g))
+                    .ok_or_else(|| {
+                        Error::MalformedStructure(format!(
+                            "Memory {} is accessed without assigning to addr0",
+                            self.layout.name
+                        ))
+                    })?
+            }
+            ir::Guard::Port(_) | ir::Guard::True => guard,
+            _ => {
+                if guard.all_ports().iter().any(|port| self.is_mem(port)) {
+                    return Err(Error::MalformedStructure(format!(
+                        "Cannot bank memory {}: it is used in a comparison",
+                        self.layout.name
+                    )));
+                }
+                guard
+            }
+        })
+    }
+}
+
+/// Add `guard` to the guards under which a bank is selected.
+fn select(sel: &mut Option<ir::Guard>, guard: ir::Guard) {
+    *sel = Some(match sel.take() {
+        Some(sel) => sel.or(guard),
+        None => guard,
+    });
+}
+
+impl Visitor for MemoryBanking {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let mut banked = vec![];
+        for cell in comp.cells.iter() {
+            if let Some(layout) = BankedMemory::from_cell(&cell.borrow())? {
+                banked.push((Rc::clone(cell), layout));
+            }
+        }
+
+        for (mem, layout) in banked {
+            check_control(&comp.control.borrow(), &layout.name)?;
+            let sel_width = layout.banks.trailing_zeros() as u64;
+            if mem.borrow().get("addr0").borrow().width < sel_width {
+                return Err(Error::MalformedStructure(format!(
+                    "Cannot bank memory {}: addr0 is too narrow to select a bank",
+                    layout.name
+                )));
+            }
+            let mut builder = ir::Builder::new(comp, sigs);
+            let banks = add_banks(&mut builder, &mem.borrow(), &layout)?;
+            let rewriter = Rewriter {
+                mem: &mem,
+                banks: &banks,
+                layout: &layout,
+            };
+
+            let groups: Vec<_> =
+                builder.component.groups.iter().cloned().collect();
+            for group in groups {
+                let assigns =
+                    group.borrow_mut().assignments.drain(..).collect();
+                let assigns = rewriter.rewrite(&mut builder, assigns)?;
+                group.borrow_mut().assignments = assigns;
+            }
+            let assigns =
+                builder.component.continuous_assignments.drain(..).collect();
+            let assigns = rewriter.rewrite(&mut builder, assigns)?;
+            builder.component.continuous_assignments = assigns;
+
+            comp.cells.retain(|cell| !Rc::ptr_eq(cell, &mem));
+        }
+
+        // The control program is not changed.
+        Ok(Action::Stop)
+    }
+}
@@ -0,0 +1,229 @@
+use super::sharing_components::{ShareComponents, ShareOpts, SHARE_OPTS};
+use crate::{
+    analysis::MemoryLiveRanges,
+    errors::FutilResult,
+    ir::{
+        self,
+        traversal::{ConstructVisitor, Named},
+        CloneName, RRC,
+    },
+};
+use std::collections::HashSet;
+
+/// Merges internal memories that are never alive at the same time and
+/// shrinks memories whose addresses provably stay below their size.
+///
+/// Memories (`std_mem_d1` to `std_mem_d4`) without the `external` attribute
+/// are merged if they have the same primitive, width, and sizes, and if the
+/// [MemoryLiveRanges] analysis shows that they are never alive at the same
+/// time. As in `minimize-regs`, a coloring of the interference graph is used to
+/// decide which memories are merged.
+///
+/// After merging, the size of a dimension of a memory is reduced if every
+/// assignment to its address port is a constant or the output of a `std_pad`.
+/// The width of the address ports is left unchanged.
+///
+/// Memories that are used by `invoke` statements and `ref` memories are left
+/// unchanged.
+/// This pass only renames uses of memories. `DeadCellRemoval` should be run
+/// after this to actually remove the memory definitions.
+pub struct MinimizeMems {
+    live: MemoryLiveRanges,
+    /// Memories used by `invoke` statements.
+    invoked: HashSet<ir::Id>,
+    rewrites: Vec<(RRC<ir::Cell>, RRC<ir::Cell>)>,
+    opts: ShareOpts,
+}
+
+impl Named for MinimizeMems {
+    fn name() -> &'static str {
+        "minimize-mems"
+    }
+    fn description() -> &'static str {
+        "merge internal memories with disjoint live ranges and shrink them"
+    }
+    fn opts() -> &'static [(&'static str, &'static str)] {
+        SHARE_OPTS
+    }
+}
+
+impl ConstructVisitor for MinimizeMems {
+    fn from(ctx: &ir::Context) -> FutilResult<Self> {
+        Ok(MinimizeMems {
+            live: MemoryLiveRanges::default(),
+            invoked: HashSet::new(),
+            rewrites: Vec::new(),
+            opts: ShareOpts::from_ctx::<Self>(ctx)?,
+        })
+    }
+}
+
+/// The size parameters of each dimension of the memory primitive `name`.
+pub(super) fn size_params(name: &str) -> &'static [&'static str] {
+    match name {
+        "std_mem_d1" => &["SIZE"],
+        "std_mem_d2" => &["D0_SIZE", "D1_SIZE"],
+        "std_mem_d3" => &["D0_SIZE", "D1_SIZE", "D2_SIZE"],
+        "std_mem_d4" => &["D0_SIZE", "D1_SIZE", "D2_SIZE", "D3_SIZE"],
+        _ => &[],
+    }
+}
+
+/// Cells whose ports are used by `invoke` statements in `c`.
+fn invoked_cells(c: &ir::Control, cells: &mut HashSet<ir::Id>) {
+    match c {
+        ir::Control::Empty(_) | ir::Control::Enable(_) => (),
+        ir::Control::Invoke(invoke) => {
+            for (_, port) in invoke.inputs.iter().chain(&invoke.outputs) {
+                cells.insert(port.borrow().get_parent_name());
+            }
+            for (_, cell) in &invoke.ref_cells {
+                cells.insert(cell.clone_name());
+            }
+        }
+        ir::Control::Seq(ir::Seq { stmts, .. })
+        | ir::Control::Par(ir::Par { stmts, .. }) => {
+            stmts.iter().for_each(|stmt| invoked_cells(stmt, cells))
+        }
+        ir::Control::If(ir::If {
+            tbranch, fbranch, ..
+        }) => {
+            invoked_cells(tbranch, cells);
+            invoked_cells(fbranch, cells);
+        }
+        ir::Control::While(ir::While { body, .. }) => {
+            invoked_cells(body, cells)
+        }
+    }
+}
+
+/// An upper bound on the values of the port `port`, if one is known.
+fn upper_bound(port: &ir::Port) -> Option<u64> {
+    let cell_ref = match &port.parent {
+        ir::PortParent::Cell(cell_wref) => cell_wref.upgrade(),
+        ir::PortParent::Group(_) => return None,
+    };
+    let cell = cell_ref.borrow();
+    match &cell.prototype {
+        ir::CellType::Constant { val, .. } => Some(val + 1),
+        ir::CellType::Primitive { name, .. } if name == "std_pad" => {
+            cell.get_parameter("IN_WIDTH").map(|width| 1 << width)
+        }
+        _ => None,
+    }
+}
+
+impl MinimizeMems {
+    /// Reduce the size of each dimension of `cell` to the bound on the
+    /// addresses assigned to it in `assigns`.
+    fn shrink(cell: &RRC<ir::Cell>, assigns: &[ir::Assignment]) {
+        let name = cell.clone_name();
+        let prim = match cell.borrow().type_name() {
+            Some(prim) => prim.clone(),
+            None => return,
+        };
+        for (idx, param) in size_params(prim.as_ref()).iter().enumerate() {
+            let addr = format!("addr{}", idx);
+            let bound = assigns
+                .iter()
+                .filter(|asgn| {
+                    let dst = asgn.dst.borrow();
+                    dst.get_parent_name() == name && dst.name == addr
+                })
+                .map(|asgn| upper_bound(&asgn.src.borrow()))
+                .try_fold(0, |acc, bound| bound.map(|b| acc.max(b)));
+            let mut cell = cell.borrow_mut();
+            if let (
+                Some(bound),
+                ir::CellType::Primitive { param_binding, .. },
+            ) = (bound, &mut cell.prototype)
+            {
+                for (key, size) in param_binding.iter_mut() {
+                    if key == param && bound > 0 && bound < *size {
+                        *size = bound;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ShareComponents for MinimizeMems {
+    fn initialize(
+        &mut self,
+        comp: &ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) {
+        self.invoked = HashSet::new();
+        invoked_cells(&comp.control.borrow(), &mut self.invoked);
+        self.live = MemoryLiveRanges::new(comp, |cell| self.cell_filter(cell));
+    }
+
+    fn lookup_group_conflicts(&self, group_name: &ir::Id) -> Vec<ir::Id> {
+        self.live.get(group_name).into_iter().collect()
+    }
+
+    fn cell_filter(&self, cell: &ir::Cell) -> bool {
+        match cell.type_name() {
+            Some(name) => {
+                !size_params(name.as_ref()).is_empty()
+                    && cell.get_attribute("external").is_none()
+                    && !cell.is_reference()
+                    && !self.invoked.contains(cell.name())
+            }
+            None => false,
+        }
+    }
+
+    fn custom_conflicts<F>(&self, comp: &ir::Component, mut add_conflicts: F)
+    where
+        F: FnMut(Vec<ir::Id>),
+    {
+        for group in comp.groups.iter() {
+            add_conflicts(
+                self.live.get(group.borrow().name()).into_iter().collect(),
+            );
+        }
+        // Memories used by continuous assignments conflict with every other
+        // memory.
+        for cell in comp.cells.iter() {
+            if !self.cell_filter(&cell.borrow()) {
+                continue;
+            }
+            let mut conflicts: Vec<_> =
+                self.live.global().iter().cloned().collect();
+            conflicts.push(cell.clone_name());
+            add_conflicts(conflicts);
+        }
+    }
+
+    fn set_rewrites(&mut self, rewrites: Vec<(RRC<ir::Cell>, RRC<ir::Cell>)>) {
+        self.rewrites = rewrites;
+    }
+
+    fn get_rewrites(&self) -> &[(RRC<ir::Cell>, RRC<ir::Cell>)] {
+        &self.rewrites
+    }
+
+    fn share_opts(&self) -> &ShareOpts {
+        &self.opts
+    }
+
+    fn finish_rewrites(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) {
+        let assigns: Vec<_> = comp
+            .groups
+            .iter()
+            .flat_map(|group| group.borrow().assignments.clone())
+            .chain(comp.continuous_assignments.iter().cloned())
+            .collect();
+        for cell in comp.cells.iter() {
+            if self.cell_filter(&cell.borrow()) {
+                Self::shrink(cell, &assigns);
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use super::sharing_components::ShareComponents;
+use super::sharing_components::{ShareComponents, ShareOpts, SHARE_OPTS};
 use crate::{
     analysis::LiveRangeAnalysis,
-    ir::{self, traversal::Named},
+    errors::FutilResult,
+    ir::{
+        self,
+        traversal::{ConstructVisitor, Named},
+    },
 };
 
 /// Given a `LiveRangeAnalysis` that specifies the registers alive at each
@@ -12,15 +16,15 @@ use crate::{
 /// between them in the interference graph. Additionally, if two registers
 /// are different sizes, then there is an edge between them.
 ///
-/// A greedy graph coloring algorithm on the interference graph
-/// is used to assign each register a name.
+/// A graph coloring of the interference graph is used to assign each register
+/// a name. The coloring algorithm is selected with the `coloring` option.
 ///
 /// This pass only renames uses of registers. `DeadCellRemoval` should be run after this
 /// to actually remove the register definitions.
-#[derive(Default)]
 pub struct MinimizeRegs {
     live: LiveRangeAnalysis,
     rewrites: Vec<(ir::RRC<ir::Cell>, ir::RRC<ir::Cell>)>,
+    opts: ShareOpts,
 }
 
 impl Named for MinimizeRegs {
@@ -30,6 +34,19 @@ impl Named for MinimizeRegs {
     fn description() -> &'static str {
         "use the fewest possible registers"
     }
+    fn opts() -> &'static [(&'static str, &'static str)] {
+        SHARE_OPTS
+    }
+}
+
+impl ConstructVisitor for MinimizeRegs {
+    fn from(ctx: &ir::Context) -> FutilResult<Self> {
+        Ok(MinimizeRegs {
+            live: LiveRangeAnalysis::default(),
+            rewrites: Vec::new(),
+            opts: ShareOpts::from_ctx::<Self>(ctx)?,
+        })
+    }
 }
 
 impl ShareComponents for MinimizeRegs {
@@ -73,4 +90,8 @@ impl ShareComponents for MinimizeRegs {
     fn get_rewrites(&self) -> &[(ir::RRC<ir::Cell>, ir::RRC<ir::Cell>)] {
         &self.rewrites
     }
+
+    fn share_opts(&self) -> &ShareOpts {
+        &self.opts
+    }
 }
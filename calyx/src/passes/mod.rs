@@ -1,9 +1,11 @@
 //! Passes for the Calyx compiler.
 mod clk_insertion;
 mod collapse_control;
+mod comb_loops;
 mod compile_control;
 mod compile_empty;
 mod compile_invoke;
+mod compile_ref;
 mod component_interface;
 mod dead_cell_removal;
 mod externalize;
@@ -12,9 +14,13 @@ mod guard_canonical;
 mod infer_static_timing;
 mod inliner;
 mod math_utilities;
+mod memory_banking;
 mod merge_assign;
+mod minimize_mems;
 mod minimize_regs;
+mod multiple_drivers;
 mod papercut;
+mod par_races;
 mod par_to_seq;
 mod register_unsharing;
 mod reset_insertion;
@@ -28,9 +34,11 @@ mod well_formed;
 
 pub use clk_insertion::ClkInsertion;
 pub use collapse_control::CollapseControl;
+pub use comb_loops::CombLoops;
 pub use compile_control::CompileControl;
 pub use compile_empty::CompileEmpty;
 pub use compile_invoke::CompileInvoke;
+pub use compile_ref::CompileRef;
 pub use component_interface::ComponentInterface;
 pub use dead_cell_removal::DeadCellRemoval;
 pub use externalize::Externalize;
@@ -38,9 +46,13 @@ pub use go_insertion::GoInsertion;
 pub use guard_canonical::GuardCanonical;
 pub use infer_static_timing::InferStaticTiming;
 pub use inliner::Inliner;
+pub use memory_banking::{BankScheme, BankedMemory, MemoryBanking};
 pub use merge_assign::MergeAssign;
+pub use minimize_mems::MinimizeMems;
 pub use minimize_regs::MinimizeRegs;
+pub use multiple_drivers::MultipleDrivers;
 pub use papercut::Papercut;
+pub use par_races::ParRaces;
 pub use par_to_seq::ParToSeq;
 pub use register_unsharing::RegisterUnsharing;
 pub use reset_insertion::ResetInsertion;
@@ -0,0 +1,231 @@
+use crate::errors::Error;
+use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::ir::{self, IRPrinter, LibrarySignatures};
+use boolean_expression::{Expr, BDD};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+/// Reports ports that may be driven by more than one assignment at the same
+/// time.
+///
+/// The assignments of each group are checked together with the continuous
+/// assignments, which are active whenever the group is. Two assignments to
+/// the same port conflict unless they drive the same value or a binary
+/// decision diagram proves that their guards are never true at the same
+/// time. Comparisons of the same port with constants, such as `fsm.out == 1`
+/// and `fsm.out < 1`, are known to be mutually exclusive when no value of the
+/// port satisfies both.
+///
+/// Assignments in groups that run in parallel are checked by `par-races`.
+#[derive(Default)]
+pub struct MultipleDrivers;
+
+impl Named for MultipleDrivers {
+    fn name() -> &'static str {
+        "multiple-drivers"
+    }
+
+    fn description() -> &'static str {
+        "report ports that may be driven by multiple assignments at once"
+    }
+}
+
+/// The value of `port` if it is the output of a constant or a `std_const`.
+fn constant(port: &ir::Port) -> Option<u64> {
+    let cell_ref = match &port.parent {
+        ir::PortParent::Cell(cell_wref) => cell_wref.upgrade(),
+        ir::PortParent::Group(_) => return None,
+    };
+    let cell = cell_ref.borrow();
+    match &cell.prototype {
+        ir::CellType::Constant { val, .. } => Some(*val),
+        ir::CellType::Primitive { name, .. } if name == "std_const" => {
+            cell.get_parameter("VALUE")
+        }
+        _ => None,
+    }
+}
+
+/// The values `[lo, hi)` of a port.
+type Interval = (u128, u128);
+
+/// If `guard` compares a port with a constant, returns the port, the number
+/// of values the port can have, and the values for which `guard` is true.
+fn const_cmp(guard: &ir::Guard) -> Option<((ir::Id, ir::Id), u128, Interval)> {
+    let (l, r) = match guard {
+        ir::Guard::Eq(l, r) | ir::Guard::Lt(l, r) | ir::Guard::Gt(l, r) => {
+            (l.borrow(), r.borrow())
+        }
+        _ => return None,
+    };
+    let (port, val, flipped) = match (constant(&l), constant(&r)) {
+        (None, Some(val)) => (l, val as u128, false),
+        (Some(val), None) => (r, val as u128, true),
+        _ => return None,
+    };
+    // The number of values of ports with 128 or more bits does not fit in a
+    // `u128`. Since constants have at most 64 bits, `u128::MAX` values are
+    // enough to decide which comparisons can be true at the same time.
+    let size = if port.width >= 128 {
+        u128::MAX
+    } else {
+        1u128 << port.width
+    };
+    let interval = match (guard, flipped) {
+        (ir::Guard::Eq(..), _) => (val, val + 1),
+        (ir::Guard::Lt(..), false) | (ir::Guard::Gt(..), true) => (0, val),
+        _ => (val + 1, size),
+    };
+    Some((port.canonical(), size, interval))
+}
+
+/// Returns true if a value in `[0, size)` can make the comparisons with the
+/// true values `i1` and `i2` evaluate to `b1` and `b2`.
+fn feasible(
+    size: u128,
+    (i1, b1): (Interval, bool),
+    (i2, b2): (Interval, bool),
+) -> bool {
+    let values = |(lo, hi): Interval, b: bool| -> Vec<Interval> {
+        if b {
+            vec![(lo, hi.min(size))]
+        } else {
+            vec![(0, lo.min(size)), (hi.min(size), size)]
+        }
+    };
+    values(i1, b1).into_iter().any(|(lo1, hi1)| {
+        values(i2, b2)
+            .into_iter()
+            .any(|(lo2, hi2)| lo1.max(lo2) < hi1.min(hi2))
+    })
+}
+
+/// Returns true if `a` and `b` always drive the same value.
+fn same_source(a: &ir::Assignment, b: &ir::Assignment) -> bool {
+    let (a, b) = (a.src.borrow(), b.src.borrow());
+    match (constant(&a), constant(&b)) {
+        (Some(v1), Some(v2)) => v1 == v2,
+        _ => a.canonical() == b.canonical(),
+    }
+}
+
+/// Returns true if `g1` and `g2` are never true at the same time.
+fn exclusive(g1: &ir::Guard, g2: &ir::Guard) -> bool {
+    let mut bdd = BDD::new();
+    let f1 = bdd.from_expr(&Expr::from(g1.clone()));
+    let f2 = bdd.from_expr(&Expr::from(g2.clone()));
+    let mut both = bdd.and(f1, f2);
+    // Guards that read constants, such as `1'd0`, are not variables.
+    for label in bdd.labels() {
+        if let ir::Guard::Port(port) = &label {
+            if let Some(val) = constant(&port.borrow()) {
+                both = bdd.restrict(both, label.clone(), val != 0);
+            }
+        }
+    }
+    // Rule out the outcomes of comparisons of a port with constants that no
+    // value of the port can produce, such as `fsm.out < 2` and
+    // `fsm.out == 3` both being true.
+    for (a, b) in bdd.labels().into_iter().tuple_combinations() {
+        let (p1, size, i1, p2, i2) = match (const_cmp(&a), const_cmp(&b)) {
+            (Some((p1, size, i1)), Some((p2, _, i2))) => (p1, size, i1, p2, i2),
+            _ => continue,
+        };
+        if p1 != p2 {
+            continue;
+        }
+        for &(b1, b2) in
+            &[(true, true), (true, false), (false, true), (false, false)]
+        {
+            if !feasible(size, (i1, b1), (i2, b2)) {
+                let ta = bdd.terminal(a.clone());
+                let tb = bdd.terminal(b.clone());
+                let la = if b1 { ta } else { bdd.not(ta) };
+                let lb = if b2 { tb } else { bdd.not(tb) };
+                let outcome = bdd.and(la, lb);
+                let impossible = bdd.not(outcome);
+                both = bdd.and(both, impossible);
+            }
+        }
+    }
+    !bdd.sat(both)
+}
+
+fn assignment_str(assign: &ir::Assignment) -> String {
+    let mut buf = Vec::new();
+    IRPrinter::write_assignment(assign, 0, &mut buf)
+        .expect("Failed to print assignment");
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// Returns an error for every port with conflicting drivers in `assigns`.
+/// Only conflicts involving at least one of the first `checked` assignments
+/// are reported.
+fn conflicts(
+    assigns: &[&ir::Assignment],
+    checked: usize,
+    context: &str,
+) -> Vec<Error> {
+    let mut by_dst: BTreeMap<(ir::Id, ir::Id), Vec<usize>> = BTreeMap::new();
+    for (idx, assign) in assigns.iter().enumerate() {
+        by_dst
+            .entry(assign.dst.borrow().canonical())
+            .or_default()
+            .push(idx);
+    }
+    by_dst
+        .values()
+        .filter_map(|idxs| {
+            idxs.iter().tuple_combinations().find(|(&i, &j)| {
+                let (a, b) = (assigns[i], assigns[j]);
+                i < checked
+                    && !same_source(a, b)
+                    && !exclusive(&a.guard, &b.guard)
+            })
+        })
+        .map(|(&i, &j)| {
+            let (a, b) = (assigns[i], assigns[j]);
+            Error::MalformedStructure(format!(
+                "Port `{}` may be driven by multiple assignments in {}:\n  {}\n  {}",
+                IRPrinter::get_port_access(&a.dst.borrow()),
+                context,
+                assignment_str(a),
+                assignment_str(b)
+            ))
+        })
+        .collect()
+}
+
+impl Visitor for MultipleDrivers {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let continuous: Vec<_> = comp.continuous_assignments.iter().collect();
+        let mut errors = conflicts(
+            &continuous,
+            continuous.len(),
+            &format!("the continuous assignments of component `{}`", comp.name),
+        );
+        for group_ref in comp.groups.iter() {
+            let group = group_ref.borrow();
+            let assigns: Vec<_> = group
+                .assignments
+                .iter()
+                .chain(continuous.iter().copied())
+                .collect();
+            errors.extend(conflicts(
+                &assigns,
+                group.assignments.len(),
+                &format!("group `{}`", group.name()),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(Action::Stop)
+        } else {
+            Err(Error::combine(errors))
+        }
+    }
+}
@@ -39,6 +39,35 @@ impl Default for Papercut<'_> {
                     ("write_data", "addr2"),
                 ],
             ),
+            (
+                "seq_mem_d1",
+                vec![
+                    ("write_data", "write_en"),
+                    ("write_data", "addr0"),
+                    ("read_en", "addr0"),
+                ],
+            ),
+            (
+                "seq_mem_d2",
+                vec![
+                    ("write_data", "write_en"),
+                    ("write_data", "addr0"),
+                    ("write_data", "addr1"),
+                    ("read_en", "addr0"),
+                    ("read_en", "addr1"),
+                ],
+            ),
+            (
+                "dp_mem_d1",
+                vec![
+                    ("write_data_a", "write_en_a"),
+                    ("write_data_a", "addr0_a"),
+                    ("read_en_a", "addr0_a"),
+                    ("write_data_b", "write_en_b"),
+                    ("write_data_b", "addr0_b"),
+                    ("read_en_b", "addr0_b"),
+                ],
+            ),
             ("std_mul_pipe", vec![("go", "left"), ("go", "right")]),
             ("std_mod_pipe", vec![("go", "left"), ("go", "right")]),
         ]
@@ -60,6 +89,10 @@ impl Named for Papercut<'_> {
 }
 
 impl Visitor for Papercut<'_> {
+    fn collect_errors() -> bool {
+        true
+    }
+
     fn start(
         &mut self,
         comp: &mut ir::Component,
@@ -0,0 +1,154 @@
+use crate::analysis::{ReadWriteSet, ScheduleConflicts};
+use crate::errors::{Error, FutilResult};
+use crate::ir::traversal::{
+    Action, ConstructVisitor, Loggable, Named, VisResult, Visitor,
+};
+use crate::ir::{self, CloneName, LibrarySignatures, RRC};
+use std::collections::{BTreeSet, HashMap};
+
+/// Reports data races between groups that may run in parallel.
+///
+/// Two groups race if they may run in parallel according to
+/// [ScheduleConflicts] and:
+/// - both write to the same cell (write-write), or
+/// - one of them writes to a cell that the other reads from (read-write).
+///
+/// A group writes to a cell if it assigns to one of its ports and reads from
+/// a cell if it uses one of its ports as a source or in a guard.
+/// Cells with the `allow_race` attribute are intentionally shared and are not
+/// checked.
+///
+/// Races are reported as errors unless the `warn` option is given, in which
+/// case they are logged and the program is left unchanged.
+/// Cells used by `invoke` statements and continuous assignments are not
+/// checked.
+pub struct ParRaces {
+    /// Log races instead of reporting errors.
+    warn: bool,
+}
+
+impl Named for ParRaces {
+    fn name() -> &'static str {
+        "par-races"
+    }
+
+    fn description() -> &'static str {
+        "report data races between groups that may run in parallel"
+    }
+
+    fn opts() -> &'static [(&'static str, &'static str)] {
+        &[("warn", "log races instead of reporting errors")]
+    }
+}
+
+impl ConstructVisitor for ParRaces {
+    fn from(ctx: &ir::Context) -> FutilResult<Self> {
+        let opts = Self::get_opts(ctx)?;
+        Ok(ParRaces {
+            warn: opts.contains_key("warn"),
+        })
+    }
+}
+
+/// The names of the cells in `cells` that are checked for races.
+fn checked(cells: Vec<RRC<ir::Cell>>) -> BTreeSet<ir::Id> {
+    cells
+        .into_iter()
+        .filter(|cell| {
+            let cell = cell.borrow();
+            !matches!(cell.prototype, ir::CellType::Constant { .. })
+                && cell.get_attribute("allow_race").is_none()
+        })
+        .map(|cell| cell.clone_name())
+        .collect()
+}
+
+impl Visitor for ParRaces {
+    fn start(
+        &mut self,
+        comp: &mut ir::Component,
+        _sigs: &LibrarySignatures,
+    ) -> VisResult {
+        let conflicts = ScheduleConflicts::from(&*comp.control.borrow());
+
+        // The cells read and written by each group.
+        let accesses: HashMap<ir::Id, (BTreeSet<ir::Id>, BTreeSet<ir::Id>)> =
+            comp.groups
+                .iter()
+                .map(|group| {
+                    let assigns = &group.borrow().assignments;
+                    (
+                        group.clone_name(),
+                        (
+                            checked(ReadWriteSet::read_set(assigns)),
+                            checked(ReadWriteSet::write_set(assigns)),
+                        ),
+                    )
+                })
+                .collect();
+
+        let pairs: BTreeSet<(ir::Id, ir::Id)> = conflicts
+            .all_conflicts()
+            .map(|(a, b)| if a <= b { (a, b) } else { (b, a) })
+            .collect();
+
+        let mut races = vec![];
+        for (a, b) in pairs {
+            let (a_reads, a_writes) = &accesses[&a];
+            let (b_reads, b_writes) = &accesses[&b];
+            for cell in a_writes.intersection(b_writes) {
+                let msg = if a == b {
+                    format!(
+                        "Group `{}` may run in parallel with itself and writes to `{}`.",
+                        a, cell
+                    )
+                } else {
+                    format!(
+                        "Groups `{}` and `{}` may run in parallel and both write to `{}`.",
+                        a, b, cell
+                    )
+                };
+                races.push((cell.clone(), msg));
+            }
+            let read_write = a_writes
+                .intersection(b_reads)
+                .map(|cell| (&a, &b, cell))
+                .chain(
+                    a_reads.intersection(b_writes).map(|cell| (&b, &a, cell)),
+                )
+                .filter(|(_, _, cell)| {
+                    !(a_writes.contains(*cell) && b_writes.contains(*cell))
+                });
+            for (writer, reader, cell) in read_write {
+                let msg = format!(
+                    "Group `{}` writes to `{}` while group `{}`, which may run in parallel, reads from it.",
+                    writer, cell, reader
+                );
+                races.push((cell.clone(), msg));
+            }
+        }
+
+        if races.is_empty() {
+            return Ok(Action::Stop);
+        }
+        if self.warn {
+            for (_, msg) in races {
+                self.elog(&comp.name, msg);
+            }
+            return Ok(Action::Stop);
+        }
+        let errors = races
+            .into_iter()
+            .map(|(cell, msg)| {
+                Error::DataRace(
+                    format!(
+                        "{} Add the `allow_race` attribute to the cell if this is intended.",
+                        msg
+                    ),
+                    cell,
+                )
+            })
+            .collect();
+        Err(Error::combine(errors))
+    }
+}
@@ -4,10 +4,11 @@ use crate::ir::{
     LibrarySignatures,
 };
 use ir::traversal::{Action, VisResult};
+use std::collections::HashSet;
 
 #[derive(Default)]
 /// Adds assignments from a components `reset` port to every
-/// component that contains an input `reset` port.
+/// cell that contains an input `reset` port that is not already driven.
 pub struct ResetInsertion;
 
 impl Named for ResetInsertion {
@@ -26,24 +27,44 @@ impl Visitor for ResetInsertion {
         comp: &mut ir::Component,
         sigs: &LibrarySignatures,
     ) -> VisResult {
+        // Ports that the program already drives explicitly.
+        let driven: HashSet<_> = comp
+            .groups
+            .iter()
+            .flat_map(|group| {
+                group
+                    .borrow()
+                    .assignments
+                    .iter()
+                    .map(|asgn| asgn.dst.borrow().canonical())
+                    .collect::<Vec<_>>()
+            })
+            .chain(
+                comp.continuous_assignments
+                    .iter()
+                    .map(|asgn| asgn.dst.borrow().canonical()),
+            )
+            .collect();
+
         let builder = ir::Builder::new(comp, sigs);
 
         for cell_ref in builder.component.cells.iter() {
             let cell = cell_ref.borrow();
-            if cell.get_attribute("generated").is_some() {
-                if let Some(port) = cell.find_with_attr("reset") {
-                    builder.component.continuous_assignments.push(
-                        builder.build_assignment(
-                            port,
-                            builder
-                                .component
-                                .signature
-                                .borrow()
-                                .get_with_attr("reset"),
-                            ir::Guard::True,
-                        ),
-                    )
+            if let Some(port) = cell.find_with_attr("reset") {
+                if driven.contains(&port.borrow().canonical()) {
+                    continue;
                 }
+                builder.component.continuous_assignments.push(
+                    builder.build_assignment(
+                        port,
+                        builder
+                            .component
+                            .signature
+                            .borrow()
+                            .get_with_attr("reset"),
+                        ir::Guard::True,
+                    ),
+                )
             }
         }
 
@@ -1,20 +1,29 @@
-use super::sharing_components::ShareComponents;
+use super::sharing_components::{ShareComponents, ShareOpts, SHARE_OPTS};
 use crate::analysis;
+use crate::errors::FutilResult;
 use crate::ir::{self, traversal::Named, CloneName, RRC};
 use ir::traversal::ConstructVisitor;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
 
 /// Rewrites groups to share cells marked with the "share" attribute
 /// when the groups are guaranteed to never run in parallel.
+///
+/// Components with the "static_sharing" attribute also share cells between
+/// groups in different arms of a statically timed `par` when the groups are
+/// never active in the same cycle. This requires the `par` to be compiled by
+/// the `static-timing` pass.
 pub struct ResourceSharing {
     /// Mapping from the name of a group to the cells that it uses.
-    used_cells_map: HashMap<ir::Id, Vec<ir::Id>>,
+    used_cells_map: BTreeMap<ir::Id, Vec<ir::Id>>,
 
     /// This is used to rewrite all uses of `old_cell` with `new_cell` in the group.
     rewrites: Vec<(RRC<ir::Cell>, RRC<ir::Cell>)>,
 
     /// Set of shareable components.
     shareable_components: HashSet<ir::Id>,
+
+    /// Options used to color the conflict graphs.
+    opts: ShareOpts,
 }
 
 impl Named for ResourceSharing {
@@ -25,10 +34,14 @@ impl Named for ResourceSharing {
     fn description() -> &'static str {
         "shares resources between groups that don't execute in parallel"
     }
+
+    fn opts() -> &'static [(&'static str, &'static str)] {
+        SHARE_OPTS
+    }
 }
 
 impl ConstructVisitor for ResourceSharing {
-    fn from(ctx: &ir::Context) -> Self {
+    fn from(ctx: &ir::Context) -> FutilResult<Self> {
         let mut shareable_components = HashSet::new();
         // add share=1 primitives to the shareable_components set
         for prim in ctx.lib.sigs.values() {
@@ -42,11 +55,12 @@ impl ConstructVisitor for ResourceSharing {
                 shareable_components.insert(comp.name.clone());
             }
         }
-        ResourceSharing {
-            used_cells_map: HashMap::new(),
+        Ok(ResourceSharing {
+            used_cells_map: BTreeMap::new(),
             rewrites: Vec::new(),
             shareable_components,
-        }
+            opts: ShareOpts::from_ctx::<Self>(ctx)?,
+        })
     }
 }
 
@@ -84,6 +98,18 @@ impl ShareComponents for ResourceSharing {
         }
     }
 
+    fn schedule_conflicts(
+        &self,
+        comp: &ir::Component,
+    ) -> analysis::ScheduleConflicts {
+        let control = comp.control.borrow();
+        if comp.attributes.has("static_sharing") {
+            analysis::ScheduleConflicts::from_static(&control)
+        } else {
+            analysis::ScheduleConflicts::from(&*control)
+        }
+    }
+
     fn custom_conflicts<F>(&self, _comp: &ir::Component, mut add_conflicts: F)
     where
         F: FnMut(Vec<ir::Id>),
@@ -100,4 +126,8 @@ impl ShareComponents for ResourceSharing {
     fn get_rewrites(&self) -> &[(RRC<ir::Cell>, RRC<ir::Cell>)] {
         &self.rewrites
     }
+
+    fn share_opts(&self) -> &ShareOpts {
+        &self.opts
+    }
 }
@@ -1,13 +1,100 @@
 use crate::{
-    analysis::{GraphColoring, ScheduleConflicts},
+    analysis::{ColoringStrategy, GraphColoring, ScheduleConflicts},
+    errors::{Error, FutilResult},
     ir,
 };
 use ir::{
-    traversal::{Action, VisResult, Visitor},
+    traversal::{
+        Action, ConstructVisitor, Loggable, Named, VisResult, Visitor,
+    },
     CloneName, RRC,
 };
 use itertools::Itertools;
-use std::{collections::HashMap, rc::Rc};
+use linked_hash_map::LinkedHashMap;
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+/// Options accepted by every pass that implements [ShareComponents].
+pub const SHARE_OPTS: &[(&str, &str)] = &[
+    (
+        "coloring",
+        "graph coloring algorithm: greedy (default), dsatur, or exact",
+    ),
+    (
+        "time-budget",
+        "milliseconds the exact coloring searches each graph (default: 1000)",
+    ),
+    (
+        "stats",
+        "report the number of cells of each type before and after sharing",
+    ),
+];
+
+/// Options that control how a [ShareComponents] pass colors the conflict
+/// graphs.
+#[derive(Default)]
+pub struct ShareOpts {
+    /// The graph coloring algorithm.
+    pub coloring: ColoringStrategy,
+    /// Report the number of cells before and after sharing.
+    pub stats: bool,
+}
+
+impl ShareOpts {
+    /// Parse the [SHARE_OPTS] given to the pass `P`.
+    pub fn from_ctx<P>(ctx: &ir::Context) -> FutilResult<Self>
+    where
+        P: Named + ConstructVisitor,
+    {
+        let opts = P::get_opts(ctx)?;
+        let budget = match opts.get("time-budget") {
+            Some(ms) => ms.parse().map_err(|_| {
+                Error::Misc(format!(
+                    "Invalid time-budget for pass {}: {}",
+                    P::name(),
+                    ms
+                ))
+            })?,
+            None => 1000,
+        };
+        let coloring = match opts.get("coloring").map(|c| c.as_str()) {
+            None | Some("greedy") => ColoringStrategy::Greedy,
+            Some("dsatur") => ColoringStrategy::DSatur,
+            Some("exact") => {
+                ColoringStrategy::Exact(Duration::from_millis(budget))
+            }
+            Some(coloring) => {
+                return Err(Error::Misc(format!(
+                    "Unknown coloring for pass {}: {}. Valid colorings: greedy, dsatur, exact.",
+                    P::name(),
+                    coloring
+                )))
+            }
+        };
+        Ok(ShareOpts {
+            coloring,
+            stats: opts.contains_key("stats"),
+        })
+    }
+}
+
+/// A readable name for the cells with the prototype `proto`.
+fn type_string(proto: &ir::CellType) -> String {
+    match proto {
+        ir::CellType::Primitive {
+            name,
+            param_binding,
+        } => format!(
+            "{}({})",
+            name,
+            param_binding.iter().map(|(_, v)| v.to_string()).join(", ")
+        ),
+        ir::CellType::Component { name } => format!("{}()", name),
+        ir::CellType::ThisComponent => "this".to_string(),
+        ir::CellType::Constant { val, width } => {
+            format!("const({}, {})", val, width)
+        }
+    }
+}
 
 /// A trait for implementing passes that want to share components
 /// by building a conflict graph and performing graph coloring
@@ -18,18 +105,19 @@ use std::{collections::HashMap, rc::Rc};
 ///  - `cell_filter`
 ///  - `set_rewrites`
 ///  - `get_rewrites`
+///  - `share_opts`
 ///
 /// Given these functions, the trait `Visitor` will automatically be
 /// implemented for your struct.
 ///
 /// The algorithm that runs is:
 ///  - instantiate conflict graph using all component cells that satisfy `cell_filter`
-///  - use `ScheduleConflicts` to find groups that run in parallel with each other
+///  - use `schedule_conflicts` to find groups that run in parallel with each other
 ///  - for each group, `G` that runs in parallel with another group `H`, add edges between
 ///  each cell in the sets `lookup_group_conflicts(G)` and `lookup_group_conflicts(H)`.
 ///  - add conflicts between cells where for `c0 != c1`
 ///  - call `custom_conflicts` to insert pass specific conflict edges
-///  - perform graph coloring using `self.ordering` to define the order of the greedy coloring
+///  - perform graph coloring using the algorithm selected by `share_opts`
 ///  - use coloring to rewrite group assignments, continuous assignments, and conditional ports.
 pub trait ShareComponents {
     /// Initialize the structure using `&ir::Component` and `&ir::LibrarySignatures`.
@@ -60,6 +148,13 @@ pub trait ShareComponents {
         cell0.prototype == cell1.prototype
     }
 
+    /// Construct the graph of groups that run in parallel in `comp`. The
+    /// default implementation assumes that groups in different arms of a
+    /// `par` always run in parallel.
+    fn schedule_conflicts(&self, comp: &ir::Component) -> ScheduleConflicts {
+        ScheduleConflicts::from(&*comp.control.borrow())
+    }
+
     /// Called after the initial conflict graph is constructed.
     /// This function let's you add custom conflicts to the graph
     /// before graph coloring is performed.
@@ -74,9 +169,21 @@ pub trait ShareComponents {
 
     /// Get the list of rewrites.
     fn get_rewrites(&self) -> &[(RRC<ir::Cell>, RRC<ir::Cell>)];
+
+    /// The options used to color the conflict graphs.
+    fn share_opts(&self) -> &ShareOpts;
+
+    /// Called after the uses of cells in assignments have been rewritten.
+    fn finish_rewrites(
+        &mut self,
+        _comp: &mut ir::Component,
+        _sigs: &ir::LibrarySignatures,
+    ) {
+        // nothing
+    }
 }
 
-impl<T: ShareComponents> Visitor for T {
+impl<T: ShareComponents + Named> Visitor for T {
     fn start(
         &mut self,
         comp: &mut ir::Component,
@@ -91,24 +198,28 @@ impl<T: ShareComponents> Visitor for T {
             .map(|cell| (cell.clone_name(), cell.borrow().prototype.clone()))
             .collect();
 
-        let mut cells_by_type: HashMap<ir::CellType, Vec<ir::Id>> =
-            HashMap::new();
+        // Keep the cell types in the order their first cell is defined so
+        // that the rewrites do not depend on the iteration order of a map.
+        let mut cells_by_type: LinkedHashMap<ir::CellType, Vec<ir::Id>> =
+            LinkedHashMap::new();
         for cell in cells {
             cells_by_type
                 .entry(cell.borrow().prototype.clone())
-                .and_modify(|v| v.push(cell.clone_name()))
-                .or_insert_with(|| vec![cell.clone_name()]);
+                .or_insert_with(Vec::new)
+                .push(cell.clone_name());
         }
 
-        let mut graphs_by_type: HashMap<ir::CellType, GraphColoring<ir::Id>> =
-            cells_by_type
-                .into_iter()
-                .map(|(key, cell_names)| {
-                    (key, GraphColoring::from(cell_names.into_iter()))
-                })
-                .collect();
+        let mut graphs_by_type: LinkedHashMap<
+            ir::CellType,
+            GraphColoring<ir::Id>,
+        > = cells_by_type
+            .into_iter()
+            .map(|(key, cell_names)| {
+                (key, GraphColoring::from(cell_names.into_iter()))
+            })
+            .collect();
 
-        let par_conflicts = ScheduleConflicts::from(&*comp.control.borrow());
+        let par_conflicts = self.schedule_conflicts(comp);
         let group_conflicts = par_conflicts
             .all_conflicts()
             .into_grouping_map_by(|(g1, _)| g1.clone())
@@ -154,14 +265,31 @@ impl<T: ShareComponents> Visitor for T {
             }
         });
 
+        let opts = self.share_opts();
         let mut coloring = Vec::new();
-        for graph in graphs_by_type.values() {
+        let mut stats = Vec::new();
+        for (proto, graph) in &graphs_by_type {
             if graph.has_nodes() {
-                coloring.extend(graph.color_greedy().iter().map(|(a, b)| {
+                let rewrites = graph.color(opts.coloring);
+                if opts.stats {
+                    let before = graph.node_count();
+                    stats.push((
+                        type_string(proto),
+                        before,
+                        before - rewrites.len(),
+                    ));
+                }
+                coloring.extend(rewrites.iter().sorted().map(|(a, b)| {
                     (comp.find_cell(&a).unwrap(), comp.find_cell(&b).unwrap())
                 }));
             }
         }
+        for (proto, before, after) in stats.into_iter().sorted() {
+            self.elog(
+                &comp.name,
+                format!("{}: {} -> {} cells", proto, before, after),
+            );
+        }
 
         // apply the coloring as a renaming of registers for both groups
         // and continuous assignments
@@ -179,6 +307,7 @@ impl<T: ShareComponents> Visitor for T {
         builder.component.continuous_assignments = assigns;
 
         self.set_rewrites(coloring);
+        self.finish_rewrites(comp, sigs);
 
         Ok(Action::Continue)
     }
@@ -1,9 +1,10 @@
 use super::math_utilities::get_bit_width_from;
+use super::top_down_compile_control::cond_label;
 use crate::ir::traversal::{Action, Named, VisResult, Visitor};
-use crate::ir::{self, LibrarySignatures};
+use crate::ir::{self, LibrarySignatures, RRC};
 use crate::{build_assignments, guard, structure};
 use itertools::Itertools;
-use std::{cmp, rc::Rc};
+use std::{cmp, ops::RangeInclusive, rc::Rc};
 
 #[derive(Default)]
 /// Optimized lowering for control statements that only contain groups with
@@ -42,6 +43,33 @@ where
         .fold_options(0, acc)
 }
 
+/// Record the control statements active in each range of states of `fsm`,
+/// the register implementing `group`.
+fn record_states(
+    comp: &mut ir::Component,
+    fsm: &RRC<ir::Cell>,
+    group: &RRC<ir::Group>,
+    states: Vec<(RangeInclusive<u64>, Vec<String>)>,
+) {
+    comp.fsm_states.push(ir::FsmStates {
+        cell: fsm.borrow().name().clone(),
+        group: group.borrow().name().clone(),
+        states: states
+            .into_iter()
+            .filter(|(range, _)| !range.is_empty())
+            .sorted_by_key(|(range, _)| (*range.start(), *range.end()))
+            .coalesce(|(r1, mut c1), (r2, c2)| {
+                if r1 == r2 {
+                    c1.extend(c2);
+                    Ok((r1, c1))
+                } else {
+                    Err(((r1, c1), (r2, c2)))
+                }
+            })
+            .collect(),
+    });
+}
+
 impl Visitor for StaticTiming {
     fn finish_while(
         &mut self,
@@ -159,6 +187,23 @@ impl Visitor for StaticTiming {
                 );
                 comp.continuous_assignments.append(&mut cleanup);
 
+                record_states(
+                    comp,
+                    &fsm,
+                    &while_group,
+                    vec![
+                        (0..=ctime - 1, vec![cond_label("while", port, cond)]),
+                        (
+                            ctime..=body_end_time - 1,
+                            vec![body.borrow().name().to_string()],
+                        ),
+                        (
+                            body_end_time..=body_end_time,
+                            vec!["end while".to_string()],
+                        ),
+                    ],
+                );
+
                 return Ok(Action::Change(ir::Control::enable(while_group)));
             }
         }
@@ -283,6 +328,27 @@ impl Visitor for StaticTiming {
                 );
                 comp.continuous_assignments.append(&mut clean_assigns);
 
+                // The condition is stored in state `ctime` and the branches
+                // start in the state after it.
+                let end_time = cmp::max(end_true_time, end_false_time);
+                record_states(
+                    comp,
+                    &fsm,
+                    &if_group,
+                    vec![
+                        (0..=ctime, vec![cond_label("if", &s.port, cond)]),
+                        (
+                            ctime + 1..=end_true_time - 1,
+                            vec![tru.borrow().name().to_string()],
+                        ),
+                        (
+                            ctime + 1..=end_false_time - 1,
+                            vec![fal.borrow().name().to_string()],
+                        ),
+                        (end_time..=end_time, vec!["done".to_string()]),
+                    ],
+                );
+
                 return Ok(Action::Change(ir::Control::enable(if_group)));
             }
         }
@@ -323,6 +389,8 @@ impl Visitor for StaticTiming {
                 par_group["done"] = done_guard ? signal_const["out"];
             );
             par_group.borrow_mut().assignments.append(&mut assigns);
+            let mut states =
+                vec![(max_time..=max_time, vec!["done".to_string()])];
             for con in s.stmts.iter() {
                 if let ir::Control::Enable(data) = con {
                     let group = &data.group;
@@ -340,6 +408,14 @@ impl Visitor for StaticTiming {
                       group["go"] = go_guard ? signal_const["out"];
                     );
                     par_group.borrow_mut().assignments.append(&mut assigns);
+
+                    // The group is computed in states [0, static_time).
+                    if static_time > 0 {
+                        states.push((
+                            0..=static_time - 1,
+                            vec![group.borrow().name().to_string()],
+                        ));
+                    }
                 }
             }
 
@@ -352,6 +428,7 @@ impl Visitor for StaticTiming {
                 fsm["write_en"] = done_guard ? signal_const["out"];
             );
             comp.continuous_assignments.append(&mut cleanup_assigns);
+            record_states(comp, &fsm, &par_group, states);
 
             Ok(Action::Change(ir::Control::enable(par_group)))
         } else {
@@ -387,6 +464,7 @@ impl Visitor for StaticTiming {
         );
 
         let mut cur_cycle = 0;
+        let mut states = Vec::new();
         for con in s.stmts.iter() {
             if let ir::Control::Enable(data) = con {
                 let group = &data.group;
@@ -418,6 +496,12 @@ impl Visitor for StaticTiming {
                 );
                 seq_group.borrow_mut().assignments.append(&mut assigns);
 
+                if static_time > 0 {
+                    states.push((
+                        cur_cycle..=cur_cycle + static_time - 1,
+                        vec![group.borrow().name().to_string()],
+                    ));
+                }
                 cur_cycle += static_time;
             }
         }
@@ -447,6 +531,8 @@ impl Visitor for StaticTiming {
             fsm["write_en"] = done_guard ? signal_const["out"];
         );
         comp.continuous_assignments.append(&mut cleanup_assigns);
+        states.push((cur_cycle..=cur_cycle, vec!["done".to_string()]));
+        record_states(comp, &fsm, &seq_group, states);
 
         // Add static attribute to this group.
         seq_group
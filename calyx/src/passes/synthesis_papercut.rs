@@ -4,7 +4,10 @@ use crate::ir::traversal::{Action, Named, VisResult, Visitor};
 use crate::ir::{self, CloneName, LibrarySignatures};
 use std::collections::HashSet;
 
+/// Prefix of the read data ports of a memory. Dual-port memories have a read
+/// port for each of their ports, like `read_data_a`.
 const READ_PORT: &str = "read_data";
+/// Prefix of the write data ports of a memory.
 const WRITE_PORT: &str = "write_data";
 
 /// Pass to check common synthesis issues.
@@ -17,10 +20,18 @@ pub struct SynthesisPapercut {
 
 impl Default for SynthesisPapercut {
     fn default() -> Self {
-        let memories = ["std_mem_d1", "std_mem_d2", "std_mem_d3", "std_mem_d4"]
-            .iter()
-            .map(|&mem| mem.into())
-            .collect();
+        let memories = [
+            "std_mem_d1",
+            "std_mem_d2",
+            "std_mem_d3",
+            "std_mem_d4",
+            "seq_mem_d1",
+            "seq_mem_d2",
+            "dp_mem_d1",
+        ]
+        .iter()
+        .map(|&mem| mem.into())
+        .collect();
         SynthesisPapercut { memories }
     }
 }
@@ -73,8 +84,20 @@ impl Visitor for SynthesisPapercut {
 
         for mem in memory_cells {
             let cell = comp.find_cell(&mem).unwrap();
-            let read_port = cell.borrow().get(READ_PORT);
-            if analysis.reads_from(&*read_port.borrow()).next().is_none() {
+            let cell = cell.borrow();
+            let ports_with = |prefix: &str| {
+                cell.ports
+                    .iter()
+                    .filter(|port| {
+                        port.borrow().name.as_ref().starts_with(prefix)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+            let is_read = ports_with(READ_PORT).iter().any(|port| {
+                analysis.reads_from(&port.borrow()).next().is_some()
+            });
+            if !is_read {
                 return Err(Error::Papercut(
                     format!(
                         "Only writes performed on memory `{}'. Synthesis tools will remove this memory. Add @external(1) to cell to turn this into an interface memory.",
@@ -83,8 +106,10 @@ impl Visitor for SynthesisPapercut {
                     mem,
                 ));
             }
-            let write_port = cell.borrow().get(WRITE_PORT);
-            if analysis.writes_to(&*write_port.borrow()).next().is_none() {
+            let is_written = ports_with(WRITE_PORT).iter().any(|port| {
+                analysis.writes_to(&port.borrow()).next().is_some()
+            });
+            if !is_written {
                 return Err(Error::Papercut(
                     format!(
                         "Only reads performed on memory `{}'. Synthesis tools will remove this memory. Add @external(1) to cell to turn this into an interface memory.",
@@ -18,9 +18,16 @@ struct Schedule {
     pub enables: HashMap<u64, Vec<ir::Assignment>>,
     /// Transition from one state to another when the guard is true.
     pub transitions: Vec<(u64, u64, ir::Guard)>,
+    /// Control statements started in a given state.
+    pub labels: HashMap<u64, Vec<String>>,
 }
 
 impl Schedule {
+    /// Record that the control statement `label` starts in `state`.
+    fn label(&mut self, state: u64, label: String) {
+        self.labels.entry(state).or_default().push(label);
+    }
+
     /// Validate that all states are reachable in the transition graph.
     fn validate(&self) {
         let graph = DiGraph::<(), u32>::from_edges(
@@ -104,6 +111,7 @@ fn calculate_states(
                 .entry(cur_state)
                 .or_default()
                 .append(&mut en_go);
+            schedule.label(cur_state, group.borrow().name().to_string());
 
             schedule.transitions.push((cur_state, nxt_state, done_cond));
             nxt_state
@@ -165,6 +173,7 @@ fn calculate_states(
                 .entry(cur_state)
                 .or_default()
                 .append(&mut cond_save_assigns);
+            schedule.label(cur_state, cond_label("if", port, cond));
             schedule.transitions.push((
                 cur_state,
                 after_cond_compute,
@@ -205,6 +214,7 @@ fn calculate_states(
                 .entry(next)
                 .or_default()
                 .append(&mut cleanup);
+            schedule.label(next, "end if".to_string());
 
             next
         }
@@ -249,6 +259,7 @@ fn calculate_states(
                 .entry(cur_state)
                 .or_default()
                 .append(&mut cond_save_assigns);
+            schedule.label(cur_state, cond_label("while", port, cond));
             schedule.transitions.push((
                 cur_state,
                 after_cond_compute,
@@ -285,6 +296,7 @@ fn calculate_states(
                 .entry(exit)
                 .or_default()
                 .append(&mut cleanup);
+            schedule.label(exit, "end while".to_string());
 
             exit
         }
@@ -301,6 +313,16 @@ fn calculate_states(
     }
 }
 
+/// Label for the state computing the condition of an `if` or `while`.
+pub(super) fn cond_label(
+    kind: &str,
+    port: &RRC<ir::Port>,
+    cond: &RRC<ir::Group>,
+) -> String {
+    let (cell, port) = port.borrow().canonical();
+    format!("{} {}.{} with {}", kind, cell, port, cond.borrow().name())
+}
+
 /// Implement a given [Schedule] and return the name of the [`ir::Group`](crate::ir::Group) that
 /// implements it.
 fn realize_schedule(
@@ -321,6 +343,22 @@ fn realize_schedule(
     // The compilation group
     let group = builder.add_group("tdcc");
 
+    // Remember what each state implements.
+    let mut labels = schedule.labels;
+    labels
+        .entry(final_state)
+        .or_default()
+        .push("done".to_string());
+    builder.component.fsm_states.push(ir::FsmStates {
+        cell: fsm.borrow().name().clone(),
+        group: group.borrow().name().clone(),
+        states: labels
+            .into_iter()
+            .sorted_by_key(|(state, _)| *state)
+            .map(|(state, control)| (state..=state, control))
+            .collect(),
+    });
+
     // Enable assignments
     group.borrow_mut().assignments.extend(
         schedule
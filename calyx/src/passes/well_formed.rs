@@ -1,23 +1,44 @@
-use crate::errors::Error;
-use crate::ir::traversal::{Action, Named, VisResult, Visitor};
+use crate::errors::{Error, FutilResult};
+use crate::ir::traversal::{
+    Action, ConstructVisitor, Named, VisResult, Visitor,
+};
 use crate::ir::{self, CloneName, Component, LibrarySignatures};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+/// The name, width, and direction of each port on a cell.
+type PortSig = Vec<(ir::Id, u64, ir::Direction)>;
+
+/// Returns the signature of the ports defined on `cell`.
+fn port_signature(cell: &ir::Cell) -> PortSig {
+    cell.ports
+        .iter()
+        .map(|p| {
+            let p = p.borrow();
+            (p.name.clone(), p.width, p.direction.clone())
+        })
+        .collect()
+}
 
 /// Pass to check if the program is well-formed.
 ///
 /// Catches the following errors:
 /// 1. Programs that use reserved SystemVerilog keywords as identifiers.
 /// 2. Programs that don't use a defined group.
+/// 3. `invoke` statements that don't bind every `ref` cell of the invoked
+///    component to a cell with a matching signature.
 pub struct WellFormed {
     /// Set of names that components and cells are not allowed to have.
     reserved_names: HashSet<String>,
 
     /// Names of the groups that have been used in the control.
     used_groups: HashSet<ir::Id>,
+
+    /// Mapping from component names to the signatures of their `ref` cells.
+    ref_cells: HashMap<ir::Id, Vec<(ir::Id, PortSig)>>,
 }
 
-impl Default for WellFormed {
-    fn default() -> Self {
+impl ConstructVisitor for WellFormed {
+    fn from(ctx: &ir::Context) -> FutilResult<Self> {
         let reserved_names = vec![
             "reg", "wire", "always", "posedge", "negedge", "logic", "tri",
             "input", "output", "if", "generate", "var", "go", "done", "clk",
@@ -26,10 +47,28 @@ impl Default for WellFormed {
         .map(|s| s.to_string())
         .collect();
 
-        WellFormed {
+        let ref_cells = ctx
+            .components
+            .iter()
+            .map(|comp| {
+                let refs = comp
+                    .cells
+                    .iter()
+                    .filter(|cell| cell.borrow().is_reference())
+                    .map(|cell| {
+                        let cell = cell.borrow();
+                        (cell.clone_name(), port_signature(&cell))
+                    })
+                    .collect();
+                (comp.name.clone(), refs)
+            })
+            .collect();
+
+        Ok(WellFormed {
             reserved_names,
             used_groups: HashSet::new(),
-        }
+            ref_cells,
+        })
     }
 }
 
@@ -44,17 +83,26 @@ impl Named for WellFormed {
 }
 
 impl Visitor for WellFormed {
+    fn collect_errors() -> bool {
+        true
+    }
+
     fn start(
         &mut self,
         comp: &mut Component,
         _ctx: &LibrarySignatures,
     ) -> VisResult {
         // Check if any of the cells use a reserved name.
-        for cell_ref in comp.cells.iter() {
-            let cell = cell_ref.borrow();
-            if self.reserved_names.contains(&cell.name().id) {
-                return Err(Error::ReservedName(cell.clone_name()));
-            }
+        let reserved = comp
+            .cells
+            .iter()
+            .filter(|cell| {
+                self.reserved_names.contains(&cell.borrow().name().id)
+            })
+            .map(|cell| Error::ReservedName(cell.clone_name()))
+            .collect::<Vec<_>>();
+        if !reserved.is_empty() {
+            return Err(Error::combine(reserved));
         }
 
         // Check if any groups refer to another group's done signal.
@@ -109,6 +157,57 @@ impl Visitor for WellFormed {
                     port.borrow().name)
             }
         }
+
+        // Check that the `ref` cells of the invoked component are bound to
+        // cells with matching signatures.
+        let cell = s.comp.borrow();
+        if cell.is_reference() {
+            return Err(Error::MalformedControl(
+                cell.name().fmt_err("Cannot invoke a `ref` cell"),
+            ));
+        }
+        let refs = match &cell.prototype {
+            ir::CellType::Component { name } => &self.ref_cells[name],
+            _ => {
+                return match s.ref_cells.first() {
+                    Some((id, _)) => {
+                        Err(Error::MalformedControl(id.fmt_err(&format!(
+                            "`{}` is not a component and has no `ref` cells",
+                            cell.name()
+                        ))))
+                    }
+                    None => Ok(Action::Continue),
+                }
+            }
+        };
+        let mut bound = HashSet::new();
+        for (id, bound_cell) in &s.ref_cells {
+            let (_, sig) =
+                refs.iter().find(|(name, _)| name == id).ok_or_else(|| {
+                    Error::Undefined(id.clone(), "ref cell".to_string())
+                })?;
+            if !bound.insert(id) {
+                return Err(Error::AlreadyBound(
+                    id.clone(),
+                    "ref cell binding".to_string(),
+                ));
+            }
+            if port_signature(&bound_cell.borrow()) != *sig {
+                return Err(Error::MalformedControl(id.fmt_err(&format!(
+                    "Cell `{}` does not match the signature of `ref` cell `{}`",
+                    bound_cell.borrow().name(),
+                    id
+                ))));
+            }
+        }
+        if let Some((name, _)) =
+            refs.iter().find(|(name, _)| !bound.contains(name))
+        {
+            return Err(Error::MalformedControl(cell.name().fmt_err(
+                &format!("`ref` cell `{}` is not bound by `invoke`", name),
+            )));
+        }
+
         Ok(Action::Continue)
     }
 
@@ -139,13 +238,17 @@ impl Visitor for WellFormed {
         comp: &mut Component,
         _ctx: &LibrarySignatures,
     ) -> VisResult {
-        let all_groups: HashSet<ir::Id> =
-            comp.groups.iter().map(|g| g.clone_name()).collect();
-        let unused_group =
-            all_groups.difference(&self.used_groups).into_iter().next();
-        match unused_group {
-            Some(group) => Err(Error::UnusedGroup(group.clone())),
-            None => Ok(Action::Continue),
+        let unused_groups = comp
+            .groups
+            .iter()
+            .map(|g| g.clone_name())
+            .filter(|name| !self.used_groups.contains(name))
+            .map(Error::UnusedGroup)
+            .collect::<Vec<_>>();
+        if unused_groups.is_empty() {
+            Ok(Action::Continue)
+        } else {
+            Err(Error::combine(unused_groups))
         }
     }
 }
@@ -134,6 +134,22 @@ impl InterpreterState {
                 }
                 prim
             }
+            "seq_mem_d1" | "seq_mem_d2" | "dp_mem_d1" => {
+                let mut prim = Box::new(match prim_name.as_ref() {
+                    "seq_mem_d1" => stateful::SeqMem::new_d1(params),
+                    "seq_mem_d2" => stateful::SeqMem::new_d2(params),
+                    _ => stateful::SeqMem::new_dual_port(params),
+                });
+
+                let init = mems
+                    .as_ref()
+                    .and_then(|x| cell_name.and_then(|name| x.get(name)));
+
+                if let Some(vals) = init {
+                    prim.initialize_memory(vals);
+                }
+                prim
+            }
 
             p => panic!("Unknown primitive: {}", p),
         }
@@ -155,7 +171,8 @@ impl InterpreterState {
                 {
                     let cell_name = match name.as_ref() {
                         "std_mem_d1" | "std_mem_d2" | "std_mem_d3"
-                        | "std_mem_d4" => Some(cl.name()),
+                        | "std_mem_d4" | "seq_mem_d1" | "seq_mem_d2"
+                        | "dp_mem_d1" => Some(cl.name()),
                         _ => None,
                     };
 
@@ -425,10 +425,14 @@ fn eval_prims<'a, 'b, I: Iterator<Item = &'b RRC<ir::Cell>>>(
                 prim.clear_update_buffer();
                 prim.reset(&inputs)
             } else {
+                // Primitives like `seq_mem_d1` have several done signals
+                // and no `done` port.
                 let done_val = if prim.is_comb() {
                     None
                 } else {
-                    Some(env.get_as_val(&(cell.borrow().get("done").borrow())))
+                    cell.borrow()
+                        .find("done")
+                        .map(|done| env.get_as_val(&done.borrow()))
                 };
                 prim.execute(&inputs, done_val)
             };
@@ -23,9 +23,9 @@ pub struct Opts {
     #[structopt(short = "o", long = "output", default_value)]
     pub output: OutputFile,
 
-    /// Path to the primitives library
-    #[structopt(long, short, default_value = "..")]
-    pub lib_path: PathBuf,
+    /// Paths searched for imported files. Can be given multiple times.
+    #[structopt(long, short, default_value = "..", number_of_values = 1)]
+    pub lib_path: Vec<PathBuf>,
 
     /// Path to optional datafile used to initialze memories. If it is not
     /// provided memories will be initialzed with zeros
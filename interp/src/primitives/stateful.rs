@@ -1020,3 +1020,288 @@ impl Primitive for StdMemD4 {
         )
     }
 }
+
+/// A port of a [SeqMem].
+#[derive(Debug)]
+struct SeqMemPort {
+    /// Suffix of the names of the signals of this port.
+    suffix: &'static str,
+    /// The current value of `read_data`.
+    read_data: Value,
+    /// The current values of `write_done` and `read_done`. These are set
+    /// when an update is committed and cleared when the memory is reset at
+    /// the end of a group.
+    write_done: bool,
+    read_done: bool,
+    read_update: Option<Value>,
+    write_update: Option<(u64, Value)>,
+}
+
+impl SeqMemPort {
+    fn new(suffix: &'static str, width: u64) -> Self {
+        SeqMemPort {
+            suffix,
+            read_data: Value::zeroes(width as usize),
+            write_done: false,
+            read_done: false,
+            read_update: None,
+            write_update: None,
+        }
+    }
+
+    fn signal(&self, name: &str) -> ir::Id {
+        ir::Id::from(format!("{}{}", name, self.suffix))
+    }
+}
+
+/// A memory with registered reads. Implements `seq_mem_d1`, `seq_mem_d2`,
+/// and the true dual-port `dp_mem_d1`, whose ports use the signals suffixed
+/// with `_a` and `_b`.
+///
+/// Each port has the following inputs:
+/// * addr0, addr1, ...: The index to be accessed or updated.
+/// * write_data: WIDTH - Data to be written to the selected memory slot.
+/// * write_en: 1 - Writes write_data to the slot indexed by the address.
+/// * read_en: 1 - Reads the slot indexed by the address into read_data.
+///
+/// And the following outputs:
+/// * read_data: WIDTH - The value read by the last read.
+/// * write_done: 1 - Goes high for one cycle after a write.
+/// * read_done: 1 - Goes high for one cycle after a read.
+///
+/// Unlike std_mem, read_data is updated one cycle after read_en is asserted.
+/// Reads return the value stored before a write in the same cycle. When both
+/// ports of a dual-port memory write the same slot, the write of port `b`
+/// takes effect.
+#[derive(Debug)]
+pub struct SeqMem {
+    pub width: u64,
+    /// The size and index width of each dimension.
+    pub dims: Vec<(u64, u64)>,
+    pub data: Vec<Value>,
+    ports: Vec<SeqMemPort>,
+}
+
+impl SeqMem {
+    fn from_params(
+        params: &ir::Binding,
+        prim: &str,
+        dims: &[(&str, &str)],
+        ports: &[&'static str],
+    ) -> Self {
+        let param = |name: &str| {
+            get_param(params, name).unwrap_or_else(|| {
+                panic!("Missing {} parameter for {}", name, prim)
+            })
+        };
+        let width = param("WIDTH");
+        let dims: Vec<_> = dims
+            .iter()
+            .map(|(size, idx_size)| (param(size), param(idx_size)))
+            .collect();
+        let size: u64 = dims.iter().map(|(size, _)| size).product();
+        SeqMem {
+            width,
+            data: vec![Value::zeroes(width as usize); size as usize],
+            dims,
+            ports: ports
+                .iter()
+                .map(|suffix| SeqMemPort::new(suffix, width))
+                .collect(),
+        }
+    }
+
+    /// Instantiates a `seq_mem_d1`.
+    pub fn new_d1(params: ir::Binding) -> Self {
+        Self::from_params(&params, "seq_mem_d1", &[("SIZE", "IDX_SIZE")], &[""])
+    }
+
+    /// Instantiates a `seq_mem_d2`.
+    pub fn new_d2(params: ir::Binding) -> Self {
+        Self::from_params(
+            &params,
+            "seq_mem_d2",
+            &[("D0_SIZE", "D0_IDX_SIZE"), ("D1_SIZE", "D1_IDX_SIZE")],
+            &[""],
+        )
+    }
+
+    /// Instantiates a `dp_mem_d1`.
+    pub fn new_dual_port(params: ir::Binding) -> Self {
+        Self::from_params(
+            &params,
+            "dp_mem_d1",
+            &[("SIZE", "IDX_SIZE")],
+            &["_a", "_b"],
+        )
+    }
+
+    pub fn initialize_memory(&mut self, vals: &[Value]) {
+        assert_eq!(self.data.len(), vals.len());
+
+        for (idx, val) in vals.iter().enumerate() {
+            assert_eq!(val.len(), self.width as usize);
+            self.data[idx] = val.clone()
+        }
+    }
+
+    /// The index into `data` addressed by `port`.
+    fn calc_addr(&self, port: &SeqMemPort, inputs: &[(ir::Id, &Value)]) -> u64 {
+        self.dims
+            .iter()
+            .enumerate()
+            .fold(0, |addr, (idx, (size, _))| {
+                let name = port.signal(&format!("addr{}", idx));
+                let val = inputs
+                    .iter()
+                    .find(|(id, _)| *id == name)
+                    .map(|(_, v)| v.as_u64())
+                    .unwrap_or_default();
+                addr * size + val
+            })
+    }
+}
+
+/// Returns true if the one bit input `name` is high.
+fn is_high(inputs: &[(ir::Id, &Value)], name: &ir::Id) -> bool {
+    inputs.iter().any(|(id, v)| id == name && v.as_u64() == 1)
+}
+
+/// A done signal that goes high in the next cycle and is currently `high`.
+fn done_pulse(high: bool) -> PulseValue {
+    let current = if high {
+        Value::bit_high()
+    } else {
+        Value::bit_low()
+    };
+    PulseValue::new(current, Value::bit_high(), Value::bit_low(), 1)
+}
+
+impl Primitive for SeqMem {
+    fn is_comb(&self) -> bool {
+        false
+    }
+
+    fn validate(&self, inputs: &[(ir::Id, &Value)]) {
+        for port in &self.ports {
+            for (id, v) in inputs {
+                if *id == port.signal("write_data") {
+                    assert_eq!(v.len() as u64, self.width)
+                } else if *id == port.signal("write_en")
+                    || *id == port.signal("read_en")
+                {
+                    assert_eq!(v.len(), 1)
+                }
+                for (idx, (size, idx_size)) in self.dims.iter().enumerate() {
+                    if *id == port.signal(&format!("addr{}", idx)) {
+                        assert!(v.as_u64() < *size);
+                        assert_eq!(v.len() as u64, *idx_size)
+                    }
+                }
+            }
+        }
+    }
+
+    fn execute(
+        &mut self,
+        inputs: &[(ir::Id, &Value)],
+        _done_val: Option<&Value>,
+    ) -> Vec<(ir::Id, crate::values::OutputValue)> {
+        let mut out = vec![];
+        for idx in 0..self.ports.len() {
+            let port = &self.ports[idx];
+            let addr = self.calc_addr(port, inputs);
+            let old = self.data[addr as usize].clone();
+            let read_data = port.signal("read_data");
+            let write_en = is_high(inputs, &port.signal("write_en"));
+            let read_en = is_high(inputs, &port.signal("read_en"));
+            let write_data = port.signal("write_data");
+            let write_update = if write_en {
+                out.push((
+                    port.signal("write_done"),
+                    done_pulse(port.write_done).into(),
+                ));
+                inputs
+                    .iter()
+                    .find(|(id, _)| *id == write_data)
+                    .map(|(_, v)| (addr, (*v).clone()))
+            } else {
+                None
+            };
+            let read_update = if read_en {
+                out.push((
+                    read_data,
+                    TimeLockedValue::new(
+                        old.clone(),
+                        1,
+                        Some(port.read_data.clone()),
+                    )
+                    .into(),
+                ));
+                out.push((
+                    port.signal("read_done"),
+                    done_pulse(port.read_done).into(),
+                ));
+                Some(old)
+            } else {
+                out.push((read_data, port.read_data.clone().into()));
+                None
+            };
+            let port = &mut self.ports[idx];
+            port.write_update = write_update;
+            port.read_update = read_update;
+        }
+        out
+    }
+
+    fn reset(
+        &mut self,
+        _: &[(ir::Id, &Value)],
+    ) -> Vec<(ir::Id, crate::values::OutputValue)> {
+        self.ports
+            .iter_mut()
+            .flat_map(|port| {
+                port.write_done = false;
+                port.read_done = false;
+                vec![
+                    (port.signal("read_data"), port.read_data.clone().into()),
+                    (port.signal("write_done"), Value::zeroes(1).into()),
+                    (port.signal("read_done"), Value::zeroes(1).into()),
+                ]
+            })
+            .collect()
+    }
+
+    fn commit_updates(&mut self) {
+        for port in &mut self.ports {
+            if let Some((idx, val)) = port.write_update.take() {
+                self.data[idx as usize] = val;
+                port.write_done = true;
+            }
+            if let Some(val) = port.read_update.take() {
+                port.read_data = val;
+                port.read_done = true;
+            }
+        }
+    }
+
+    fn clear_update_buffer(&mut self) {
+        for port in &mut self.ports {
+            port.write_update = None;
+            port.read_update = None;
+        }
+    }
+
+    fn serialize(&self) -> Serializeable {
+        let data = self.data.iter().map(Value::as_u64).collect();
+        let shape = match self.dims[..] {
+            [(d0, _), (d1, _)] => (d0 as usize, d1 as usize).into(),
+            _ => (self.data.len(),).into(),
+        };
+        Serializeable::Array(data, shape)
+    }
+
+    fn has_serializeable_state(&self) -> bool {
+        true
+    }
+}
@@ -90,6 +90,39 @@ fn test_mem_d1_panic_input() {
         mem_d1.validate_and_execute(&binds, (&Value::bit_low()).into());
 }
 #[test]
+fn test_seq_mem_d1_read() {
+    let mut mem = stfl::SeqMem::new_d1(crate::utils::construct_bindings(
+        [("WIDTH", 32), ("SIZE", 4), ("IDX_SIZE", 2)].iter(),
+    ));
+    mem.initialize_memory(&[
+        Value::from(1, 32).unwrap(),
+        Value::from(2, 32).unwrap(),
+        Value::from(3, 32).unwrap(),
+        Value::from(4, 32).unwrap(),
+    ]);
+    port_bindings![binds;
+        write_data -> (5, 32),
+        write_en -> (1, 1),
+        read_en -> (1, 1),
+        addr0 -> (2, 2)
+    ];
+    let mut mem_out = mem.validate_and_execute(&binds, None).into_iter();
+    let (_, write_done) = mem_out.next().unwrap();
+    assert!(matches!(write_done, OutputValue::PulseValue(_)));
+    // The read returns the value stored before the write after one cycle.
+    match mem_out.next().unwrap() {
+        (_, OutputValue::LockedValue(mut rd)) => {
+            assert_eq!(rd.get_count(), 1);
+            rd.dec_count();
+            assert!(rd.unlockable());
+            assert_eq!(rd.unlock().as_u64(), 3);
+        }
+        _ => panic!("seq_mem_d1 did not return a lockedval"),
+    }
+    mem.commit_updates();
+    assert_eq!(mem.data[2].as_u64(), 5);
+}
+#[test]
 fn test_mem_d2_tlv() {
     let mut mem_d2 = stfl::StdMemD2::from_constants(32, 8, 8, 3, 3);
     port_bindings![binds;
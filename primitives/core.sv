@@ -354,4 +354,132 @@ module std_mem_d4 #(
   end
 endmodule
 
+/// Memories with a registered read port
+module seq_mem_d1 #(
+    parameter WIDTH = 32,
+    parameter SIZE = 16,
+    parameter IDX_SIZE = 4
+) (
+   input wire                logic [IDX_SIZE-1:0] addr0,
+   input wire                logic [ WIDTH-1:0] write_data,
+   input wire                logic write_en,
+   input wire                logic read_en,
+   input wire                logic clk,
+   input wire                logic reset,
+   output logic [ WIDTH-1:0] read_data,
+   output logic              write_done,
+   output logic              read_done
+);
+
+  logic [WIDTH-1:0] mem[SIZE-1:0];
+
+  /* verilator lint_off WIDTH */
+  always_ff @(posedge clk) begin
+    if (write_en) mem[addr0] <= write_data;
+  end
+
+  always_ff @(posedge clk) begin
+    if (reset) begin
+      read_data <= '0;
+      write_done <= 1'd0;
+      read_done <= 1'd0;
+    end else begin
+      if (read_en) read_data <= mem[addr0];
+      write_done <= write_en;
+      read_done <= read_en;
+    end
+  end
+endmodule
+
+module seq_mem_d2 #(
+    parameter WIDTH = 32,
+    parameter D0_SIZE = 16,
+    parameter D1_SIZE = 16,
+    parameter D0_IDX_SIZE = 4,
+    parameter D1_IDX_SIZE = 4
+) (
+   input wire                logic [D0_IDX_SIZE-1:0] addr0,
+   input wire                logic [D1_IDX_SIZE-1:0] addr1,
+   input wire                logic [ WIDTH-1:0] write_data,
+   input wire                logic write_en,
+   input wire                logic read_en,
+   input wire                logic clk,
+   input wire                logic reset,
+   output logic [ WIDTH-1:0] read_data,
+   output logic              write_done,
+   output logic              read_done
+);
+
+  /* verilator lint_off WIDTH */
+  logic [WIDTH-1:0] mem[D0_SIZE-1:0][D1_SIZE-1:0];
+
+  always_ff @(posedge clk) begin
+    if (write_en) mem[addr0][addr1] <= write_data;
+  end
+
+  always_ff @(posedge clk) begin
+    if (reset) begin
+      read_data <= '0;
+      write_done <= 1'd0;
+      read_done <= 1'd0;
+    end else begin
+      if (read_en) read_data <= mem[addr0][addr1];
+      write_done <= write_en;
+      read_done <= read_en;
+    end
+  end
+endmodule
+
+/// True dual-port memory. When both ports write to the same address in the
+/// same cycle, the write from port b takes effect.
+module dp_mem_d1 #(
+    parameter WIDTH = 32,
+    parameter SIZE = 16,
+    parameter IDX_SIZE = 4
+) (
+   input wire                logic [IDX_SIZE-1:0] addr0_a,
+   input wire                logic [ WIDTH-1:0] write_data_a,
+   input wire                logic write_en_a,
+   input wire                logic read_en_a,
+   input wire                logic [IDX_SIZE-1:0] addr0_b,
+   input wire                logic [ WIDTH-1:0] write_data_b,
+   input wire                logic write_en_b,
+   input wire                logic read_en_b,
+   input wire                logic clk,
+   input wire                logic reset,
+   output logic [ WIDTH-1:0] read_data_a,
+   output logic              write_done_a,
+   output logic              read_done_a,
+   output logic [ WIDTH-1:0] read_data_b,
+   output logic              write_done_b,
+   output logic              read_done_b
+);
+
+  logic [WIDTH-1:0] mem[SIZE-1:0];
+
+  /* verilator lint_off WIDTH */
+  always_ff @(posedge clk) begin
+    if (write_en_a) mem[addr0_a] <= write_data_a;
+    if (write_en_b) mem[addr0_b] <= write_data_b;
+  end
+
+  always_ff @(posedge clk) begin
+    if (reset) begin
+      read_data_a <= '0;
+      read_data_b <= '0;
+      write_done_a <= 1'd0;
+      read_done_a <= 1'd0;
+      write_done_b <= 1'd0;
+      read_done_b <= 1'd0;
+    end else begin
+      if (read_en_a) read_data_a <= mem[addr0_a];
+      if (read_en_b) read_data_b <= mem[addr0_b];
+      write_done_a <= write_en_a;
+      read_done_a <= read_en_a;
+      write_done_b <= write_en_b;
+      read_done_b <= read_en_b;
+    end
+  end
+endmodule
+
 `default_nettype wire
@@ -1,10 +1,24 @@
 use calyx::backend::traits::Backend;
 use calyx::backend::{
-    verilog::VerilogBackend, xilinx::XilinxInterfaceBackend,
-    xilinx::XilinxXmlBackend,
+    firrtl::FirrtlBackend,
+    resources::{ResourcesBackend, ResourcesJsonBackend},
+    source_map::SourceMapBackend,
+    testbench::TestbenchBackend,
+    verilog::{VerilogBackend, VerilogSplitBackend},
+    xilinx::{
+        AxiWrapperBackend, AxiWrapperHeaderBackend, AxiWrapperJsonBackend,
+        XilinxInterfaceBackend, XilinxXmlBackend,
+    },
+};
+use calyx::{
+    errors::{Error, FutilResult},
+    frontend::{self, formatter::Formatter},
+    ir,
+    passes::BankedMemory,
+    utils::OutputFile,
 };
-use calyx::{errors::FutilResult, ir, utils::OutputFile};
 use itertools::Itertools;
+use std::io::Read;
 use std::path::PathBuf;
 use std::str::FromStr;
 pub use structopt::StructOpt;
@@ -29,9 +43,11 @@ pub struct Opts {
     #[structopt(long = "force-color")]
     pub color: bool,
 
-    /// Path to the primitives library
-    #[structopt(long, short, default_value = ".")]
-    pub lib_path: PathBuf,
+    /// Paths searched for imported files. Can be given multiple times.
+    /// Additional paths can be provided with the `CALYX_LIB_PATH`
+    /// environment variable
+    #[structopt(long, short, default_value = ".", number_of_values = 1)]
+    pub lib_path: Vec<PathBuf>,
 
     /// Enable debug mode output
     #[structopt(long = "debug")]
@@ -41,6 +57,15 @@ pub struct Opts {
     #[structopt(long = "synthesis")]
     pub enable_synthesis: bool,
 
+    /// Do not emit `initial` and `final` blocks in the generated Verilog.
+    #[structopt(long = "disable-init")]
+    pub disable_init: bool,
+
+    /// Annotate the generated Verilog with `// calyx:` comments that show the
+    /// Calyx assignments implemented by each `assign`.
+    #[structopt(long = "source-comments")]
+    pub source_comments: bool,
+
     /// Select a backend.
     #[structopt(short = "b", long = "backend", default_value)]
     pub backend: BackendOpt,
@@ -57,9 +82,55 @@ pub struct Opts {
     #[structopt(short = "d", long = "disable-pass")]
     pub disable_pass: Vec<String>,
 
+    /// Provide an option to a pass using `<pass>:<option>[=<value>]`.
+    /// Can be given multiple times.
+    #[structopt(short = "x", long = "extra-opt", number_of_values = 1)]
+    pub extra_opts: Vec<String>,
+
     /// list all avaliable pass options
     #[structopt(long = "list-passes")]
     pub list_passes: bool,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands that do not run the compiler.
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Format a futil program while preserving its comments
+    Fmt {
+        /// Input futil program
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+
+        /// Report an error if the program is not formatted instead of
+        /// printing it
+        #[structopt(long)]
+        check: bool,
+    },
+
+    /// Split the data of memories with the `bank_cyclic` or `bank_block`
+    /// attributes between their banks
+    BankData {
+        /// Input futil program
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+
+        /// JSON data file used by the interpreter and fud. The result is
+        /// written to the output file.
+        #[structopt(long, parse(from_os_str))]
+        data: Option<PathBuf>,
+
+        /// Directory with the `.dat` files loaded by `$readmemh`
+        #[structopt(long, parse(from_os_str))]
+        dat: Option<PathBuf>,
+
+        /// Combine the banks of each memory instead. For `--dat`, combines
+        /// the `.out` files written by `$writememh`
+        #[structopt(long)]
+        merge: bool,
+    },
 }
 
 // ================== Backend Variant and Parsing ===================== //
@@ -68,8 +139,17 @@ pub struct Opts {
 #[derive(Debug, Copy, Clone)]
 pub enum BackendOpt {
     Verilog,
+    VerilogSplit,
+    SourceMap,
+    Testbench,
+    Firrtl,
     Xilinx,
     XilinxXml,
+    AxiWrapper,
+    AxiWrapperHeader,
+    AxiWrapperJson,
+    Resources,
+    ResourcesJson,
     Futil,
     // Dot,
     None,
@@ -78,8 +158,17 @@ pub enum BackendOpt {
 fn backends() -> Vec<(&'static str, BackendOpt)> {
     vec![
         ("verilog", BackendOpt::Verilog),
+        ("verilog-split", BackendOpt::VerilogSplit),
+        ("source-map", BackendOpt::SourceMap),
+        ("testbench", BackendOpt::Testbench),
+        ("firrtl", BackendOpt::Firrtl),
         ("xilinx", BackendOpt::Xilinx),
         ("xilinx-xml", BackendOpt::XilinxXml),
+        ("axi-wrapper", BackendOpt::AxiWrapper),
+        ("axi-wrapper-header", BackendOpt::AxiWrapperHeader),
+        ("axi-wrapper-json", BackendOpt::AxiWrapperJson),
+        ("resources", BackendOpt::Resources),
+        ("resources-json", BackendOpt::ResourcesJson),
         ("futil", BackendOpt::Futil),
         ("none", BackendOpt::None),
     ]
@@ -123,8 +212,17 @@ impl ToString for BackendOpt {
     fn to_string(&self) -> String {
         match self {
             Self::Verilog => "verilog",
+            Self::VerilogSplit => "verilog-split",
+            Self::SourceMap => "source-map",
+            Self::Testbench => "testbench",
+            Self::Firrtl => "firrtl",
             Self::Xilinx => "xilinx",
             Self::XilinxXml => "xilinx-xml",
+            Self::AxiWrapper => "axi-wrapper",
+            Self::AxiWrapperHeader => "axi-wrapper-header",
+            Self::AxiWrapperJson => "axi-wrapper-json",
+            Self::Resources => "resources",
+            Self::ResourcesJson => "resources-json",
             Self::Futil => "futil",
             // Self::Dot => "dot",
             Self::None => "none",
@@ -134,6 +232,94 @@ impl ToString for BackendOpt {
 }
 
 impl Opts {
+    /// Run the subcommand `command`.
+    pub fn run_command(&self, command: &Command) -> FutilResult<()> {
+        match command {
+            Command::Fmt { file, check } => {
+                let source = match file {
+                    Some(path) => std::fs::read_to_string(path)?,
+                    None => {
+                        let mut buf = String::new();
+                        std::io::stdin().read_to_string(&mut buf)?;
+                        buf
+                    }
+                };
+                let formatted = Formatter::format(&source)?;
+                if !*check {
+                    write!(self.output.get_write(), "{}", formatted)?;
+                } else if formatted != source {
+                    let name = file.as_ref().map_or_else(
+                        || "<stdin>".to_string(),
+                        |path| path.to_string_lossy().to_string(),
+                    );
+                    return Err(Error::Misc(format!(
+                        "{} is not formatted",
+                        name
+                    )));
+                }
+                Ok(())
+            }
+            Command::BankData {
+                file,
+                data,
+                dat,
+                merge,
+            } => {
+                let namespace =
+                    frontend::NamespaceDef::new(file, &self.lib_path)?;
+                let ctx = ir::from_ast::ast_to_ir(namespace, false, false)?;
+                let comp = ctx
+                    .components
+                    .iter()
+                    .find(|comp| comp.name == self.toplevel)
+                    .ok_or_else(|| {
+                        Error::Undefined(
+                            ir::Id::from(self.toplevel.as_str()),
+                            "component".to_string(),
+                        )
+                    })?;
+                let mems = comp
+                    .cells
+                    .iter()
+                    .filter_map(|cell| {
+                        BankedMemory::from_cell(&cell.borrow()).transpose()
+                    })
+                    .collect::<FutilResult<Vec<_>>>()?;
+
+                if let Some(path) = data {
+                    let contents = std::fs::read_to_string(path)?;
+                    let mut json: serde_json::Map<String, serde_json::Value> =
+                        serde_json::from_str(&contents).map_err(|err| {
+                            Error::InvalidFile(format!(
+                                "{}: {}",
+                                path.to_string_lossy(),
+                                err
+                            ))
+                        })?;
+                    for mem in &mems {
+                        if *merge {
+                            mem.merge_json(&mut json)?
+                        } else {
+                            mem.split_json(&mut json)?
+                        }
+                    }
+                    let json = serde_json::to_string_pretty(&json).unwrap();
+                    writeln!(self.output.get_write(), "{}", json)?;
+                }
+                if let Some(dir) = dat {
+                    for mem in &mems {
+                        if *merge {
+                            mem.merge_dat(dir, "out")?
+                        } else {
+                            mem.split_dat(dir, "dat")?
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
     /// Given a context, calls the backend corresponding to the `BackendOpt` variant
     pub fn run_backend(self, context: &ir::Context) -> FutilResult<()> {
         match self.backend {
@@ -141,6 +327,22 @@ impl Opts {
                 let backend = VerilogBackend::default();
                 backend.run(&context, self.output)
             }
+            BackendOpt::VerilogSplit => {
+                let backend = VerilogSplitBackend::default();
+                backend.run(&context, self.output)
+            }
+            BackendOpt::SourceMap => {
+                let backend = SourceMapBackend::default();
+                backend.run(&context, self.output)
+            }
+            BackendOpt::Testbench => {
+                let backend = TestbenchBackend::default();
+                backend.run(&context, self.output)
+            }
+            BackendOpt::Firrtl => {
+                let backend = FirrtlBackend::default();
+                backend.run(&context, self.output)
+            }
             BackendOpt::Xilinx => {
                 let backend = XilinxInterfaceBackend::default();
                 backend.run(&context, self.output)
@@ -149,6 +351,26 @@ impl Opts {
                 let backend = XilinxXmlBackend::default();
                 backend.run(&context, self.output)
             }
+            BackendOpt::AxiWrapper => {
+                let backend = AxiWrapperBackend::default();
+                backend.run(&context, self.output)
+            }
+            BackendOpt::AxiWrapperHeader => {
+                let backend = AxiWrapperHeaderBackend::default();
+                backend.run(&context, self.output)
+            }
+            BackendOpt::AxiWrapperJson => {
+                let backend = AxiWrapperJsonBackend::default();
+                backend.run(&context, self.output)
+            }
+            BackendOpt::Resources => {
+                let backend = ResourcesBackend::default();
+                backend.run(&context, self.output)
+            }
+            BackendOpt::ResourcesJson => {
+                let backend = ResourcesJsonBackend::default();
+                backend.run(&context, self.output)
+            }
             BackendOpt::Futil => {
                 for import_path in &context.imports {
                     writeln!(
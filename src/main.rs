@@ -16,6 +16,11 @@ fn main() -> FutilResult<()> {
         return Ok(());
     }
 
+    // Subcommands do not run the compiler.
+    if let Some(command) = &opts.command {
+        return opts.run_command(command);
+    }
+
     // Construct the namespace.
     let namespace = frontend::NamespaceDef::new(&opts.file, &opts.lib_path)?;
 
@@ -25,6 +30,9 @@ fn main() -> FutilResult<()> {
         opts.enable_debug,
         opts.enable_synthesis,
     )?;
+    rep.disable_init = opts.disable_init;
+    rep.source_comments = opts.source_comments;
+    rep.extra_opts = opts.extra_opts.clone();
 
     // Run all passes specified by the command line
     pm.execute_plan(&mut rep, &opts.pass, &opts.disable_pass)?;